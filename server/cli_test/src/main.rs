@@ -1,13 +1,37 @@
 use game::{
     move_validation,
-    execute_move,
+    execute_move, captures_king,
     GameBoard,
     Rankfile,
     //MoveData,
+    MoveKind,
     UltimaPiece,
     UltimaPieceType,
-    PlayerColor
+    PlayerColor,
+    Tablebase,
+    TablebaseResult,
+    GameRecord,
+    AnalysisSettings,
+    EvaluationBackend,
+    annotate_game,
+    spawn_analysis,
+    format_score,
+    move_to_string,
+    square_to_string,
+    describe_captures,
+    EnginePlayer,
+    Player,
+    OpeningBook,
+    SkillLevel,
+    MAX_SKILL_LEVEL,
+    RuleSet,
+    StartPosition,
+    BoardBuilder,
+    Handicap,
+    Rng,
+    rococo::{self, ROCOCO_GEOMETRY}
 };
+use std::sync::Arc;
 
 fn board_to_string(board: &GameBoard) -> String {
 
@@ -27,7 +51,8 @@ fn board_to_string(board: &GameBoard) -> String {
             UltimaPieceType::King => {out += "K"},
             UltimaPieceType::Longleaper => {out += "L"},
             UltimaPieceType::Pawn => {out += "P"},
-            UltimaPieceType::Withdrawer => {out += "W"}
+            UltimaPieceType::Withdrawer => {out += "W"},
+            UltimaPieceType::Custom(letter) => {out.push(letter.to_char())}
         }
         out + " "
     }
@@ -46,15 +71,20 @@ fn board_to_string(board: &GameBoard) -> String {
 
     let mut out = String::with_capacity(64 * 3 + 4 * 8 + 4 * 8 + 4 * 4);
 
-    for r in (0..8).rev() {
-        out = out + format!("[{}] ", r + 1).as_str();
-        for f in 0..8 {
-            out = out + square_to_char(board.get_square_from_coords(r, f)).as_str();
+    let geometry = board.geometry();
+    let width = geometry.ranks().to_string().len();
+    for r in (0..geometry.ranks()).rev() {
+        out += format!("[{:>width$}] ", r + 1).as_str();
+        for f in 0..geometry.files() {
+            out += square_to_char(board.get_square_from_coords(r, f)).as_str();
         }
-        out = out + "\n\n";
+        out += "\n\n";
     }
-    out = out + "   " + "[A]" + "[B]" + "[C]" + "[D]" + "[E]" + "[F]" + "[G]" + "[H]" + "\n\n";
-    out
+    out = out + "  " + &" ".repeat(width);
+    for f in 0..geometry.files() {
+        out += format!("[{}]", char::from(b'A' + f as u8)).as_str();
+    }
+    out + "\n\n"
 }
 
 fn scan_string() -> String {
@@ -70,9 +100,228 @@ fn flip_color(color: PlayerColor) -> PlayerColor {
     }
 }
 
+//Usage: cli_test [--tablebases <dir>] [--book <file>] [--annotate <game record>] [--analysis]
+//                [--engine <white|black>] [--skill-level <0-20> | --elo <elo>]
+//                [--rules <abbott|default>[,[no-]<option>...]] [--suicide] [--diagonal-pawns] [--king-coordinates]
+//                [--chameleon-withdraws] [--no-chameleon-captures-king]
+//                [--setup <rotational|abbott|shuffle-<seed>|shuffle>]
+//                [--odds <white|black> <withdrawer|immobilizer|coordinator|longleaper|chameleon|withdrawer-and-immobilizer>]
+//                [--variant <ultima|rococo>]
+struct Args {
+    tablebases: Option<String>,
+    book: Option<String>,
+    annotate: Option<String>,
+    analysis: bool,
+    engine: Option<PlayerColor>,
+    skill_level: SkillLevel,
+    rules: RuleSet,
+    setup: StartPosition,
+    //The side giving odds, and what it gives.
+    odds: Option<(PlayerColor, Handicap)>,
+    rococo: bool
+}
+
+fn parse_args() -> Args {
+    let mut out = Args {
+        tablebases: None,
+        book: None,
+        annotate: None,
+        analysis: false,
+        engine: None,
+        skill_level: SkillLevel::strongest(),
+        rules: RuleSet::default(),
+        setup: StartPosition::default(),
+        odds: None,
+        rococo: false
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tablebases" => out.tablebases = args.next(),
+            "--book" => out.book = args.next(),
+            "--annotate" => out.annotate = args.next(),
+            "--analysis" => out.analysis = true,
+            "--engine" => out.engine = match args.next().as_deref() {
+                Some("white") => Some(PlayerColor::White),
+                Some("black") => Some(PlayerColor::Black),
+                _ => {println!("--engine takes white or black."); None}
+            },
+            "--skill-level" => match args.next().and_then(|level| level.parse().ok()) {
+                Some(level) => out.skill_level = SkillLevel::new(level),
+                None => println!("--skill-level takes a number from 0 to {MAX_SKILL_LEVEL}.")
+            },
+            "--elo" => match args.next().and_then(|elo| elo.parse().ok()) {
+                Some(elo) => out.skill_level = SkillLevel::from_elo(elo),
+                None => println!("--elo takes a number.")
+            },
+            "--rules" => match args.next().as_deref().and_then(RuleSet::from_id) {
+                Some(rules) => out.rules = rules,
+                None => println!("--rules takes abbott or default, then any options, e.g. default,chameleon-withdraws.")
+            },
+            "--suicide" => out.rules.immobilized_suicide = true,
+            "--diagonal-pawns" => out.rules.pawns_capture_diagonally = true,
+            "--king-coordinates" => out.rules.king_coordinates = true,
+            "--chameleon-withdraws" => out.rules.chameleon_withdraws_from_immobilizer = true,
+            "--no-chameleon-captures-king" => out.rules.chameleon_captures_king = false,
+            "--setup" => match args.next().as_deref() {
+                Some("shuffle") => out.setup = StartPosition::Shuffled(Rng::from_entropy().next_u64()),
+                id => match id.and_then(StartPosition::from_id) {
+                    Some(setup) => out.setup = setup,
+                    None => println!("--setup takes rotational, abbott, shuffle or shuffle-<seed>.")
+                }
+            },
+            "--variant" => match args.next().as_deref() {
+                Some("ultima") => out.rococo = false,
+                Some("rococo") => out.rococo = true,
+                _ => println!("--variant takes ultima or rococo.")
+            },
+            "--odds" => {
+                let color = match args.next().as_deref() {
+                    Some("white") => Some(PlayerColor::White),
+                    Some("black") => Some(PlayerColor::Black),
+                    _ => None
+                };
+                match (color, args.next().as_deref().and_then(Handicap::from_name)) {
+                    (Some(color), Some(handicap)) => out.odds = Some((color, handicap)),
+                    _ => println!("--odds takes white or black, then the piece to give up.")
+                }
+            },
+            _ => println!("Ignoring unknown argument {arg}.")
+        }
+    }
+    out
+}
+
+fn load_tablebases(dir: &str, rules: RuleSet) -> Option<Tablebase> {
+    match Tablebase::load_dir(dir, rules) {
+        Ok(tablebase) => Some(tablebase),
+        Err(e) => {println!("Couldn't load tablebases from {dir}: {e}"); None}
+    }
+}
+
+//Runs the engine over a finished game and prints it back with its mistakes commented.
+fn annotate(path: &str) {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {println!("Couldn't read {path}: {e}"); return;}
+    };
+    let record = match GameRecord::from_text(&text) {
+        Ok(record) => record,
+        Err(e) => {println!("Couldn't parse {path}: {e}"); return;}
+    };
+    let annotated = annotate_game(&record, &AnalysisSettings::default(), &EvaluationBackend::HandWritten);
+    println!("{}", annotated.to_text());
+}
+
+//Prints the engine's top lines as it deepens.
+fn show_analysis(board: &GameBoard, whose_turn: PlayerColor, rules: &RuleSet, backend: &Arc<EvaluationBackend>) {
+    const DEPTH: u32 = 4;
+    const LINES: usize = 3;
+    let handle = spawn_analysis(board.clone(), whose_turn, DEPTH, LINES, *rules, backend.clone());
+    for progress in handle.updates.iter() {
+        println!("Engine, depth {} ({} nodes):", progress.depth, progress.nodes);
+        for line in &progress.lines {
+            let mut position = board.clone();
+            let mut mover = whose_turn;
+            let mut moves = vec![];
+            for mv in &line.moves {
+                moves.push(move_to_string(&position, mv));
+                execute_move(&mut position, mv.clone(), mover);
+                mover = flip_color(mover);
+            }
+            println!("    {}: {}", format_score(line.score), moves.join(" "));
+        }
+    }
+    handle.join();
+}
+
+//Lists the mover's pieces that the opponent is attacking, and the ones that are frozen.
+fn show_threats(board: &GameBoard, whose_turn: PlayerColor, rules: &RuleSet) {
+    let threats = board.threats_against(whose_turn, rules);
+    if !threats.threats.is_empty() {
+        let squares: Vec<String> = threats.threatened_squares().map(square_to_string).collect();
+        println!("Under attack: {}", squares.join(", "));
+    }
+    for immobilization in board.immobilized_pieces(whose_turn, rules) {
+        let by: Vec<String> = immobilization.immobilized_by.iter().map(|by| square_to_string(by.square)).collect();
+        let withdraw = if immobilization.can_withdraw {", can still withdraw"} else {""};
+        println!("Immobilized: {} (by {}{})", square_to_string(immobilization.square), by.join(", "), withdraw);
+    }
+}
+
+//Two players at the same keyboard; there's no Rococo engine yet.
+fn play_rococo() {
+    let mut board = rococo::new_in_start_position();
+    let mut whose_turn = PlayerColor::White;
+    loop {
+        for rank in (0..ROCOCO_GEOMETRY.ranks()).rev() {
+            let mut line = format!("[{:>2}] ", rank + 1);
+            for file in 0..ROCOCO_GEOMETRY.files() {
+                let square = ROCOCO_GEOMETRY.rankfile(rank, file).expect("on the board");
+                line += &match board.get_square(square) {
+                    Some(p) if p.color == PlayerColor::White => format!("w{} ", p.piece_type.to_char()),
+                    Some(p) => format!("b{} ", p.piece_type.to_char()),
+                    None if rococo::is_edge(square) => String::from(".. "),
+                    None => String::from("__ ")
+                };
+            }
+            println!("{line}\n");
+        }
+        println!("     [A][B][C][D][E][F][G][H][I][J]\n");
+        let bw = if whose_turn == PlayerColor::White {"white"} else {"black"};
+        println!("Your move, {bw}? E.g. f3-f4, i2<>i3 to swap, or i2*j3 for a swapper's mutual destruction.");
+        let Some(mv) = rococo::parse_move(&board, whose_turn, &scan_string()) else {
+            println!("That's not a legal move!");
+            continue;
+        };
+        let wins = captures_king(&board, &mv, whose_turn);
+        execute_move(&mut board, mv, whose_turn);
+        if wins {
+            println!("{bw} takes the king and wins.");
+            return;
+        }
+        whose_turn = flip_color(whose_turn);
+    }
+}
+
 fn main() {
 
-    let mut board = GameBoard::new_in_start_position();
+    let args = parse_args();
+    if let Some(path) = &args.annotate {
+        annotate(path);
+        return;
+    }
+    if args.rococo {
+        play_rococo();
+        return;
+    }
+
+    //So that a shuffled game can be set up again.
+    println!("Starting from the {} setup.", args.setup);
+    let mut builder = BoardBuilder::from_setup(args.setup);
+    if let Some((color, handicap)) = args.odds {
+        builder = builder.give_odds(color, handicap);
+    }
+    let mut board = match builder.build() {
+        Ok(board) => board,
+        Err(e) => {println!("Couldn't set up the board: {e}."); return;}
+    };
+    let tablebase = args.tablebases.as_deref().and_then(|dir| load_tablebases(dir, args.rules));
+    let backend = Arc::new(EvaluationBackend::HandWritten);
+    let book = args.book.as_deref().and_then(|path| match OpeningBook::load(path) {
+        Ok(book) => Some(book),
+        Err(e) => {println!("Couldn't load the opening book from {path}: {e}"); None}
+    });
+    let mut engine = EnginePlayer::new(args.skill_level, &backend, Rng::from_entropy().next_u64());
+    engine.set_book(book.as_ref());
+    engine.set_tablebase(tablebase.as_ref());
+    if args.engine.is_some() {
+        println!(
+            "The engine is playing at skill level {} (about {} elo).", 
+            args.skill_level.level(), 
+            args.skill_level.approximate_elo()
+        );
+    }
 
     let mut whose_turn = PlayerColor::White;
     loop {
@@ -82,6 +331,27 @@ fn main() {
             PlayerColor::White => "white",
         };
         println!("{bw}'s turn.");
+        if let Some(result) = tablebase.as_ref().and_then(|tb| tb.probe(&board, whose_turn)) {
+            match result {
+                TablebaseResult::Win {plies_to_mate} => println!("Tablebase: {bw} wins in {plies_to_mate} plies."),
+                TablebaseResult::Loss {plies_to_mate} => println!("Tablebase: {bw} loses in {plies_to_mate} plies."),
+                TablebaseResult::Draw => println!("Tablebase: draw.")
+            }
+        }
+        show_threats(&board, whose_turn, &args.rules);
+        if args.analysis {
+            show_analysis(&board, whose_turn, &args.rules, &backend);
+        }
+        if args.engine == Some(whose_turn) {
+            let Some(mv) = engine.choose_move(&board, whose_turn, &args.rules) else {
+                println!("The engine has no moves left.");
+                break;
+            };
+            println!("The engine plays {}.", move_to_string(&board, &mv));
+            execute_move(&mut board, mv, whose_turn);
+            whose_turn = flip_color(whose_turn);
+            continue;
+        }
         let (piece_moved, start) = loop {
             println!("Which piece will you move, {bw}? Enter the piece's rank", );
             let rank_str = scan_string()
@@ -102,19 +372,23 @@ fn main() {
             if piece.color != whose_turn {
                 println!("That's your opponent's piece."); continue;
             }
+            if let Some(immobilization) = board.immobilization(rf, &args.rules) {
+                println!("{}", immobilization.explanation());
+                //It may still have a suicide move, or a withdrawal if it's a chameleon.
+                if move_validation::get_all_legal_moves(&board, rf, piece, &args.rules).is_empty() {continue}
+            }
             break(piece, rf);
         };
         
-        let legal_moves = move_validation::get_all_legal_moves(&board, start, piece_moved);
+        let legal_moves = move_validation::get_all_legal_moves(&board, start, piece_moved, &args.rules);
         println!("Great! Here are your legal moves:");
         for mv in legal_moves.iter() {
             let (r, f) = mv.end.to_strings();
-            print!("    {r}{f}, captures: ");
-            for capture in &mv.captures {
-                let (cr, cf) = capture.to_strings();
-                print!("{cr}{cf},");
+            if mv.kind == MoveKind::Suicide {
+                println!("    {r}{f}, removes itself from the board");
+            } else {
+                println!("    {r}{f}, captures: {}", describe_captures(mv));
             }
-            println!();
         }
 
         println!();
@@ -140,7 +414,7 @@ fn main() {
                 }
             }
             if let Some(final_move) = final_move {
-                break(final_move);
+                break final_move;
             } else {
                 println!("That's not a legal move!");
             }
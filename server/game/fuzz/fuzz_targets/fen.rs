@@ -0,0 +1,14 @@
+#![no_main]
+
+use game::*;
+use game_fuzz::exercise_position;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(fen) = std::str::from_utf8(data) else {return};
+    let Ok((board, whose_turn)) = board_from_fen(fen) else {return};
+    let written = board_to_fen(&board, whose_turn);
+    let (reread, _) = board_from_fen(&written).expect("written fens read back");
+    assert_eq!(board_to_fen(&reread, whose_turn), written);
+    exercise_position(&board, whose_turn);
+});
@@ -0,0 +1,16 @@
+#![no_main]
+
+use game::*;
+use game_fuzz::check_king_caches;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {return};
+    let Ok(record) = GameRecord::from_text(text) else {return};
+    let written = record.to_text();
+    let reread = GameRecord::from_text(&written).expect("written records read back");
+    assert_eq!(reread.to_text(), written);
+    for (board, whose_turn) in record.positions() {
+        check_king_caches(&board, whose_turn);
+    }
+});
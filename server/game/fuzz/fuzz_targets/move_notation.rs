@@ -0,0 +1,18 @@
+#![no_main]
+
+use game::*;
+use game_fuzz::play_checked;
+use libfuzzer_sys::fuzz_target;
+
+//A fen on the first line, or the start position if it doesn't parse, then a move.
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {return};
+    let (fen, notation) = text.split_once('\n').unwrap_or(("", text));
+    let (mut board, whose_turn) = board_from_fen(fen)
+        .unwrap_or_else(|_| (GameBoard::new_in_start_position(), PlayerColor::White));
+    let rules = RuleSet::default();
+    let Some(mv) = parse_move(&board, whose_turn, notation, &rules) else {return};
+    let written = move_to_string(&board, &mv);
+    assert!(parse_move(&board, whose_turn, &written, &rules) == Some(mv.clone()), "{written} doesn't read back");
+    play_checked(&mut board, mv, whose_turn);
+});
@@ -0,0 +1,27 @@
+#![no_main]
+
+use game::*;
+use game_fuzz::play_checked;
+use libfuzzer_sys::fuzz_target;
+
+//The first byte picks the rules and setup, and each byte after that picks a move.
+fuzz_target!(|data: &[u8]| {
+    let Some((&header, choices)) = data.split_first() else {return};
+    let rules = if header & 1 == 0 {RuleSet::abbott()} else {RuleSet::default()};
+    let setup = match header >> 1 {
+        0 => StartPosition::Rotational,
+        1 => StartPosition::Abbott,
+        seed => StartPosition::Shuffled(seed as u64)
+    };
+    let mut board = setup.board();
+    let mut whose_turn = PlayerColor::White;
+    for &choice in choices {
+        let moves = move_validation::get_all_legal_moves_for_color(&board, whose_turn, &rules);
+        if moves.is_empty() || board.get_king_locs(whose_turn).is_empty() {
+            return;
+        }
+        let mv = moves[choice as usize % moves.len()].clone();
+        play_checked(&mut board, mv, whose_turn);
+        whose_turn = whose_turn.opposite();
+    }
+});
@@ -0,0 +1,51 @@
+//Invariants shared by the fuzz targets. Run them with e.g. `cargo +nightly fuzz run move_sequences`
+//from `game/`.
+
+use game::*;
+
+//Every `GameBoard::get_king_locs` entry holds a king of that colour, once, and every king is listed.
+pub fn check_king_caches(board: &GameBoard, whose_turn: PlayerColor) {
+    for problem in validate_position(board, whose_turn, &RuleSet::default()) {
+        match problem.kind {
+            PositionProblemKind::KingNotCached
+            | PositionProblemKind::CachedKingMissing {..}
+            | PositionProblemKind::DuplicateCachedKing {..} => panic!("king cache out of date: {problem}"),
+            _ => {}
+        }
+    }
+}
+
+//How many of each piece each side has, indexed by colour then piece type.
+pub fn piece_counts(board: &GameBoard) -> [[usize; 7]; 2] {
+    let mut counts = [[0; 7]; 2];
+    for rf in board.squares() {
+        if let Some(piece) = board.get_square(rf) {
+            counts[piece.color.as_index()][piece.piece_type.as_index()] += 1;
+        }
+    }
+    counts
+}
+
+//Plays `mv` and checks that nothing appeared and the king caches kept up.
+pub fn play_checked(board: &mut GameBoard, mv: MoveData, whose_turn: PlayerColor) {
+    let before = piece_counts(board);
+    execute_move(board, mv, whose_turn);
+    let after = piece_counts(board);
+    for color in 0..2 {
+        for piece_type in 0..7 {
+            assert!(after[color][piece_type] <= before[color][piece_type], "a move added a piece");
+        }
+    }
+    check_king_caches(board, whose_turn.opposite());
+}
+
+//Generates every move in the position under each rule set and plays each one on a copy.
+pub fn exercise_position(board: &GameBoard, whose_turn: PlayerColor) {
+    check_king_caches(board, whose_turn);
+    for rules in [RuleSet::abbott(), RuleSet::default()] {
+        for mv in move_validation::get_all_legal_moves_for_color(board, whose_turn, &rules) {
+            let mut copy = board.clone();
+            play_checked(&mut copy, mv, whose_turn);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use super::datatypes::moves::MoveData;
+use super::logic::{execute_move, captures_king};
+use super::evaluation::EvaluationBackend;
+use super::search::{Searcher, MATE_SCORE, format_score};
+use super::game_record::GameRecord;
+use super::notation::{move_to_string, describe_captures};
+
+//Post-game analysis: every move is compared against the engine's choice,
+//and flagged by how much worse it scores (from the mover's point of view).
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum MoveQuality {
+    Inaccuracy,
+    Mistake,
+    Blunder
+}
+
+impl MoveQuality {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MoveQuality::Inaccuracy => "Inaccuracy",
+            MoveQuality::Mistake => "Mistake",
+            MoveQuality::Blunder => "Blunder"
+        }
+    }
+}
+
+pub struct AnalysisSettings {
+    pub depth: u32,
+    //Minimum score drops, in centipawns.
+    pub inaccuracy: i32,
+    pub mistake: i32,
+    pub blunder: i32
+}
+
+impl Default for AnalysisSettings {
+    fn default() -> Self {
+        AnalysisSettings {
+            depth: 3,
+            inaccuracy: 50,
+            mistake: 150,
+            blunder: 300
+        }
+    }
+}
+
+impl AnalysisSettings {
+    fn classify(&self, drop: i32) -> Option<MoveQuality> {
+        if drop >= self.blunder {
+            Some(MoveQuality::Blunder)
+        } else if drop >= self.mistake {
+            Some(MoveQuality::Mistake)
+        } else if drop >= self.inaccuracy {
+            Some(MoveQuality::Inaccuracy)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct MoveAnalysis {
+    pub played: MoveData,
+    //Both from the mover's point of view.
+    pub played_score: i32,
+    pub best_score: i32,
+    pub best_move: Option<MoveData>,
+    pub principal_variation: Vec<MoveData>,
+    pub quality: Option<MoveQuality>
+}
+
+//Searches under the record's rules.
+pub fn analyze_game(record: &GameRecord, settings: &AnalysisSettings, backend: &EvaluationBackend) -> Vec<MoveAnalysis> {
+    let mut searcher = Searcher::new(backend);
+    searcher.set_rules(record.rules());
+    let positions = record.positions();
+    let mut out = vec![];
+    for (i, played) in record.moves.iter().enumerate() {
+        let (board, whose_turn) = &positions[i];
+        let best = searcher.search(board, *whose_turn, settings.depth);
+        let played_score = if captures_king(board, played, *whose_turn) {
+            MATE_SCORE - 1
+        } else {
+            let mut child = board.clone();
+            execute_move(&mut child, played.clone(), *whose_turn);
+            -searcher.search(&child, whose_turn.opposite(), settings.depth.saturating_sub(1)).score
+        };
+        let played_best = best.best_move.as_ref()
+            .is_some_and(|mv| mv.start == played.start && mv.end == played.end);
+        //Searching the played move one ply shallower can make it look better than the engine's own choice.
+        let best_score = best.score.max(played_score);
+        let quality = if played_best {None} else {settings.classify(best_score - played_score)};
+        out.push(MoveAnalysis {
+            played: played.clone(),
+            played_score,
+            best_score,
+            best_move: best.best_move,
+            principal_variation: best.principal_variation,
+            quality
+        });
+        if captures_king(board, played, *whose_turn) {
+            break;
+        }
+    }
+    out
+}
+
+//A copy of the record with a comment on every inaccuracy, mistake and blunder,
+//giving the score drop, the better move and the line the engine expected.
+pub fn annotate_game(record: &GameRecord, settings: &AnalysisSettings, backend: &EvaluationBackend) -> GameRecord {
+    let mut annotated = record.clone();
+    annotated.comments.resize(annotated.moves.len(), None);
+    let positions = record.positions();
+    for (i, analysis) in analyze_game(record, settings, backend).iter().enumerate() {
+        let Some(quality) = analysis.quality else {continue};
+        let Some(best_move) = &analysis.best_move else {continue};
+        let (board, whose_turn) = &positions[i];
+        let mut line = vec![];
+        let mut position = board.clone();
+        let mut mover = *whose_turn;
+        for mv in &analysis.principal_variation {
+            line.push(move_to_string(&position, mv));
+            execute_move(&mut position, mv.clone(), mover);
+            mover = mover.opposite();
+        }
+        let mut better = move_to_string(board, best_move);
+        if !best_move.capture_details.is_empty() {
+            better += &format!(", taking the {}", describe_captures(best_move));
+        }
+        let comment = format!(
+            "{} ({} -> {}). Better was {}: {}",
+            quality.name(),
+            format_score(analysis.best_score),
+            format_score(analysis.played_score),
+            better,
+            line.join(" ")
+        );
+        annotated.comments[i] = Some(match record.comments.get(i).and_then(|c| c.as_ref()) {
+            Some(existing) => format!("{existing} {comment}"),
+            None => comment
+        });
+    }
+    annotated.set_tag("Annotator", &format!("ultima engine, depth {}", settings.depth));
+    annotated
+}
@@ -0,0 +1,230 @@
+use std::fmt;
+
+use super::datatypes::{
+        board::{GameBoard,
+            rankfile::{Rankfile, Geometry}
+        },
+        piece::{UltimaPiece, UltimaPieceType, PlayerColor}
+    };
+use super::notation::{parse_square, square_to_string};
+use super::setups::StartPosition;
+
+//Builds positions a piece at a time, for tests, puzzles and odds games:
+//
+//  let board = BoardBuilder::new()
+//      .place("e1", UltimaPiece {piece_type: King, color: White})
+//      .place("e8", UltimaPiece {piece_type: King, color: Black})
+//      .build()?;
+//
+//Unlike `GameBoard::set_square`, placing a king registers it. Mistakes, like a square that
+//doesn't parse, are kept until `build`, which reports the first one.
+
+//Pieces one side can start without. Each removes pieces from that side's back rank.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Handicap {
+    Withdrawer,
+    Immobilizer,
+    Coordinator,
+    Longleaper,
+    Chameleon,
+    WithdrawerAndImmobilizer
+}
+
+impl Handicap {
+    pub const ALL: [Handicap; 6] = [
+        Handicap::Withdrawer,
+        Handicap::Immobilizer,
+        Handicap::Coordinator,
+        Handicap::Longleaper,
+        Handicap::Chameleon,
+        Handicap::WithdrawerAndImmobilizer
+    ];
+
+    pub fn pieces(&self) -> &'static [UltimaPieceType] {
+        match self {
+            Handicap::Withdrawer => &[UltimaPieceType::Withdrawer],
+            Handicap::Immobilizer => &[UltimaPieceType::Immobilizer],
+            Handicap::Coordinator => &[UltimaPieceType::Coordinator],
+            Handicap::Longleaper => &[UltimaPieceType::Longleaper],
+            Handicap::Chameleon => &[UltimaPieceType::Chameleon],
+            Handicap::WithdrawerAndImmobilizer => &[UltimaPieceType::Withdrawer, UltimaPieceType::Immobilizer]
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Handicap::Withdrawer => "withdrawer",
+            Handicap::Immobilizer => "immobilizer",
+            Handicap::Coordinator => "coordinator",
+            Handicap::Longleaper => "longleaper",
+            Handicap::Chameleon => "chameleon",
+            Handicap::WithdrawerAndImmobilizer => "withdrawer-and-immobilizer"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|handicap| handicap.name() == name)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BoardBuildError {
+    BadSquare(String),
+    WrongKingCount {color: PlayerColor, count: usize},
+    //Only with `forbid_pawns_on_back_ranks`.
+    PawnOnBackRank(String),
+    //The side giving odds doesn't have the piece on its back rank.
+    MissingHandicapPiece {color: PlayerColor, piece_type: UltimaPieceType}
+}
+
+impl fmt::Display for BoardBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardBuildError::BadSquare(s) => write!(f, "{s:?} isn't a square"),
+            BoardBuildError::WrongKingCount {color, count} => write!(f, "{} has {count} kings", color_name(*color)),
+            BoardBuildError::PawnOnBackRank(s) => write!(f, "pawn on {s}, which is a back rank"),
+            BoardBuildError::MissingHandicapPiece {color, piece_type} => {
+                write!(f, "{} has no {} on its back rank to give as odds", color_name(*color), piece_type.name())
+            }
+        }
+    }
+}
+
+impl std::error::Error for BoardBuildError {}
+
+fn color_name(color: PlayerColor) -> &'static str {
+    match color {
+        PlayerColor::White => "white",
+        PlayerColor::Black => "black"
+    }
+}
+
+#[derive(Clone)]
+pub struct BoardBuilder {
+    board: GameBoard,
+    //None allows any number, for positions that don't need to be playable.
+    kings_per_side: Option<usize>,
+    pawns_on_back_ranks: bool,
+    error: Option<BoardBuildError>
+}
+
+impl BoardBuilder {
+    //An empty board.
+    pub fn new() -> Self {
+        Self::from_board(GameBoard::new_empty())
+    }
+
+    //An empty board of another shape.
+    pub fn with_geometry(geometry: Geometry) -> Self {
+        Self::from_board(GameBoard::new_empty_with_geometry(geometry))
+    }
+
+    pub fn from_setup(setup: StartPosition) -> Self {
+        Self::from_board(setup.board())
+    }
+
+    pub fn from_board(board: GameBoard) -> Self {
+        BoardBuilder {
+            board,
+            kings_per_side: Some(1),
+            pawns_on_back_ranks: true,
+            error: None
+        }
+    }
+
+    pub fn place(mut self, square: &str, piece: UltimaPiece) -> Self {
+        if let Some(rf) = self.square(square) {
+            self.board.place_piece(rf, piece);
+        }
+        self
+    }
+
+    pub fn remove(mut self, square: &str) -> Self {
+        if let Some(rf) = self.square(square) {
+            self.board.remove_piece(rf);
+        }
+        self
+    }
+
+    //Takes the handicap's pieces off `color`'s back rank. Where there are two, like the longleapers,
+    //the one nearest the h-file goes for white and the a-file for black:
+    //in the usual setup, the one furthest from that side's immobilizer.
+    pub fn give_odds(mut self, color: PlayerColor, handicap: Handicap) -> Self {
+        let geometry = self.board.geometry();
+        let back_rank = match color {
+            PlayerColor::White => 0,
+            PlayerColor::Black => geometry.ranks() - 1
+        };
+        for &piece_type in handicap.pieces() {
+            let mut files: Vec<i8> = (0..geometry.files()).collect();
+            if color == PlayerColor::White {
+                files.reverse();
+            }
+            let found = files.into_iter()
+                .filter_map(|file| Rankfile::from(back_rank, file))
+                .find(|&rf| self.board.get_square(rf) == Some(UltimaPiece {piece_type, color}));
+            match found {
+                Some(rf) => self.board.remove_piece(rf),
+                None => self.fail(BoardBuildError::MissingHandicapPiece {color, piece_type})
+            }
+        }
+        self
+    }
+
+    //How many kings each side must end up with. The default is one.
+    pub fn kings_per_side(mut self, count: Option<usize>) -> Self {
+        self.kings_per_side = count;
+        self
+    }
+
+    //Pawns never promote in Ultima, so they may stand anywhere by default.
+    //Some variants don't let them back onto either back rank.
+    pub fn forbid_pawns_on_back_ranks(mut self) -> Self {
+        self.pawns_on_back_ranks = false;
+        self
+    }
+
+    pub fn build(self) -> Result<GameBoard, BoardBuildError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if let Some(expected) = self.kings_per_side {
+            for color in [PlayerColor::White, PlayerColor::Black] {
+                let count = self.board.get_king_locs(color).len();
+                if count != expected {
+                    return Err(BoardBuildError::WrongKingCount {color, count});
+                }
+            }
+        }
+        if !self.pawns_on_back_ranks {
+            let last_rank = self.board.geometry().ranks() - 1;
+            let pawn_on_back_rank = self.board.squares().find(|&rf| {
+                let (rank, _) = rf.to_signed_coords();
+                (rank == 0 || rank == last_rank) && self.board.get_square(rf).is_some_and(|p| p.piece_type == UltimaPieceType::Pawn)
+            });
+            if let Some(rf) = pawn_on_back_rank {
+                return Err(BoardBuildError::PawnOnBackRank(square_to_string(rf)));
+            }
+        }
+        Ok(self.board)
+    }
+
+    fn square(&mut self, square: &str) -> Option<Rankfile> {
+        let rf = parse_square(square).filter(|&rf| self.board.contains(rf));
+        if rf.is_none() {
+            self.fail(BoardBuildError::BadSquare(square.to_string()));
+        }
+        rf
+    }
+
+    //Only the first mistake is kept.
+    fn fail(&mut self, error: BoardBuildError) {
+        self.error.get_or_insert(error);
+    }
+}
+
+impl Default for BoardBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
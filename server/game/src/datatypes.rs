@@ -1,11 +1,11 @@
 pub mod piece {
-    #[derive(PartialEq, Eq, Copy, Clone)]
+    #[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
     pub struct UltimaPiece {
         pub piece_type: UltimaPieceType,
         pub color: PlayerColor
     }
     
-    #[derive(PartialEq, Eq, Copy, Clone)]
+    #[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
     pub enum UltimaPieceType {
         Pawn,
         Immobilizer,
@@ -13,85 +13,210 @@ pub mod piece {
         Longleaper,
         Chameleon,
         Withdrawer,
-        King
+        King,
+        //A piece from outside Ultima, e.g. one of Rococo's, named by its letter.
+        //What it does is up to the `PieceBehavior` registered for it in the rules' `PieceSet`.
+        Custom(CustomLetter)
     }
 
-    #[derive(PartialEq, Eq, Copy, Clone)]
+    //An uppercase letter that none of Ultima's pieces use. Only `UltimaPieceType::custom` makes one,
+    //so every custom piece type has a slot in a `PieceSet` and its own FEN letter.
+    #[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+    pub struct CustomLetter(u8);
+
+    impl CustomLetter {
+        pub fn to_char(&self) -> char {
+            self.0 as char
+        }
+    }
+
+    impl UltimaPieceType {
+        //Ultima's own pieces.
+        pub const ALL: [UltimaPieceType; 7] = [
+            UltimaPieceType::Pawn, UltimaPieceType::Immobilizer,
+            UltimaPieceType::Coordinator, UltimaPieceType::Longleaper,
+            UltimaPieceType::Chameleon, UltimaPieceType::Withdrawer,
+            UltimaPieceType::King
+        ];
+        //Ultima's pieces, then one custom piece per letter.
+        pub const COUNT: usize = Self::ALL.len() + 26;
+
+        //A custom piece for the letter `c`, unless Ultima already uses it (or it isn't a letter).
+        //Const, so that variants can name their pieces in constants, hence Ultima's letters spelled out.
+        pub const fn custom(c: char) -> Option<Self> {
+            let c = c.to_ascii_uppercase();
+            if !c.is_ascii_uppercase() || matches!(c, 'P' | 'I' | 'O' | 'L' | 'C' | 'W' | 'K') {
+                return None;
+            }
+            Some(UltimaPieceType::Custom(CustomLetter(c as u8)))
+        }
+
+        pub fn is_custom(&self) -> bool {
+            matches!(self, UltimaPieceType::Custom(_))
+        }
+
+        //Below `COUNT`. Ultima's pieces come first, in `ALL` order.
+        pub fn as_index(&self) -> usize {
+            use UltimaPieceType::*;
+            match self {
+                Pawn => 0, Immobilizer => 1,
+                Coordinator => 2, Longleaper => 3,
+                Chameleon => 4, Withdrawer => 5,
+                King => 6,
+                Custom(CustomLetter(c)) => Self::ALL.len() + (c - b'A') as usize
+            }
+        }
+        //Same letters as the cli: K = king, W = withdrawer, I = immobilizer, C = chameleon,
+        //O = coordinator, L = longleaper, P = pawn.
+        pub fn to_char(&self) -> char {
+            use UltimaPieceType::*;
+            match self {
+                Pawn => 'P', Immobilizer => 'I',
+                Coordinator => 'O', Longleaper => 'L',
+                Chameleon => 'C', Withdrawer => 'W',
+                King => 'K',
+                Custom(letter) => letter.to_char()
+            }
+        }
+        //Any other letter is a custom piece.
+        pub fn from_char(c: char) -> Option<Self> {
+            use UltimaPieceType::*;
+            let out = match c.to_ascii_uppercase() {
+                'P' => Pawn, 'I' => Immobilizer,
+                'O' => Coordinator, 'L' => Longleaper,
+                'C' => Chameleon, 'W' => Withdrawer,
+                'K' => King,
+                _ => {return Self::custom(c);}
+            };
+            Some(out)
+        }
+        pub fn name(&self) -> &'static str {
+            use UltimaPieceType::*;
+            match self {
+                Pawn => "pawn", Immobilizer => "immobilizer",
+                Coordinator => "coordinator", Longleaper => "longleaper",
+                Chameleon => "chameleon", Withdrawer => "withdrawer",
+                King => "king",
+                //Its behaviour has the real name.
+                Custom(_) => "house piece"
+            }
+        }
+    }
+
+    #[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
     pub enum PlayerColor {
         Black,
         White
     }
 
+    impl PlayerColor {
+        pub fn as_index(&self) -> usize {
+            match self {
+                PlayerColor::White => 0,
+                PlayerColor::Black => 1
+            }
+        }
+        pub fn opposite(&self) -> Self {
+            match self {
+                PlayerColor::Black => PlayerColor::White,
+                PlayerColor::White => PlayerColor::Black
+            }
+        }
+    }
+
 }
 
 pub mod board {
-    use super::piece::{PlayerColor, UltimaPiece};
+    use super::piece::{PlayerColor, UltimaPiece, UltimaPieceType};
     pub type Square = Option<UltimaPiece>;
     pub mod rankfile {
-        #[derive(PartialEq, Eq, Hash, Clone, Copy)]
-        pub enum Rank {
-            R1,
-            R2,
-            R3,
-            R4,
-            R5,
-            R6,
-            R7,
-            R8
-        } 
+        //Boards may be up to this many ranks and files (see `Geometry`).
+        pub const MAX_BOARD_SIZE: i8 = 12;
+
+        #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+        pub struct Rank(i8);
         impl Rank {
+            pub const R1: Rank = Rank(0);
+            pub const R2: Rank = Rank(1);
+            pub const R3: Rank = Rank(2);
+            pub const R4: Rank = Rank(3);
+            pub const R5: Rank = Rank(4);
+            pub const R6: Rank = Rank(5);
+            pub const R7: Rank = Rank(6);
+            pub const R8: Rank = Rank(7);
             pub fn as_index(&self) -> i8 {
-                match self {
-                    Rank::R1 => 0, Rank::R2 => 1, 
-                    Rank::R3 => 2, Rank::R4 => 3,
-                    Rank::R5 => 4, Rank::R6 => 5, 
-                    Rank::R7 => 6, Rank::R8 => 7
-                }
+                self.0
             }
             pub fn new_if_exists(r: i8) -> Option<Self> {
-                let out = match r {
-                    0 => Rank::R1, 1 => Rank::R2, 
-                    2 => Rank::R3, 3 => Rank::R4, 
-                    4 => Rank::R5, 5 => Rank::R6, 
-                    6 => Rank::R7, 7 => Rank::R8,
-                    _ => {return None;}
-                };
-                Some(out)
+                (0..MAX_BOARD_SIZE).contains(&r).then_some(Rank(r))
             }
         }
-        #[derive(PartialEq, Eq, Hash, Clone, Copy)]
+        #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
         //chess file, not system file
-        pub enum File {
-            A,
-            B,
-            C,
-            D,
-            E,
-            F,
-            G,
-            H
-        }  
+        pub struct File(i8);
         impl File {
+            pub const A: File = File(0);
+            pub const B: File = File(1);
+            pub const C: File = File(2);
+            pub const D: File = File(3);
+            pub const E: File = File(4);
+            pub const F: File = File(5);
+            pub const G: File = File(6);
+            pub const H: File = File(7);
             pub fn as_index(&self) -> i8 {
-                match self {
-                    File::A => 0, File::B => 1,
-                    File::C => 2, File::D => 3,
-                    File::E => 4, File::F => 5,
-                    File::G => 6, File::H => 7
+                self.0
+            }
+            pub fn new_if_exists(f: i8) -> Option<Self> {
+                (0..MAX_BOARD_SIZE).contains(&f).then_some(File(f))
+            }
+        }
+
+        //The shape of a board: `ranks` by `files`, each between 2 and `MAX_BOARD_SIZE`.
+        //Rank and file indices run from 0 at white's bottom left, as on the standard board.
+        #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+        pub struct Geometry {
+            ranks: i8,
+            files: i8
+        }
+        impl Geometry {
+            pub const STANDARD: Geometry = Geometry {ranks: 8, files: 8};
+
+            pub const fn new(ranks: i8, files: i8) -> Option<Self> {
+                if ranks < 2 || files < 2 || ranks > MAX_BOARD_SIZE || files > MAX_BOARD_SIZE {
+                    return None;
                 }
+                Some(Geometry {ranks, files})
             }
-            pub fn new_if_exists(r: i8) -> Option<Self> {
-                let out = match r {
-                    0 => File::A, 1 => File::B,
-                    2 => File::C, 3 => File::D, 
-                    4 => File::E, 5 => File::F,
-                    6 => File::G, 7 => File::H,
-                    _ => {return None;}
-                };
-                Some(out)
+            pub fn ranks(&self) -> i8 {
+                self.ranks
+            }
+            pub fn files(&self) -> i8 {
+                self.files
+            }
+            pub fn is_standard(&self) -> bool {
+                *self == Self::STANDARD
+            }
+            pub fn contains(&self, rf: Rankfile) -> bool {
+                let (r, f) = rf.to_signed_coords();
+                r < self.ranks && f < self.files
+            }
+            //None if (r, f) is off this board.
+            pub fn rankfile(&self, r: i8, f: i8) -> Option<Rankfile> {
+                Rankfile::from(r, f).filter(|&rf| self.contains(rf))
+            }
+            //Every square, rank by rank.
+            pub fn squares(&self) -> impl Iterator<Item = Rankfile> {
+                let Geometry {ranks, files} = *self;
+                (0..ranks).flat_map(move |r| (0..files).filter_map(move |f| Rankfile::from(r, f)))
             }
         }
-        #[derive(PartialEq, Eq, Hash, Clone, Copy)]
+        impl Default for Geometry {
+            fn default() -> Self {
+                Self::STANDARD
+            }
+        }
+
+        #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
         pub struct Rankfile {
             pub rank: Rank,
             pub file: File
@@ -102,6 +227,7 @@ pub mod board {
         const CARDINAL_DIRECTIONS: [Direction; 4] = [(1, 0), (0, -1), (-1, 0), (0, 1)];
 
         impl Rankfile {
+            //Any square that fits on the largest board. Use `Geometry::rankfile` to stay on a particular one.
             pub fn from(r: i8, f: i8) -> Option<Rankfile> {
                 Some(Self {
                     rank: Rank::new_if_exists(r)?,
@@ -114,45 +240,33 @@ pub mod board {
             pub fn to_unsigned_coords(&self) -> (usize, usize) {
                 (self.rank.as_index() as usize, self.file.as_index() as usize)
             }
+            //Ranks are numbered from "1", files lettered from "a" (either case).
             pub fn from_strings(r: String, f: String) -> Option<Self> {
-                let r = match r.as_str() {
-                    "1" => Rank::R1, "2" => Rank::R2, 
-                    "3" => Rank::R3, "4" => Rank::R4, 
-                    "5" => Rank::R5, "6" => Rank::R6, 
-                    "7" => Rank::R7, "8" => Rank::R8,
-                    _ => {return None;}
-                };
-                let f = match f.as_str() {
-                    "A" | "a" => File::A, "B" | "b" => File::B, 
-                    "C" | "c" => File::C, "D" | "d" => File::D, 
-                    "E" | "e" => File::E, "F" | "f" => File::F, 
-                    "G" | "g" => File::G, "H" | "h" => File::H,
-                    _ => {return None;}
-                };
-                Some(Self {
-                    rank: r, file: f
-                })
+                if r.starts_with('0') || !r.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                let rank: i8 = r.parse().ok()?;
+                let mut f = f.chars();
+                let file = f.next()?.to_ascii_uppercase();
+                if f.next().is_some() || !file.is_ascii_uppercase() {
+                    return None;
+                }
+                Self::from(rank - 1, (file as u8 - b'A') as i8)
             }
             pub fn to_strings(&self) -> (String, String) {
-                let Rankfile{rank: r, file: f} = self;
-                let r_str = match r {
-                    Rank::R1 => "1", Rank::R2 => "2", 
-                    Rank::R3 => "3", Rank::R4 => "4", 
-                    Rank::R5 => "5", Rank::R6 => "6", 
-                    Rank::R7 => "7", Rank::R8 => "8"
-                };
-                let f_str = match f {
-                    File::A => "A", File::B =>"B", 
-                    File::C => "C", File::D =>"D", 
-                    File::E => "E", File::F =>"F", 
-                    File::G => "G", File::H =>"H"
-                };
-                (String::from(r_str), String::from(f_str))
+                let (r, f) = self.to_unsigned_coords();
+                ((r + 1).to_string(), char::from(b'A' + f as u8).to_string())
             }
 
             //Iterators:
             //(All exclude self / the center)
 
+            //(...except this one, which is every square on the standard board, rank by rank.
+            //For any other board, use `GameBoard::squares`.)
+            pub fn all_rankfiles() -> impl Iterator<Item = Rankfile> {
+                Geometry::STANDARD.squares()
+            }
+
 
             pub fn all_directions() -> impl Iterator <Item = &'static Direction> {
                 ALL_DIRECTIONS.iter()
@@ -160,6 +274,8 @@ pub mod board {
             pub fn cardinal_directions() -> impl Iterator<Item = &'static Direction> {
                 CARDINAL_DIRECTIONS.iter()
             }
+            //These don't know which board they're on, so they run to the edge of the largest one.
+            //Filter with `Geometry::contains`, or use `GameBoard::los`.
             pub fn surrounding_rankfiles(&self) -> impl Iterator<Item = Rankfile> {
                 let (r, f) = self.to_signed_coords();
                 ALL_DIRECTIONS.iter().filter_map(move |(dr, df)| {
@@ -169,34 +285,45 @@ pub mod board {
             pub fn card_ord_rankfiles(&self) -> impl Iterator<Item = Rankfile> {
                 let (r, f) = self.to_signed_coords();
                 ALL_DIRECTIONS.iter().flat_map(move |(dr, df)| {
-                    (1..8).map_while(move |i| Rankfile::from(r + dr * i, f + df * i))
+                    (1..MAX_BOARD_SIZE).map_while(move |i| Rankfile::from(r + dr * i, f + df * i))
                 })
             }
             pub fn card_rankfiles(&self) -> impl Iterator<Item = Rankfile> {
                 let (r, f) = self.to_signed_coords();
                 CARDINAL_DIRECTIONS.iter().flat_map(move |(dr, df)| {
-                    (1..8).map_while(move |i| Rankfile::from(r + dr * i, f + df * i))
+                    (1..MAX_BOARD_SIZE).map_while(move |i| Rankfile::from(r + dr * i, f + df * i))
                 })
             }
 
         }
     }
 
-    use rankfile::Rankfile;
+    use rankfile::{Rankfile, Geometry, MAX_BOARD_SIZE};
 
     #[derive(Clone)]
     pub struct GameBoard {
-        board:[[Square; 8]; 8],
+        //Rank by rank, `geometry.files()` squares each, so a board only holds the squares it has.
+        board: Vec<Square>,
+        geometry: Geometry,
         black_king_locs: Vec<Rankfile>,
         white_king_locs: Vec<Rankfile> //fully general, allows for silly boards with multiple kings.
     }
+    //Boards are equal when they have the same shape and pieces. The king locations follow from the pieces.
+    impl PartialEq for GameBoard {
+        fn eq(&self, other: &Self) -> bool {
+            self.geometry == other.geometry && self.board == other.board
+        }
+    }
+
+    impl Eq for GameBoard {}
+
     mod board_init_consts {
         use super::{UltimaPiece, Square, Rankfile};
         use super::rankfile::{Rank, File};
         use super::super::piece::{UltimaPieceType, PlayerColor};
 
         const EMPTY_SQUARE: Square = None;
-        pub const EMPTY_RANK: [Square; 8] = [EMPTY_SQUARE; 8];
+        const EMPTY_RANK: [Square; 8] = [EMPTY_SQUARE; 8];
 
         const W_PAWN: UltimaPiece = UltimaPiece {
             color: PlayerColor::White,
@@ -280,6 +407,7 @@ pub mod board {
             })
         ];
 
+        //The rotational setup. The others are built in `setups`.
         pub const START_BOARD: [[Square; 8]; 8] = [
             W_BACK_RANK,
             W_PAWN_RANK,
@@ -297,31 +425,52 @@ pub mod board {
     }
     
     impl GameBoard {
+        //Squares off the board are always empty.
         pub fn get_square(&self, rf: Rankfile) -> Square {
-            let (r, f) = rf.to_unsigned_coords();
-            self.board[r][f]
+            if !self.contains(rf) {
+                return None;
+            }
+            self.board[self.index(rf)]
         }
         pub fn get_square_from_coords(&self, r: i8, f: i8) -> Square {
-            let rf = match Rankfile::from(r, f) {
-                None => return None,
-                Some(rf) => rf
-            };
-            return self.get_square(rf);
+            let rf = Rankfile::from(r, f)?;
+            self.get_square(rf)
+        }
+        pub fn geometry(&self) -> Geometry {
+            self.geometry
+        }
+        fn index(&self, rf: Rankfile) -> usize {
+            let (r, f) = rf.to_unsigned_coords();
+            r * self.geometry.files() as usize + f
+        }
+        pub fn contains(&self, rf: Rankfile) -> bool {
+            self.geometry.contains(rf)
+        }
+        //None if (r, f) is off this board.
+        pub fn rankfile(&self, r: i8, f: i8) -> Option<Rankfile> {
+            self.geometry.rankfile(r, f)
+        }
+        //Every square, rank by rank.
+        pub fn squares(&self) -> impl Iterator<Item = Rankfile> {
+            self.geometry.squares()
         }
         pub fn new_empty() -> Self {
+            Self::new_empty_with_geometry(Geometry::STANDARD)
+        }
+        pub fn new_empty_with_geometry(geometry: Geometry) -> Self {
             GameBoard {
-                board: [board_init_consts::EMPTY_RANK; 8],
+                board: vec![None; geometry.ranks() as usize * geometry.files() as usize],
+                geometry,
                 black_king_locs: vec![],
                 white_king_locs: vec![]
             }
         }
         pub fn new_in_start_position() -> Self {
-            Self{
-                board: board_init_consts::START_BOARD,
-                black_king_locs: vec![board_init_consts::B_KING_LOC],
-                white_king_locs: vec![board_init_consts::W_KING_LOC]
-
-            }
+            let mut board = Self::new_empty();
+            board.board = board_init_consts::START_BOARD.concat();
+            board.black_king_locs = vec![board_init_consts::B_KING_LOC];
+            board.white_king_locs = vec![board_init_consts::W_KING_LOC];
+            board
         }
         pub fn get_king_locs(&self, color: PlayerColor) -> &Vec<Rankfile> {
             match color {
@@ -331,37 +480,190 @@ pub mod board {
         }
         pub fn los(&self, start: Rankfile, dir: rankfile::Direction) -> impl Iterator<Item = Rankfile> + '_ {
             let (r, f) = start.to_signed_coords();
-            (1..8).map_while(move |i| {Rankfile::from(r + dir.0 * i, f + dir.1 * i)})
+            (1..MAX_BOARD_SIZE).map_while(move |i| {self.rankfile(r + dir.0 * i, f + dir.1 * i)})
             .map_while(|rf| {
-                if let None = self.get_square(rf) {
+                if self.get_square(rf).is_none() {
                     Some(rf)
                 } else {
                     None
                 }
             })
         }
+        //Unlike `set_square`, this registers kings.
+        pub fn place_piece(&mut self, rf: Rankfile, piece: UltimaPiece) {
+            self.remove_piece(rf);
+            self.set_square(rf, Some(piece));
+            if piece.piece_type == UltimaPieceType::King {
+                self.king_locs_mut(piece.color).push(rf);
+            }
+        }
+        //Panics if `rf` is off the board.
         pub fn set_square(&mut self, rf: Rankfile, value: Square) {
-            let (r, f) = rf.to_unsigned_coords();
-            self.board[r][f] = value;
+            assert!(self.contains(rf), "{rf:?} is off the board");
+            let index = self.index(rf);
+            self.board[index] = value;
+        }
+        fn king_locs_mut(&mut self, color: PlayerColor) -> &mut Vec<Rankfile> {
+            match color {
+                PlayerColor::White => &mut self.white_king_locs,
+                PlayerColor::Black => &mut self.black_king_locs
+            }
+        }
+        //Unlike `set_square`, these also keep the king locations up to date.
+        pub fn remove_piece(&mut self, rf: Rankfile) {
+            if let Some(piece) = self.get_square(rf) {
+                if piece.piece_type == UltimaPieceType::King {
+                    self.king_locs_mut(piece.color).retain(|&loc| loc != rf);
+                }
+            }
+            self.set_square(rf, None);
+        }
+        pub fn move_piece(&mut self, start: Rankfile, end: Rankfile) {
+            let piece = self.get_square(start);
+            self.remove_piece(end);
+            self.set_square(start, None);
+            self.set_square(end, piece);
+            if let Some(piece) = piece {
+                if piece.piece_type == UltimaPieceType::King {
+                    for loc in self.king_locs_mut(piece.color) {
+                        if *loc == start {*loc = end;}
+                    }
+                }
+            }
         }
     }
 }
 use board::rankfile::Rankfile;
 
 pub mod moves {
+    use std::fmt;
     use super::Rankfile;
+    use super::board::GameBoard;
+    use super::piece::{UltimaPiece, UltimaPieceType};
+
+    //The rule a piece was captured by.
+    #[derive(PartialEq, Eq, Hash, Clone, Copy)]
+    pub enum CaptureKind {
+        //By a pawn, sandwiching it against another friendly piece.
+        Custodian,
+        //By a withdrawer, moving directly away from it.
+        Withdrawal,
+        //By a longleaper, jumping over it.
+        Leap,
+        //By a coordinator, on the coordinator's rank and a friendly king's file or the other way round.
+        Coordination,
+        //By a king, moving onto it.
+        KingDisplacement,
+        //By moving up to it, stopping on the square just before it (Rococo's advancer).
+        Approach,
+        //By the moving piece taking itself off the board along with it (`MoveKind::MutualDestruction`).
+        MutualDestruction,
+        //By a chameleon, capturing it the way it would capture.
+        ChameleonAs(UltimaPieceType)
+    }
+
+    impl fmt::Display for CaptureKind {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                CaptureKind::Custodian => write!(f, "custodian capture"),
+                CaptureKind::Withdrawal => write!(f, "withdrawal"),
+                CaptureKind::Leap => write!(f, "leap"),
+                CaptureKind::Coordination => write!(f, "coordination"),
+                CaptureKind::KingDisplacement => write!(f, "king displacement"),
+                CaptureKind::Approach => write!(f, "approach"),
+                CaptureKind::MutualDestruction => write!(f, "mutual destruction"),
+                CaptureKind::ChameleonAs(piece_type) => write!(f, "chameleon as {}", piece_type.name())
+            }
+        }
+    }
+
+    #[derive(PartialEq, Eq, Hash, Clone, Copy)]
+    pub struct Capture {
+        pub square: Rankfile,
+        pub piece: UltimaPiece,
+        pub kind: CaptureKind
+    }
+
+    #[derive(PartialEq, Eq, Hash, Clone, Copy)]
+    pub enum MoveKind {
+        Normal,
+        //A frozen piece taking itself off the board (`RuleSet::immobilized_suicide`).
+        //The move's start and end are both the piece's square.
+        Suicide,
+        //The piece trades places with the piece on `end`, of either colour (Rococo's swapper).
+        Swap,
+        //The piece takes itself off the board along with the adjacent enemy on `end` (Rococo's swapper).
+        MutualDestruction
+    }
 
     #[derive(PartialEq, Eq, Hash, Clone)]
     pub struct MoveData {
         pub start: Rankfile,
         pub end: Rankfile,
-        pub captures: Vec<Rankfile>
+        //The moving piece.
+        pub piece: UltimaPiece,
+        //The enemy pieces taken, and how, each square once.
+        pub capture_details: Vec<Capture>,
+        pub kind: MoveKind
     }
 
     impl MoveData {
-        pub fn new(start: Rankfile, end: Rankfile, captures: Vec<Rankfile>) -> Self {
+        //A move that doesn't take anything.
+        pub fn new(piece: UltimaPiece, start: Rankfile, end: Rankfile) -> Self {
+            MoveData {
+                start, end, piece, capture_details: vec![], kind: MoveKind::Normal
+            }
+        }
+
+        //A move taking whatever enemies are on `squares`, all by `kind`.
+        //Squares that are empty or friendly, or already listed, are skipped, so generators can pass every candidate.
+        pub fn capturing(
+            board: &GameBoard,
+            piece: UltimaPiece,
+            start: Rankfile,
+            end: Rankfile,
+            squares: impl IntoIterator<Item = Rankfile>,
+            kind: CaptureKind
+        ) -> Self {
+            let mut mv = Self::new(piece, start, end);
+            mv.add_captures(board, squares, kind);
+            mv
+        }
+
+        //`capturing`, for a move that already takes something.
+        pub fn add_captures(&mut self, board: &GameBoard, squares: impl IntoIterator<Item = Rankfile>, kind: CaptureKind) {
+            for square in squares {
+                let Some(captured) = board.get_square(square) else {continue};
+                if captured.color == self.piece.color || self.captures().any(|taken| taken == square) {continue}
+                self.capture_details.push(Capture {square, piece: captured, kind});
+            }
+        }
+
+        //The squares of the pieces taken.
+        pub fn captures(&self) -> impl Iterator<Item = Rankfile> + '_ {
+            self.capture_details.iter().map(|capture| capture.square)
+        }
+
+        pub fn swap(piece: UltimaPiece, start: Rankfile, end: Rankfile) -> Self {
+            MoveData {
+                kind: MoveKind::Swap,
+                ..Self::new(piece, start, end)
+            }
+        }
+
+        //`captured` is the enemy on `end`.
+        pub fn mutual_destruction(piece: UltimaPiece, start: Rankfile, end: Rankfile, captured: UltimaPiece) -> Self {
+            MoveData {
+                kind: MoveKind::MutualDestruction,
+                capture_details: vec![Capture {square: end, piece: captured, kind: CaptureKind::MutualDestruction}],
+                ..Self::new(piece, start, end)
+            }
+        }
+
+        pub fn suicide(piece: UltimaPiece, square: Rankfile) -> Self {
             MoveData {
-                start, end, captures
+                kind: MoveKind::Suicide,
+                ..Self::new(piece, square, square)
             }
         }
     }
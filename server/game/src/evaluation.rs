@@ -0,0 +1,323 @@
+use super::datatypes::{
+        board::{GameBoard,
+            rankfile::Rankfile
+        },
+        piece::{UltimaPiece, UltimaPieceType, PlayerColor}
+    };
+
+//Scores are always from the point of view of `color`: positive is good for `color`.
+
+pub mod hand_written {
+    use super::*;
+
+    //The king is never traded, so it carries no material value.
+    //Losing it is handled separately, since the game is over at that point.
+    pub const KING_CAPTURED_SCORE: i32 = 1_000_000;
+
+    pub fn piece_value(piece_type: UltimaPieceType) -> i32 {
+        use UltimaPieceType::*;
+        match piece_type {
+            Pawn => 100,
+            Withdrawer => 400,
+            Chameleon => 400,
+            Longleaper => 450,
+            Coordinator => 450,
+            Immobilizer => 550,
+            King => 0,
+            //No better guess without knowing what it does.
+            Custom(_) => 400
+        }
+    }
+
+    pub fn evaluate(board: &GameBoard, color: PlayerColor) -> i32 {
+        if board.get_king_locs(color).is_empty() {
+            return -KING_CAPTURED_SCORE;
+        }
+        if board.get_king_locs(color.opposite()).is_empty() {
+            return KING_CAPTURED_SCORE;
+        }
+        let mut score = 0;
+        for rf in board.squares() {
+            let Some(piece) = board.get_square(rf) else {continue};
+            if piece.color == color {
+                score += piece_value(piece.piece_type);
+            } else {
+                score -= piece_value(piece.piece_type);
+            }
+        }
+        score
+    }
+}
+
+pub mod nnue {
+    //A small efficiently updatable network:
+    //  piece-square features (x2 perspectives) -> HIDDEN_SIZE int16 accumulator -> clipped relu -> int8 output layer.
+    //The accumulator is kept up to date move by move (see `execute_move_with_accumulator`),
+    //so only the tiny output layer has to be computed per evaluation.
+
+    use super::*;
+    use std::fmt;
+    use std::path::Path;
+
+    pub const NUM_PIECE_TYPES: usize = UltimaPieceType::ALL.len();
+    //(own / opponent) x piece type x square
+    pub const NUM_FEATURES: usize = 2 * NUM_PIECE_TYPES * 64;
+    pub const HIDDEN_SIZE: usize = 128;
+
+    //Activations are clipped to [0, ACTIVATION_MAX] so they fit in an i8 for the output layer.
+    pub const ACTIVATION_MAX: i32 = 127;
+    //Fixed point scale of the int8 output weights.
+    pub const OUTPUT_WEIGHT_SCALE: i32 = 64;
+    //Converts the network's output to roughly the same units as `hand_written::evaluate`.
+    pub const OUTPUT_SCALE: i32 = 400;
+    //Outputs are clamped to this, well clear of the scores for a captured king.
+    pub const MAX_OUTPUT: i32 = 100_000;
+
+    const MAGIC: &[u8; 4] = b"ULNN";
+    const VERSION: u32 = 1;
+
+    //Each side sees the board from its own back rank, so that one set of weights serves both colors.
+    //Networks only know the standard board and Ultima's pieces: house pieces, and squares off the 8x8 board,
+    //have no feature.
+    pub fn feature_index(perspective: PlayerColor, piece: UltimaPiece, location: Rankfile) -> Option<usize> {
+        let (r, f) = location.to_unsigned_coords();
+        if piece.piece_type.is_custom() || r >= 8 || f >= 8 {
+            return None;
+        }
+        let r = match perspective {
+            PlayerColor::White => r,
+            PlayerColor::Black => 7 - r
+        };
+        let relation = if piece.color == perspective {0} else {1};
+        Some((relation * NUM_PIECE_TYPES + piece.piece_type.as_index()) * 64 + r * 8 + f)
+    }
+
+    #[derive(Debug)]
+    pub enum NnueLoadError {
+        Io(std::io::Error),
+        BadMagic,
+        UnsupportedVersion(u32),
+        HiddenSizeMismatch {expected: usize, found: usize},
+        UnexpectedEof,
+        TrailingBytes
+    }
+
+    impl fmt::Display for NnueLoadError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                NnueLoadError::Io(e) => write!(f, "couldn't read weights file: {e}"),
+                NnueLoadError::BadMagic => write!(f, "not an ultima nnue weights file"),
+                NnueLoadError::UnsupportedVersion(v) => write!(f, "unsupported weights file version {v}"),
+                NnueLoadError::HiddenSizeMismatch {expected, found} => {
+                    write!(f, "weights file has hidden size {found}, expected {expected}")
+                },
+                NnueLoadError::UnexpectedEof => write!(f, "weights file is truncated"),
+                NnueLoadError::TrailingBytes => write!(f, "weights file has trailing bytes")
+            }
+        }
+    }
+
+    impl std::error::Error for NnueLoadError {}
+
+    impl From<std::io::Error> for NnueLoadError {
+        fn from(e: std::io::Error) -> Self {
+            NnueLoadError::Io(e)
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct Network {
+        //NUM_FEATURES rows of HIDDEN_SIZE weights.
+        feature_weights: Vec<i16>,
+        feature_bias: Vec<i16>,
+        //First HIDDEN_SIZE weights apply to the side to move, the rest to the opponent.
+        output_weights: Vec<i8>,
+        output_bias: i32
+    }
+
+    //Reads little-endian values off the front of a byte slice.
+    struct Reader<'a> {
+        bytes: &'a [u8]
+    }
+
+    impl Reader<'_> {
+        fn take<const N: usize>(&mut self) -> Result<[u8; N], NnueLoadError> {
+            if self.bytes.len() < N {
+                return Err(NnueLoadError::UnexpectedEof);
+            }
+            let (head, tail) = self.bytes.split_at(N);
+            self.bytes = tail;
+            Ok(head.try_into().unwrap())
+        }
+        fn u32(&mut self) -> Result<u32, NnueLoadError> {
+            Ok(u32::from_le_bytes(self.take()?))
+        }
+        fn i32(&mut self) -> Result<i32, NnueLoadError> {
+            Ok(i32::from_le_bytes(self.take()?))
+        }
+        fn i16s(&mut self, n: usize) -> Result<Vec<i16>, NnueLoadError> {
+            (0..n).map(|_| Ok(i16::from_le_bytes(self.take()?))).collect()
+        }
+        fn i8s(&mut self, n: usize) -> Result<Vec<i8>, NnueLoadError> {
+            (0..n).map(|_| Ok(i8::from_le_bytes(self.take()?))).collect()
+        }
+    }
+
+    impl Network {
+        //Weights file layout (all little-endian):
+        //  b"ULNN", version: u32, hidden size: u32,
+        //  feature weights: [i16; NUM_FEATURES * HIDDEN_SIZE], feature bias: [i16; HIDDEN_SIZE],
+        //  output weights: [i8; 2 * HIDDEN_SIZE], output bias: i32
+        pub fn from_bytes(bytes: &[u8]) -> Result<Self, NnueLoadError> {
+            let mut reader = Reader {bytes};
+            if &reader.take::<4>()? != MAGIC {
+                return Err(NnueLoadError::BadMagic);
+            }
+            let version = reader.u32()?;
+            if version != VERSION {
+                return Err(NnueLoadError::UnsupportedVersion(version));
+            }
+            let hidden_size = reader.u32()? as usize;
+            if hidden_size != HIDDEN_SIZE {
+                return Err(NnueLoadError::HiddenSizeMismatch {expected: HIDDEN_SIZE, found: hidden_size});
+            }
+            let network = Network {
+                feature_weights: reader.i16s(NUM_FEATURES * HIDDEN_SIZE)?,
+                feature_bias: reader.i16s(HIDDEN_SIZE)?,
+                output_weights: reader.i8s(2 * HIDDEN_SIZE)?,
+                output_bias: reader.i32()?
+            };
+            if !reader.bytes.is_empty() {
+                return Err(NnueLoadError::TrailingBytes);
+            }
+            Ok(network)
+        }
+
+        pub fn load(path: impl AsRef<Path>) -> Result<Self, NnueLoadError> {
+            Self::from_bytes(&std::fs::read(path)?)
+        }
+
+        pub fn to_bytes(&self) -> Vec<u8> {
+            let mut out = Vec::with_capacity(12 + 2 * self.feature_weights.len() + 2 * HIDDEN_SIZE + 2 * HIDDEN_SIZE + 4);
+            out.extend_from_slice(MAGIC);
+            out.extend_from_slice(&VERSION.to_le_bytes());
+            out.extend_from_slice(&(HIDDEN_SIZE as u32).to_le_bytes());
+            for w in self.feature_weights.iter().chain(self.feature_bias.iter()) {
+                out.extend_from_slice(&w.to_le_bytes());
+            }
+            for w in &self.output_weights {
+                out.extend_from_slice(&w.to_le_bytes());
+            }
+            out.extend_from_slice(&self.output_bias.to_le_bytes());
+            out
+        }
+
+        fn feature_row(&self, feature: usize) -> &[i16] {
+            &self.feature_weights[feature * HIDDEN_SIZE..(feature + 1) * HIDDEN_SIZE]
+        }
+
+        //int8/int16 inference: the accumulator does all the heavy lifting,
+        //so this is just a clipped relu and one dot product per perspective.
+        pub fn evaluate(&self, accumulator: &Accumulator, color: PlayerColor) -> i32 {
+            let (own, opponent) = match color {
+                PlayerColor::White => (&accumulator.white, &accumulator.black),
+                PlayerColor::Black => (&accumulator.black, &accumulator.white)
+            };
+            let (own_weights, opponent_weights) = self.output_weights.split_at(HIDDEN_SIZE);
+            let mut sum: i32 = 0;
+            for i in 0..HIDDEN_SIZE {
+                sum += (own[i] as i32).clamp(0, ACTIVATION_MAX) * own_weights[i] as i32;
+                sum += (opponent[i] as i32).clamp(0, ACTIVATION_MAX) * opponent_weights[i] as i32;
+            }
+            //The sum is at most 2 * HIDDEN_SIZE * 127 * 128, but the bias can be anything, so scale in i64.
+            let scaled = (sum as i64 + self.output_bias as i64) * OUTPUT_SCALE as i64
+                / (ACTIVATION_MAX * OUTPUT_WEIGHT_SCALE) as i64;
+            scaled.clamp(-MAX_OUTPUT as i64, MAX_OUTPUT as i64) as i32
+        }
+    }
+
+    #[derive(Clone, PartialEq, Eq)]
+    pub struct Accumulator {
+        white: [i16; HIDDEN_SIZE],
+        black: [i16; HIDDEN_SIZE]
+    }
+
+    impl Accumulator {
+        //None for boards other than the standard one, which networks aren't trained on.
+        pub fn new(network: &Network, board: &GameBoard) -> Option<Self> {
+            if !board.geometry().is_standard() {
+                return None;
+            }
+            let mut bias = [0; HIDDEN_SIZE];
+            bias.copy_from_slice(&network.feature_bias);
+            let mut accumulator = Accumulator {
+                white: bias,
+                black: bias
+            };
+            for rf in board.squares() {
+                if let Some(piece) = board.get_square(rf) {
+                    accumulator.add_piece(network, piece, rf);
+                }
+            }
+            Some(accumulator)
+        }
+
+        pub fn add_piece(&mut self, network: &Network, piece: UltimaPiece, location: Rankfile) {
+            let (Some(white), Some(black)) = (feature_index(PlayerColor::White, piece, location), feature_index(PlayerColor::Black, piece, location)) else {return};
+            let white_row = network.feature_row(white);
+            let black_row = network.feature_row(black);
+            for i in 0..HIDDEN_SIZE {
+                self.white[i] = self.white[i].wrapping_add(white_row[i]);
+                self.black[i] = self.black[i].wrapping_add(black_row[i]);
+            }
+        }
+
+        pub fn remove_piece(&mut self, network: &Network, piece: UltimaPiece, location: Rankfile) {
+            let (Some(white), Some(black)) = (feature_index(PlayerColor::White, piece, location), feature_index(PlayerColor::Black, piece, location)) else {return};
+            let white_row = network.feature_row(white);
+            let black_row = network.feature_row(black);
+            for i in 0..HIDDEN_SIZE {
+                self.white[i] = self.white[i].wrapping_sub(white_row[i]);
+                self.black[i] = self.black[i].wrapping_sub(black_row[i]);
+            }
+        }
+    }
+}
+
+pub enum EvaluationBackend {
+    HandWritten,
+    Nnue(nnue::Network)
+}
+
+impl EvaluationBackend {
+    //Convenience for callers that don't keep an accumulator around.
+    //Anything evaluating many positions in a row should keep one up to date with
+    //`execute_move_with_accumulator` and use `evaluate_incrementally`, like the search does.
+    pub fn evaluate(&self, board: &GameBoard, color: PlayerColor) -> i32 {
+        self.evaluate_incrementally(board, color, None)
+    }
+
+    //`accumulator` has to be up to date with `board`. Without one, the nnue refreshes from scratch.
+    pub fn evaluate_incrementally(&self, board: &GameBoard, color: PlayerColor, accumulator: Option<&nnue::Accumulator>) -> i32 {
+        match self {
+            EvaluationBackend::HandWritten => hand_written::evaluate(board, color),
+            EvaluationBackend::Nnue(network) => {
+                //The network is never trained on finished games.
+                if board.get_king_locs(color).is_empty() {
+                    return -hand_written::KING_CAPTURED_SCORE;
+                }
+                if board.get_king_locs(color.opposite()).is_empty() {
+                    return hand_written::KING_CAPTURED_SCORE;
+                }
+                match accumulator {
+                    Some(accumulator) => network.evaluate(accumulator, color),
+                    None => match nnue::Accumulator::new(network, board) {
+                        Some(accumulator) => network.evaluate(&accumulator, color),
+                        //Nor on any other board shape.
+                        None => hand_written::evaluate(board, color)
+                    }
+                }
+            }
+        }
+    }
+}
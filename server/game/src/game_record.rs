@@ -0,0 +1,196 @@
+use std::fmt;
+
+use super::datatypes::{
+        board::GameBoard,
+        piece::PlayerColor,
+        moves::MoveData
+    };
+use super::logic::execute_move;
+use super::notation::{move_to_string, parse_move};
+use super::rules::RuleSet;
+use super::setups::StartPosition;
+
+//A PGN-like record of a game:
+//
+//  [White "alice"]
+//  [Black "bob"]
+//  [Setup "abbott"]
+//  [Rules "abbott,diagonal-pawns"]
+//
+//  1. e2-e4 d7-d5 {a comment on black's move} 2. a1-a4 ...
+//
+//The Setup tag names the start position by its `StartPosition` identifier. Without one, the game
+//started from the usual rotational setup.
+//The Rules tag names the rules it was played under by their `RuleSet` identifier, and the moves are
+//read under them. Without one, the game was played under the default rules.
+//Moves use the notation from `notation`. Comments go in braces after the move they're about.
+
+#[derive(Clone, Default)]
+pub struct GameRecord {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<MoveData>,
+    //One per move.
+    pub comments: Vec<Option<String>>
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum GameRecordError {
+    BadTag(String),
+    UnknownSetup(String),
+    UnknownRules(String),
+    UnterminatedComment,
+    IllegalMove {ply: usize, text: String}
+}
+
+impl fmt::Display for GameRecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameRecordError::BadTag(line) => write!(f, "couldn't read tag {line:?}"),
+            GameRecordError::UnknownSetup(id) => write!(f, "unknown setup {id:?}"),
+            GameRecordError::UnknownRules(id) => write!(f, "unknown rules {id:?}"),
+            GameRecordError::UnterminatedComment => write!(f, "comment is missing its closing brace"),
+            GameRecordError::IllegalMove {ply, text} => write!(f, "move {text:?} at ply {ply} is not legal")
+        }
+    }
+}
+
+impl std::error::Error for GameRecordError {}
+
+impl GameRecord {
+    pub fn new(moves: Vec<MoveData>) -> Self {
+        let comments = vec![None; moves.len()];
+        GameRecord {tags: vec![], moves, comments}
+    }
+
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    pub fn set_tag(&mut self, key: &str, value: &str) {
+        match self.tags.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.tags.push((key.to_string(), value.to_string()))
+        }
+    }
+
+    //From the Setup tag. An unknown setup reads as the rotational one; `from_text` rejects those.
+    pub fn start_position(&self) -> StartPosition {
+        self.tag("Setup").and_then(StartPosition::from_id).unwrap_or_default()
+    }
+
+    pub fn set_start_position(&mut self, setup: StartPosition) {
+        self.set_tag("Setup", &setup.id());
+    }
+
+    //From the Rules tag. Unknown rules read as the default ones; `from_text` rejects those.
+    pub fn rules(&self) -> RuleSet {
+        self.tag("Rules").and_then(RuleSet::from_id).unwrap_or_default()
+    }
+
+    pub fn set_rules(&mut self, rules: &RuleSet) {
+        self.set_tag("Rules", &rules.id());
+    }
+
+    //Every position in the game, before each move and after the last one.
+    pub fn positions(&self) -> Vec<(GameBoard, PlayerColor)> {
+        let mut board = self.start_position().board();
+        let mut whose_turn = PlayerColor::White;
+        let mut out = vec![(board.clone(), whose_turn)];
+        for mv in &self.moves {
+            execute_move(&mut board, mv.clone(), whose_turn);
+            whose_turn = whose_turn.opposite();
+            out.push((board.clone(), whose_turn));
+        }
+        out
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (key, value) in &self.tags {
+            out += &format!("[{key} \"{}\"]\n", value.replace('"', "'"));
+        }
+        if !self.tags.is_empty() {
+            out += "\n";
+        }
+        let mut tokens: Vec<String> = vec![];
+        let positions = self.positions();
+        for (i, mv) in self.moves.iter().enumerate() {
+            if i % 2 == 0 {
+                tokens.push(format!("{}.", i / 2 + 1));
+            }
+            tokens.push(move_to_string(&positions[i].0, mv));
+            if let Some(Some(comment)) = self.comments.get(i) {
+                tokens.push(format!("{{{}}}", comment.replace('}', ")")));
+            }
+        }
+        out += &tokens.join(" ");
+        out += "\n";
+        out
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, GameRecordError> {
+        let mut record = GameRecord::default();
+        let mut movetext = String::new();
+        for line in text.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') && movetext.trim().is_empty() {
+                let inner = trimmed.strip_prefix('[').and_then(|l| l.strip_suffix(']'))
+                    .ok_or_else(|| GameRecordError::BadTag(line.to_string()))?;
+                let (key, value) = inner.split_once(' ')
+                    .ok_or_else(|| GameRecordError::BadTag(line.to_string()))?;
+                let value = value.trim().strip_prefix('"').and_then(|v| v.strip_suffix('"'))
+                    .ok_or_else(|| GameRecordError::BadTag(line.to_string()))?;
+                record.tags.push((key.to_string(), value.to_string()));
+            } else {
+                movetext += line;
+                movetext += "\n";
+            }
+        }
+
+        if let Some(id) = record.tag("Setup") {
+            if StartPosition::from_id(id).is_none() {
+                return Err(GameRecordError::UnknownSetup(id.to_string()));
+            }
+        }
+        if let Some(id) = record.tag("Rules") {
+            if RuleSet::from_id(id).is_none() {
+                return Err(GameRecordError::UnknownRules(id.to_string()));
+            }
+        }
+        let rules = record.rules();
+        let mut board = record.start_position().board();
+        let mut whose_turn = PlayerColor::White;
+        let mut rest = movetext.as_str();
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                break;
+            }
+            if let Some(after_brace) = rest.strip_prefix('{') {
+                let end = after_brace.find('}').ok_or(GameRecordError::UnterminatedComment)?;
+                if let Some(last) = record.comments.last_mut() {
+                    *last = Some(after_brace[..end].trim().to_string());
+                }
+                rest = &after_brace[end + 1..];
+                continue;
+            }
+            let end = rest.find(|c: char| c.is_whitespace() || c == '{').unwrap_or(rest.len());
+            let token = &rest[..end];
+            rest = &rest[end..];
+            let is_move_number = token.ends_with('.') && token[..token.len() - 1].chars().all(|c| c.is_ascii_digit());
+            let is_result = matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*");
+            if is_move_number || is_result {
+                continue;
+            }
+            let mv = parse_move(&board, whose_turn, token, &rules).ok_or_else(|| GameRecordError::IllegalMove {
+                ply: record.moves.len() + 1,
+                text: token.to_string()
+            })?;
+            execute_move(&mut board, mv.clone(), whose_turn);
+            whose_turn = whose_turn.opposite();
+            record.moves.push(mv);
+            record.comments.push(None);
+        }
+        Ok(record)
+    }
+}
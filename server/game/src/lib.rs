@@ -1,13 +1,60 @@
 mod datatypes;
 mod logic;
+mod evaluation;
+mod rng;
+mod zobrist;
+mod opening_book;
+mod tablebase;
+mod notation;
+mod mate_search;
+mod puzzles;
+mod search;
+mod game_record;
+mod analysis;
+mod strength;
+mod match_runner;
+mod time_management;
+mod search_trace;
+mod threats;
+mod rules;
+mod setups;
+mod board_builder;
+mod position_validation;
+mod pieces;
+mod symmetry;
+mod packed_moves;
+pub mod rococo;
 
 
 pub use datatypes::{
     board::{GameBoard, 
-        rankfile::Rankfile
+        rankfile::{Rankfile, Geometry, MAX_BOARD_SIZE}
     },
-    piece::{UltimaPiece, UltimaPieceType, PlayerColor},
-    moves::MoveData
+    piece::{UltimaPiece, UltimaPieceType, CustomLetter, PlayerColor},
+    moves::{MoveData, MoveKind, Capture, CaptureKind}
 };
 
-pub use logic::*;
\ No newline at end of file
+pub use logic::*;
+pub use evaluation::*;
+pub use rng::Rng;
+pub use zobrist::*;
+pub use opening_book::*;
+pub use tablebase::{Material, Table, Tablebase, TablebaseResult, TablebaseLoadError, packbits};
+pub use notation::*;
+pub use mate_search::{mate_distance, mating_moves, find_mate};
+pub use puzzles::*;
+pub use search::*;
+pub use game_record::*;
+pub use analysis::*;
+pub use strength::*;
+pub use match_runner::*;
+pub use time_management::*;
+pub use search_trace::*;
+pub use rules::RuleSet;
+pub use setups::{StartPosition, shuffled_back_rank};
+pub use board_builder::{BoardBuilder, BoardBuildError, Handicap};
+pub use pieces::{PieceBehavior, PieceSet};
+pub use position_validation::{validate_position, PositionProblem, PositionProblemKind};
+pub use symmetry::Symmetry;
+pub use packed_moves::{PackedMove, PackedMoveList, generate_packed_moves, MAX_PACKED_MOVES};
+pub use threats::{Threat, ThreatMap, Immobilization, ImmobilizationRule, ImmobilizingPiece};
\ No newline at end of file
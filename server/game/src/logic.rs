@@ -3,32 +3,39 @@ use super::datatypes::{
             rankfile
         },
         piece::{UltimaPiece, UltimaPieceType, PlayerColor},
-        moves::MoveData
+        moves::{MoveData, MoveKind, Capture, CaptureKind}
     };
+use super::evaluation::nnue;
+use super::rules::RuleSet;
 use rankfile::Rankfile;
 
 pub mod move_validation {
 
     use super::*;
-    mod piece_checkers {
+    //The move generators behind the `PieceBehavior`s in `pieces`.
+    pub(crate) mod piece_checkers {
         use super::*;
         pub mod pawn {
 
             use super::*;
             pub fn move_generator_iter<'board>
-            (board: &'board GameBoard, start: Rankfile, color: PlayerColor) 
+            (board: &'board GameBoard, start: Rankfile, color: PlayerColor, rules: &RuleSet) 
             -> impl Iterator<Item = MoveData> + 'board 
             { 
+                let piece = UltimaPiece {piece_type: UltimaPieceType::Pawn, color};
+                let diagonally = rules.pawns_capture_diagonally;
                 Rankfile::cardinal_directions().flat_map(move |&dir| {
                     board.los(start, dir).map(move |rf| {
-                        let captures = Rankfile::all_directions().filter_map(|&(dr, df)| {
+                        let captures = Rankfile::all_directions().filter(|&&(dr, df)| {
+                            diagonally || dr == 0 || df == 0
+                        }).filter_map(|&(dr, df)| {
                             let (r, f) = rf.to_signed_coords();
                             let surrounding = board.get_square_from_coords(r + 2 * dr, f + 2 * df)?;
                             if surrounding.color == color {
                                 Some(Rankfile::from(r + dr, f + df)?)
                             } else {None}
-                        }).collect();
-                        MoveData{start, end: rf, captures}
+                        });
+                        MoveData::capturing(board, piece, start, rf, captures, CaptureKind::Custodian)
                     })
                 })
             }
@@ -39,36 +46,35 @@ pub mod move_validation {
 
             //In addition to checking for legal moves and captures, 
             //we provide a function to see if some OTHER piece is next to an immobilizer or not.
-            //This also checks to see if an immobilizer is immobilized by a chameleon.
+            //Which pieces freeze which is up to their `PieceBehavior::immobilizes`,
+            //e.g. a chameleon freezes an immobilizer.
            
-            pub fn is_immobilized(board: &GameBoard, location: Rankfile, piece: UltimaPiece) -> bool {
-                use UltimaPieceType::*;
-                let (r, f) = location.to_signed_coords();
-                for (dr, df) in Rankfile::all_directions() {
-                    let Some(rf) = Rankfile::from(r + dr, f + df) else {continue};
-                    let Some(adjacent_piece) = board.get_square(rf) else {continue};
-                    if adjacent_piece.piece_type == Immobilizer 
-                        && piece.color != adjacent_piece.color 
-                    {
-                        return true;
-                    }
-                    if piece.piece_type == Immobilizer
-                        && adjacent_piece.piece_type == Chameleon
-                        && piece.color != adjacent_piece.color 
-                    {
-                        return true;
+            pub fn is_immobilized(board: &GameBoard, location: Rankfile, piece: UltimaPiece, rules: &RuleSet) -> bool {
+                immobilizers(board, location, piece, rules).next().is_some()
+            }
+
+            //The squares of the pieces immobilizing `piece`.
+            pub fn immobilizers<'board>(board: &'board GameBoard, location: Rankfile, piece: UltimaPiece, rules: &RuleSet)
+            -> impl Iterator<Item = Rankfile> + 'board
+            {
+                let pieces = rules.pieces;
+                location.surrounding_rankfiles().filter(move |&rf| {
+                    let Some(adjacent_piece) = board.get_square(rf) else {return false};
+                    if piece.color == adjacent_piece.color {
+                        return false;
                     }
-                }
-                false
+                    pieces.behavior(adjacent_piece.piece_type).is_some_and(|behavior| behavior.immobilizes(piece.piece_type))
+                })
             }
 
             pub fn move_generator_iter<'board> 
-            (board: &'board GameBoard, start:Rankfile)
+            (board: &'board GameBoard, start:Rankfile, color: PlayerColor)
             -> impl Iterator<Item = MoveData> + 'board
             {
+                let piece = UltimaPiece {piece_type: UltimaPieceType::Immobilizer, color};
                 Rankfile::all_directions().flat_map(move |&dir| {
                     board.los(start, dir).map(move |rf| {
-                        MoveData::new(start, rf, vec![])
+                        MoveData::new(piece, start, rf)
                     })
                 })
             }
@@ -81,6 +87,7 @@ pub mod move_validation {
             (board: &'board GameBoard, start: Rankfile, color: PlayerColor) 
             -> impl Iterator<Item = MoveData> + 'board 
             {
+                let piece = UltimaPiece {piece_type: UltimaPieceType::Coordinator, color};
                 Rankfile::all_directions().flat_map(move |&dir| {
                     board.los(start, dir).map(move |rf| {
                         let mut captures = vec![];
@@ -88,7 +95,7 @@ pub mod move_validation {
                             captures.push(Rankfile{rank: rf.rank, file: king.file});
                             captures.push(Rankfile{rank: king.rank, file: rf.file});
                         }
-                        MoveData::new(start, rf, captures)
+                        MoveData::capturing(board, piece, start, rf, captures, CaptureKind::Coordination)
                     })
                 })
             }
@@ -96,37 +103,38 @@ pub mod move_validation {
 
         pub mod longleaper {
             use super::*;
-            pub fn generate_moves<'board> (board: &'board GameBoard, start: Rankfile, color: PlayerColor) -> Vec<MoveData>
+            //Slides like a queen, and may jump any number of enemy pieces along the way, capturing them,
+            //as long as each one has an empty square right behind it. Friendly pieces, and two enemies in a row, block.
+            pub fn generate_moves(board: &GameBoard, start: Rankfile, color: PlayerColor) -> Vec<MoveData>
             {
+                let piece = UltimaPiece {piece_type: UltimaPieceType::Longleaper, color};
                 let mut moves = vec![];
                 let (r, f) = start.to_signed_coords();
                 for (dr, df) in Rankfile::all_directions() {
-                    let mut leapt_prev_target = false;
+                    let mut jumping = None;
                     let mut dr_mut = *dr; let mut df_mut = *df;
                     let mut captures = vec![];
-                    while let Some(rf) = Rankfile::from(r + dr_mut, f + df_mut) {
-                        if let Some(piece) =  board.get_square(rf) {
-                            if piece.color == color {
+                    while let Some(rf) = board.rankfile(r + dr_mut, f + df_mut) {
+                        dr_mut += dr;
+                        df_mut += df;
+                        if let Some(piece) = board.get_square(rf) {
+                            if piece.color == color || jumping.is_some() {
                                 break;
-                            } else if leapt_prev_target {
-                                break; //can't leap two adjacent enemies
-                            } else {
-                                leapt_prev_target = true;
-                                continue; //can't move onto this square, but maybe the next is good.
                             }
-                        } else {
-                            leapt_prev_target = false;
+                            jumping = Some(rf); //can't move onto this square, but maybe the next is good.
+                            continue;
                         }
-                        if leapt_prev_target {
-                            captures.push(rf);
+                        if let Some(jumped) = jumping.take() {
+                            captures.push(jumped);
                         }
-                        moves.push(MoveData::new(
+                        moves.push(MoveData::capturing(
+                            board,
+                            piece,
                             start,
                             rf,
-                            captures.clone()
+                            captures.iter().copied(),
+                            CaptureKind::Leap
                         ));
-                        dr_mut += dr;
-                        df_mut += df;
                     }
                 }
                 moves
@@ -141,9 +149,10 @@ pub mod move_validation {
             //Note: chameleons immobilizing immobilizers is handled inside the `immobilizer` module.
 
             use super::*;
-            pub fn generate_moves(board: &GameBoard, start: Rankfile, color: PlayerColor) -> Vec<MoveData> {
+            pub fn generate_moves(board: &GameBoard, start: Rankfile, color: PlayerColor, rules: &RuleSet) -> Vec<MoveData> {
                 use UltimaPieceType::*;
 
+                let piece = UltimaPiece {piece_type: Chameleon, color};
                 let mut moves = vec![];
 
                 //overall number of valid moves should be *very* small,
@@ -154,8 +163,8 @@ pub mod move_validation {
                     for rhs_move in &extension {
                         unioned = false;
                         for lhs_move in &mut *moves {
-                            if lhs_move.end == rhs_move.end {
-                                lhs_move.captures.extend(rhs_move.captures.iter());
+                            if lhs_move.end == rhs_move.end && lhs_move.kind == rhs_move.kind {
+                                lhs_move.capture_details.extend(rhs_move.capture_details.iter());
                                 unioned = true;
                                 break;
                             }
@@ -168,82 +177,74 @@ pub mod move_validation {
 
                 let keep_if_cham_match = |piece_type: UltimaPieceType| {
                     move |move_data: MoveData| {
-                        let mut captures_out = vec![];
-                        for capture in move_data.captures {
-                            if let Some(p) = board.get_square(capture) {
-                                if p.color != color && p.piece_type == piece_type {
-                                    captures_out.push(capture);
-                                }
-                            }
+                        //A swap only goes with an enemy of the imitated kind.
+                        if move_data.kind == MoveKind::Swap {
+                            let swaps_with = board.get_square(move_data.end);
+                            return swaps_with.is_some_and(|p| p.color != color && p.piece_type == piece_type)
+                                .then(|| MoveData::swap(piece, move_data.start, move_data.end));
                         }
-                        if !captures_out.is_empty() {
-                            Some(MoveData{start: move_data.start, end: move_data.end, captures: captures_out})
-                        } else {
-                            None
+                        //A leap has to go over nothing but longleapers; the other pieces just ignore what they can't take.
+                        if piece_type == Longleaper && move_data.capture_details.iter().any(|capture| capture.piece.piece_type != Longleaper) {
+                            return None;
                         }
+                        let mut out = MoveData::new(piece, move_data.start, move_data.end);
+                        out.capture_details = move_data.capture_details.into_iter()
+                            .filter(|capture| capture.piece.piece_type == piece_type)
+                            .map(|capture| Capture {kind: CaptureKind::ChameleonAs(piece_type), ..capture})
+                            .collect();
+                        (!out.capture_details.is_empty()).then_some(out)
                     }
                 };
 
                 //objection! needs to filter by the CAPTURES including the correct color and type,
                 //NOT by anything involving the end square!!!
 
-                //As pawn:
-                union_moves(&mut moves, pawn::move_generator_iter(board, start, color)
-                    .filter_map(keep_if_cham_match(Pawn)).collect());
-
-                //As king:
-                //do NOT check for checkmate.
-                union_moves(&mut moves, king::generate_moves_naive(board, start, color).into_iter()
-                    .filter_map(keep_if_cham_match(King)).collect());
-
-                //As Longleaper:
-                union_moves(&mut moves, longleaper::generate_moves(board, start, color).into_iter()
-                    .filter_map(keep_if_cham_match(Longleaper)).collect());
-                //As Withdrawer:
-                union_moves(&mut moves, withdrawer::move_generator_iter(board, start, color)
-                    .filter_map(keep_if_cham_match(Withdrawer)).collect());
-                //As Coordinator:
-                union_moves(&mut moves, withdrawer::move_generator_iter(board, start, color)
-                    .filter_map(keep_if_cham_match(King)).collect());
-
+                //As every piece that lets itself be imitated (`PieceBehavior::chameleon_can_copy`).
+                //As king, do NOT check for checkmate.
                 //(No captures possible on Immobilizers or Chameleons)
+                for behavior in rules.pieces.behaviors().filter(|behavior| behavior.chameleon_can_copy(rules)) {
+                    union_moves(&mut moves, behavior.generate_moves(board, start, color, rules).into_iter()
+                        .filter_map(keep_if_cham_match(behavior.piece_type())).collect());
+                }
 
                 //Native / non-capture moves:
+                //(los only yields empty squares.)
                 union_moves(&mut moves, Rankfile::all_directions().flat_map(|&dir| {
-                    board.los(start, dir).filter_map(move |rf| {
-                        if let None = board.get_square(rf) {
-                            Some(MoveData{start, end: rf, captures: vec![]})
-                        } else {
-                            //Theoretically impossible, since los already filters away this case.
-                            panic!()
-                            //Maybe should do something besides panicking...
-                        }
-                    })
+                    board.los(start, dir).map(move |rf| MoveData::new(piece, start, rf))
                 }).collect());
                 
                 moves
             }
 
+            //A chameleon next to an enemy immobilizer is frozen, but under
+            //`RuleSet::chameleon_withdraws_from_immobilizer` it may still capture it by moving directly away.
+            //Only when that immobilizer is the only thing freezing it.
+            pub fn withdrawals_from_immobilizer(board: &GameBoard, start: Rankfile, color: PlayerColor, rules: &RuleSet) -> Vec<MoveData> {
+                let piece = UltimaPiece {piece_type: UltimaPieceType::Chameleon, color};
+                let freezing: Vec<Rankfile> = immobilizer::immobilizers(board, start, piece, rules).collect();
+                let [immobilizer_square] = freezing[..] else {return vec![]};
+                let (r, f) = start.to_signed_coords();
+                let (ir, i_f) = immobilizer_square.to_signed_coords();
+                let away = (r - ir, f - i_f);
+                board.los(start, away).map(|rf| {
+                    MoveData::capturing(board, piece, start, rf, [immobilizer_square], CaptureKind::ChameleonAs(UltimaPieceType::Withdrawer))
+                }).collect()
+            }
+
         }
         pub mod withdrawer {
             use super::*;
+            //Captures the piece it moves directly away from, which has to be right next to it.
             pub fn move_generator_iter<'board> (board: &'board GameBoard, start: Rankfile, color: PlayerColor)
             -> impl Iterator<Item = MoveData> + 'board 
             {
+                let piece = UltimaPiece {piece_type: UltimaPieceType::Withdrawer, color};
+                let (r, f) = start.to_signed_coords();
                 Rankfile::all_directions().flat_map(move |&dir| {
+                    let behind = board.rankfile(r - dir.0, f - dir.1);
                     board.los(start, dir).map(move |rf| {
-                        (start.surrounding_rankfiles().filter_map(|adj_start| {
-                            let piece = board.get_square(adj_start)?;
-                            if piece.color != color {Some(rf)} else {None}
-                        }).filter(|&adj_start| {
-                            for adj_end in rf.surrounding_rankfiles() {
-                                if adj_end == adj_start {return true;}
-                            }
-                            false
-                        }).collect(), rf)
-                    }).map(move |(captures, end)| {
-                        MoveData {start, end, captures}
-                    })  
+                        MoveData::capturing(board, piece, start, rf, behind, CaptureKind::Withdrawal)
+                    })
                 })
             }
         }
@@ -251,73 +252,190 @@ pub mod move_validation {
         pub mod king {
             //for now, ignore checkmate, just play by capture-the-king rules.
             use super::*;
-            pub fn generate_moves_naive(board: &GameBoard, start: Rankfile, color: PlayerColor) -> Vec<MoveData> {
+            pub fn generate_moves_naive(board: &GameBoard, start: Rankfile, color: PlayerColor, rules: &RuleSet) -> Vec<MoveData> {
+                let king = UltimaPiece {piece_type: UltimaPieceType::King, color};
+                //A chameleon borrowing these moves only takes kings, so it never coordinates.
+                let coordinates = rules.king_coordinates && board.get_square(start) == Some(king);
                 let mut moves = vec![];
-                moves.extend(start.surrounding_rankfiles().filter_map(|rf| {
-                    let Some(piece) = board.get_square(rf) 
-                    else {
-                        return Some(MoveData{start, end: rf, captures: vec![]});
+                moves.extend(start.surrounding_rankfiles().filter(|&rf| board.contains(rf)).filter_map(|rf| {
+                    let mut mv = match board.get_square(rf) {
+                        None => MoveData::new(king, start, rf),
+                        Some(piece) if piece.color != color => MoveData::capturing(board, king, start, rf, [rf], CaptureKind::KingDisplacement),
+                        Some(_) => return None
                     };
-                    if piece.color != color {
-                        return Some(MoveData{start, end: rf, captures: vec![rf]});
-                    } else {
-                        return None;
+                    if coordinates {
+                        add_coordinated_captures(board, &mut mv);
                     }
+                    Some(mv)
                 }));
                 moves
             }
-            
+
+            //`RuleSet::king_coordinates`: the squares where the king's new lines cross each friendly coordinator's.
+            //A piece on the king's own square is already taken by displacement, which `add_captures` keeps.
+            fn add_coordinated_captures(board: &GameBoard, mv: &mut MoveData) {
+                let coordinator = UltimaPiece {piece_type: UltimaPieceType::Coordinator, color: mv.piece.color};
+                let end = mv.end;
+                let crossings = board.squares().filter(|&rf| board.get_square(rf) == Some(coordinator)).flat_map(move |rf| {
+                    [Rankfile {rank: end.rank, file: rf.file}, Rankfile {rank: rf.rank, file: end.file}]
+                });
+                mv.add_captures(board, crossings, CaptureKind::Coordination);
+            }
         }
         
     }
 
-    pub fn get_all_legal_moves(board: &GameBoard, start: Rankfile, piece: UltimaPiece) -> Vec<MoveData> {
+    pub fn get_all_legal_moves(board: &GameBoard, start: Rankfile, piece: UltimaPiece, rules: &RuleSet) -> Vec<MoveData> {
         //cache efficiency makes it prudent to go for vecs over hashsets for such small collections of data.
         //empirical testing is still to be done.
-        use UltimaPieceType::*;
         use piece_checkers::*;
-        if immobilizer::is_immobilized(board, start, piece) {return Vec::new()}
-        let color = piece.color;
-        match piece.piece_type {
-            Chameleon => {
-                chameleon::generate_moves(board, start, color)
-            },
-            Coordinator => {
-                coordinator::move_generator_iter(board, start, color).collect()
-            },
-            Immobilizer => {
-                immobilizer::move_generator_iter(board, start).collect()
-            },
-            King => {
-                king::generate_moves_naive(board, start, color)
-            },
-            Longleaper => {
-                longleaper::generate_moves(board, start, color)
-            },
-            Pawn => {
-                pawn::move_generator_iter(board, start, color).collect()
-            },
-            Withdrawer => {
-                withdrawer::move_generator_iter(board, start, color).collect()
-            }     
+        if immobilizer::is_immobilized(board, start, piece, rules) {
+            let mut moves = vec![];
+            if piece.piece_type == UltimaPieceType::Chameleon && rules.chameleon_withdraws_from_immobilizer {
+                moves = chameleon::withdrawals_from_immobilizer(board, start, piece.color, rules);
+            }
+            if can_commit_suicide(piece, rules) {
+                moves.push(MoveData::suicide(piece, start));
+            }
+            return moves;
         }
+        let Some(behavior) = rules.pieces.behavior(piece.piece_type) else {return vec![]};
+        behavior.generate_moves(board, start, piece.color, rules)
+    }
+
+    pub use piece_checkers::immobilizer::{is_immobilized, immobilizers};
+
+    //Whether `piece` may remove itself from the board when it's frozen.
+    //A king can't: that would just be resigning.
+    pub fn can_commit_suicide(piece: UltimaPiece, rules: &RuleSet) -> bool {
+        rules.immobilized_suicide && piece.piece_type != UltimaPieceType::King
+    }
+
+    //Whether frozen `piece` on `start` still has a move under `RuleSet::chameleon_withdraws_from_immobilizer`.
+    pub fn can_withdraw_from_immobilizer(board: &GameBoard, start: Rankfile, piece: UltimaPiece, rules: &RuleSet) -> bool {
+        piece.piece_type == UltimaPieceType::Chameleon && rules.chameleon_withdraws_from_immobilizer
+            && !piece_checkers::chameleon::withdrawals_from_immobilizer(board, start, piece.color, rules).is_empty()
+    }
+
+    //The legal move from `start` to `end`, if there is one.
+    pub fn find_legal_move(board: &GameBoard, whose_turn: PlayerColor, start: Rankfile, end: Rankfile, rules: &RuleSet) -> Option<MoveData> {
+        let piece = board.get_square(start)?;
+        if piece.color != whose_turn {
+            return None;
+        }
+        get_all_legal_moves(board, start, piece, rules).into_iter().find(|mv| mv.end == end)
+    }
+
+    pub fn get_all_legal_moves_for_color(board: &GameBoard, color: PlayerColor, rules: &RuleSet) -> Vec<MoveData> {
+        let mut moves = vec![];
+        for rf in board.squares() {
+            let Some(piece) = board.get_square(rf) else {continue};
+            if piece.color == color {
+                moves.extend(get_all_legal_moves(board, rf, piece, rules));
+            }
+        }
+        moves
     }
 }
 
 
+//Whether the move would take one of the opponent's kings, ending the game.
+pub fn captures_king(board: &GameBoard, mv: &MoveData, whose_turn: PlayerColor) -> bool {
+    mv.captures().any(|rf| {
+        board.get_square(rf).is_some_and(|p| p.piece_type == UltimaPieceType::King && p.color != whose_turn)
+    })
+}
+
+//Whether `color`'s opponent could take one of `color`'s kings, if it were the opponent's move.
+pub fn is_in_check(board: &GameBoard, color: PlayerColor, rules: &RuleSet) -> bool {
+    board.threats_against(color, rules).threats.iter().any(|threat| threat.piece.piece_type == UltimaPieceType::King)
+}
+
 pub fn execute_move(board: &mut GameBoard, move_to_execute: MoveData, whose_turn: PlayerColor) {
     let MoveData {
         start,
         end,
-        captures
+        capture_details,
+        kind,
+        ..
     } = move_to_execute;
-    for square in captures {
-        if let Some(p) = board.get_square(square) {
-            if p.color != whose_turn {
-                board.set_square(square, None);
+    match kind {
+        MoveKind::Normal => {},
+        MoveKind::Suicide => {
+            board.remove_piece(start);
+            return;
+        },
+        MoveKind::Swap => {
+            let other = board.get_square(end);
+            board.move_piece(start, end);
+            if let Some(other) = other {
+                board.place_piece(start, other);
+            }
+            return;
+        },
+        MoveKind::MutualDestruction => {
+            board.remove_piece(start);
+        }
+    }
+    for capture in capture_details {
+        if board.get_square(capture.square).is_some_and(|p| p.color != whose_turn) {
+            board.remove_piece(capture.square);
+        }
+    }
+    if kind == MoveKind::Normal {
+        board.move_piece(start, end);
+    }
+}
+
+//Takes back `execute_move`, putting the pieces in `capture_details` back.
+pub fn undo_move(board: &mut GameBoard, executed: &MoveData) {
+    match executed.kind {
+        MoveKind::Normal => board.move_piece(executed.end, executed.start),
+        MoveKind::Suicide => {
+            board.place_piece(executed.start, executed.piece);
+            return;
+        },
+        MoveKind::Swap => {
+            let other = board.get_square(executed.start);
+            board.move_piece(executed.end, executed.start);
+            if let Some(other) = other {
+                board.place_piece(executed.end, other);
             }
+            return;
+        },
+        MoveKind::MutualDestruction => board.place_piece(executed.start, executed.piece)
+    }
+    for capture in &executed.capture_details {
+        board.place_piece(capture.square, capture.piece);
+    }
+}
+
+//Same as `execute_move`, but also updates an nnue accumulator for the position, so that evaluating
+//the resulting position doesn't require refreshing the accumulator from scratch.
+pub fn execute_move_with_accumulator(
+    board: &mut GameBoard, 
+    move_to_execute: MoveData, 
+    whose_turn: PlayerColor,
+    accumulator: &mut nnue::Accumulator,
+    network: &nnue::Network
+) {
+    let MoveData {start, end, capture_details, kind, ..} = &move_to_execute;
+    for capture in capture_details {
+        if let Some(p) = board.get_square(capture.square).filter(|p| p.color != whose_turn) {
+            accumulator.remove_piece(network, p, capture.square);
+        }
+    }
+    if let Some(p) = board.get_square(*start) {
+        accumulator.remove_piece(network, p, *start);
+        if matches!(kind, MoveKind::Normal | MoveKind::Swap) {
+            accumulator.add_piece(network, p, *end);
+        }
+    }
+    if *kind == MoveKind::Swap {
+        if let Some(other) = board.get_square(*end) {
+            accumulator.remove_piece(network, other, *end);
+            accumulator.add_piece(network, other, *start);
         }
     }
-    board.set_square(end, board.get_square(start));
-    board.set_square(start, None);
+    execute_move(board, move_to_execute, whose_turn);
 }
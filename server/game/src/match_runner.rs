@@ -0,0 +1,257 @@
+use super::datatypes::{
+        board::GameBoard,
+        piece::PlayerColor,
+        moves::MoveData
+    };
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::logic::execute_move;
+use super::evaluation::EvaluationBackend;
+use super::strength::{SkillLevel, choose_move};
+use super::search::Searcher;
+use super::time_management::{TimeControl, PonderHandle, spawn_ponder};
+use super::opening_book::OpeningBook;
+use super::tablebase::Tablebase;
+use super::rng::Rng;
+use super::rules::RuleSet;
+
+//Plays engine games against each other, e.g. to measure the strength levels.
+
+pub trait Player {
+    //None if there's nothing to play.
+    fn choose_move(&mut self, board: &GameBoard, whose_turn: PlayerColor, rules: &RuleSet) -> Option<MoveData>;
+
+    //Used instead of `choose_move` in timed games. `clock` is the mover's side of the clock.
+    fn choose_move_on_clock(&mut self, board: &GameBoard, whose_turn: PlayerColor, rules: &RuleSet, clock: &TimeControl) -> Option<MoveData> {
+        let _ = clock;
+        self.choose_move(board, whose_turn, rules)
+    }
+}
+
+pub struct EnginePlayer<'a> {
+    pub skill: SkillLevel,
+    backend: &'a EvaluationBackend,
+    book: Option<&'a OpeningBook>,
+    tablebase: Option<&'a Tablebase>,
+    rng: Rng
+}
+
+impl<'a> EnginePlayer<'a> {
+    pub fn new(skill: SkillLevel, backend: &'a EvaluationBackend, seed: u64) -> Self {
+        EnginePlayer {skill, backend, book: None, tablebase: None, rng: Rng::new(seed)}
+    }
+
+    //Plays from the book while the game is in it. The book has to be built under the game's rules.
+    pub fn set_book(&mut self, book: Option<&'a OpeningBook>) {
+        self.book = book;
+    }
+
+    //Lets the search look up endgames. Tables generated under other rules than the game's are ignored.
+    pub fn set_tablebase(&mut self, tablebase: Option<&'a Tablebase>) {
+        self.tablebase = tablebase;
+    }
+}
+
+impl Player for EnginePlayer<'_> {
+    fn choose_move(&mut self, board: &GameBoard, whose_turn: PlayerColor, rules: &RuleSet) -> Option<MoveData> {
+        if let Some(mv) = self.book.and_then(|book| book.probe(board, whose_turn, rules, &mut self.rng)) {
+            return Some(mv);
+        }
+        choose_move(board, whose_turn, self.skill, rules, self.backend, self.tablebase, &mut self.rng)
+    }
+}
+
+//Plays at full strength, thinking for as long as the clock allows,
+//and if `ponder` is set, thinking about its next move on the opponent's time.
+pub struct TimedEnginePlayer {
+    backend: Arc<EvaluationBackend>,
+    //How long to think per move in untimed games.
+    pub move_time: Duration,
+    pub ponder: bool,
+    pondering: Option<PonderHandle>,
+    book: Option<Arc<OpeningBook>>,
+    rng: Rng
+}
+
+impl TimedEnginePlayer {
+    pub fn new(backend: Arc<EvaluationBackend>, move_time: Duration, ponder: bool) -> Self {
+        TimedEnginePlayer {backend, move_time, ponder, pondering: None, book: None, rng: Rng::new(0)}
+    }
+
+    //Like `EnginePlayer::set_book`. `seed` picks between the book's moves.
+    pub fn set_book(&mut self, book: Option<Arc<OpeningBook>>, seed: u64) {
+        self.book = book;
+        self.rng = Rng::new(seed);
+    }
+}
+
+impl Player for TimedEnginePlayer {
+    fn choose_move(&mut self, board: &GameBoard, whose_turn: PlayerColor, rules: &RuleSet) -> Option<MoveData> {
+        self.choose_move_on_clock(board, whose_turn, rules, &TimeControl::move_time(self.move_time))
+    }
+
+    fn choose_move_on_clock(&mut self, board: &GameBoard, whose_turn: PlayerColor, rules: &RuleSet, clock: &TimeControl) -> Option<MoveData> {
+        if let Some(mv) = self.book.as_ref().and_then(|book| book.probe(board, whose_turn, rules, &mut self.rng)) {
+            //Whatever was being pondered is moot now.
+            if let Some(handle) = self.pondering.take() {
+                handle.stop();
+                handle.join();
+            }
+            return Some(mv);
+        }
+        let result = match self.pondering.take() {
+            Some(handle) if handle.is_hit(board, whose_turn) => {
+                handle.ponderhit(clock);
+                handle.join()
+            },
+            pondering => {
+                if let Some(handle) = pondering {
+                    handle.stop();
+                    handle.join();
+                }
+                let mut searcher = Searcher::new(&self.backend);
+                searcher.set_rules(*rules);
+                searcher.search_timed(board, whose_turn, clock)
+            }
+        };
+        let best_move = result.best_move?;
+        if self.ponder {
+            if let Some(reply) = result.principal_variation.get(1) {
+                let mut after = board.clone();
+                execute_move(&mut after, best_move.clone(), whose_turn);
+                self.pondering = spawn_ponder(&after, whose_turn.opposite(), reply.clone(), *rules, self.backend.clone());
+            }
+        }
+        Some(best_move)
+    }
+}
+
+//Waits for the ponder search to wind down, so it isn't left running (and holding the backend) after the player is gone.
+impl Drop for TimedEnginePlayer {
+    fn drop(&mut self) {
+        if let Some(handle) = self.pondering.take() {
+            handle.stop();
+            handle.join();
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum GameOutcome {
+    WhiteWins,
+    BlackWins,
+    Draw
+}
+
+fn win_for(color: PlayerColor) -> GameOutcome {
+    match color {
+        PlayerColor::White => GameOutcome::WhiteWins,
+        PlayerColor::Black => GameOutcome::BlackWins
+    }
+}
+
+//A game is won by taking the king, or when the opponent runs out of time.
+//It's drawn if the side to move has nothing to play, or if nobody has won after `max_plies` plies.
+//With a `time_control`, both sides start with its clock. If it has moves to go,
+//its time is added again every time a side has played that many moves.
+pub fn play_game(
+    white: &mut dyn Player,
+    black: &mut dyn Player,
+    max_plies: u32,
+    time_control: Option<&TimeControl>,
+    rules: &RuleSet
+) -> (GameOutcome, Vec<MoveData>) {
+    let mut board = GameBoard::new_in_start_position();
+    let mut whose_turn = PlayerColor::White;
+    let mut clocks = [time_control.copied(); 2];
+    let mut moves = vec![];
+    for _ in 0..max_plies {
+        let player: &mut dyn Player = match whose_turn {
+            PlayerColor::White => &mut *white,
+            PlayerColor::Black => &mut *black
+        };
+        let clock = &mut clocks[whose_turn.as_index()];
+        let started = Instant::now();
+        let choice = match clock {
+            Some(clock) => player.choose_move_on_clock(&board, whose_turn, rules, clock),
+            None => player.choose_move(&board, whose_turn, rules)
+        };
+        if let (Some(clock), Some(time_control)) = (clock, time_control) {
+            let elapsed = started.elapsed();
+            if elapsed > clock.remaining {
+                return (win_for(whose_turn.opposite()), moves);
+            }
+            clock.remaining = clock.remaining - elapsed + clock.increment;
+            if let Some(moves_to_go) = &mut clock.moves_to_go {
+                *moves_to_go -= 1;
+                if *moves_to_go == 0 {
+                    *moves_to_go = time_control.moves_to_go.unwrap_or(1);
+                    clock.remaining += time_control.remaining;
+                }
+            }
+        }
+        let Some(mv) = choice else {break};
+        execute_move(&mut board, mv.clone(), whose_turn);
+        moves.push(mv);
+        if board.get_king_locs(whose_turn.opposite()).is_empty() {
+            return (win_for(whose_turn), moves);
+        }
+        whose_turn = whose_turn.opposite();
+    }
+    (GameOutcome::Draw, moves)
+}
+
+//From the first player's point of view.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct MatchResult {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32
+}
+
+impl MatchResult {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    //Points per game, counting a draw as half a point.
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    //The elo difference that would give this score. A clean sweep is capped at +-800.
+    pub fn elo_difference(&self) -> f64 {
+        let score = self.score().clamp(0.01, 0.99);
+        (-400.0 * (1.0 / score - 1.0).log10()).clamp(-800.0, 800.0)
+    }
+}
+
+//The players swap colors every game, with the first player starting as white.
+pub fn play_match(
+    first: &mut dyn Player,
+    second: &mut dyn Player,
+    games: u32,
+    max_plies: u32,
+    time_control: Option<&TimeControl>,
+    rules: &RuleSet
+) -> MatchResult {
+    let mut result = MatchResult::default();
+    for game in 0..games {
+        let first_is_white = game % 2 == 0;
+        let (outcome, _) = if first_is_white {
+            play_game(first, second, max_plies, time_control, rules)
+        } else {
+            play_game(second, first, max_plies, time_control, rules)
+        };
+        match (outcome, first_is_white) {
+            (GameOutcome::Draw, _) => result.draws += 1,
+            (GameOutcome::WhiteWins, true) | (GameOutcome::BlackWins, false) => result.wins += 1,
+            _ => result.losses += 1
+        }
+    }
+    result
+}
@@ -0,0 +1,102 @@
+use super::datatypes::{
+        board::GameBoard,
+        piece::PlayerColor,
+        moves::MoveData
+    };
+use super::logic::{move_validation, execute_move, captures_king, is_in_check};
+use super::rules::RuleSet;
+
+//Mate-in-N search that only follows forcing lines:
+//the attacker only ever plays moves that take the king or threaten to take it next move,
+//while every reply of the defender is considered.
+//Games are played by capture-the-king rules, so mate means the king actually gets taken,
+//and N counts the attacker's moves, including the capture.
+//A defender with no legal moves at all is not mated, so such lines don't count.
+
+fn forcing_moves(board: &GameBoard, attacker: PlayerColor, rules: &RuleSet) -> Vec<(MoveData, GameBoard)> {
+    move_validation::get_all_legal_moves_for_color(board, attacker, rules).into_iter()
+        .filter_map(|mv| {
+            let mut child = board.clone();
+            execute_move(&mut child, mv.clone(), attacker);
+            if is_in_check(&child, attacker.opposite(), rules) {Some((mv, child))} else {None}
+        }).collect()
+}
+
+fn forces_mate(board: &GameBoard, attacker: PlayerColor, moves_left: u32, rules: &RuleSet) -> bool {
+    let moves = move_validation::get_all_legal_moves_for_color(board, attacker, rules);
+    if moves.iter().any(|mv| captures_king(board, mv, attacker)) {
+        return true;
+    }
+    if moves_left <= 1 {
+        return false;
+    }
+    forcing_moves(board, attacker, rules).iter()
+        .any(|(_, child)| defender_is_lost(child, attacker, moves_left - 1, rules))
+}
+
+fn defender_is_lost(board: &GameBoard, attacker: PlayerColor, moves_left: u32, rules: &RuleSet) -> bool {
+    let defender = attacker.opposite();
+    let replies = move_validation::get_all_legal_moves_for_color(board, defender, rules);
+    if replies.is_empty() {
+        return false;
+    }
+    for reply in replies {
+        if captures_king(board, &reply, defender) {
+            return false;
+        }
+        let mut child = board.clone();
+        execute_move(&mut child, reply, defender);
+        if !forces_mate(&child, attacker, moves_left, rules) {
+            return false;
+        }
+    }
+    true
+}
+
+//The smallest number of attacker moves that forces mate, if it's at most `max_moves`.
+pub fn mate_distance(board: &GameBoard, attacker: PlayerColor, max_moves: u32, rules: &RuleSet) -> Option<u32> {
+    (1..=max_moves).find(|&n| forces_mate(board, attacker, n, rules))
+}
+
+//Every first move that forces mate within `max_moves` moves, with its mate distance.
+pub fn mating_moves(board: &GameBoard, attacker: PlayerColor, max_moves: u32, rules: &RuleSet) -> Vec<(MoveData, u32)> {
+    let mut out = vec![];
+    for mv in move_validation::get_all_legal_moves_for_color(board, attacker, rules) {
+        if captures_king(board, &mv, attacker) {
+            out.push((mv, 1));
+            continue;
+        }
+        let mut child = board.clone();
+        execute_move(&mut child, mv.clone(), attacker);
+        if !is_in_check(&child, attacker.opposite(), rules) {continue}
+        if let Some(n) = (1..max_moves).find(|&n| defender_is_lost(&child, attacker, n, rules)) {
+            out.push((mv, n + 1));
+        }
+    }
+    out
+}
+
+//The shortest forced mate within `max_moves` moves, as a line of moves for both sides.
+//The defender always plays the reply that holds out the longest.
+pub fn find_mate(board: &GameBoard, attacker: PlayerColor, max_moves: u32, rules: &RuleSet) -> Option<Vec<MoveData>> {
+    let (first, distance) = mating_moves(board, attacker, max_moves, rules).into_iter()
+        .min_by_key(|(_, distance)| *distance)?;
+    let mut line = vec![first.clone()];
+    if distance == 1 {
+        return Some(line);
+    }
+    let mut board = board.clone();
+    execute_move(&mut board, first, attacker);
+    let defender = attacker.opposite();
+    let (reply, child, n) = move_validation::get_all_legal_moves_for_color(&board, defender, rules).into_iter()
+        .filter_map(|reply| {
+            let mut child = board.clone();
+            execute_move(&mut child, reply.clone(), defender);
+            let n = mate_distance(&child, attacker, distance - 1, rules)?;
+            Some((reply, child, n))
+        })
+        .max_by_key(|(_, _, n)| *n)?;
+    line.push(reply);
+    line.extend(find_mate(&child, attacker, n, rules)?);
+    Some(line)
+}
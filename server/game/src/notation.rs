@@ -0,0 +1,190 @@
+use std::fmt;
+
+use super::datatypes::{
+        board::{GameBoard, Square,
+            rankfile::{Rankfile, Geometry, MAX_BOARD_SIZE}
+        },
+        piece::{UltimaPiece, UltimaPieceType, PlayerColor},
+        moves::{MoveData, MoveKind}
+    };
+use super::logic::move_validation;
+use super::rules::RuleSet;
+
+//Squares are written lowercase, e.g. "d2".
+pub fn square_to_string(rf: Rankfile) -> String {
+    let (r, f) = rf.to_strings();
+    f.to_lowercase() + &r
+}
+
+//Ranks past the ninth take two digits, e.g. "b10" on a larger board.
+pub fn parse_square(s: &str) -> Option<Rankfile> {
+    let f = s.chars().next()?;
+    let r = &s[f.len_utf8()..];
+    Rankfile::from_strings(r.to_string(), f.to_string())
+}
+
+//Moves are written as start-end, followed by each captured square: e.g. "d2-d5xd6xe6".
+//`board` is the position the move is played in.
+//A suicide move goes from the piece's square to itself, e.g. "c4-c4".
+//Rococo's swaps are written "d2<>d9", and mutual destruction "i2*j3".
+pub fn move_to_string(board: &GameBoard, mv: &MoveData) -> String {
+    match mv.kind {
+        MoveKind::Swap => return square_to_string(mv.start) + "<>" + &square_to_string(mv.end),
+        MoveKind::MutualDestruction => return square_to_string(mv.start) + "*" + &square_to_string(mv.end),
+        MoveKind::Normal | MoveKind::Suicide => {}
+    }
+    let mut out = square_to_string(mv.start) + "-" + &square_to_string(mv.end);
+    for capture in &mv.capture_details {
+        debug_assert!(board.get_square(capture.square) == Some(capture.piece), "the move belongs to another position");
+        out += "x";
+        out += &square_to_string(capture.square);
+    }
+    out
+}
+
+//What a move takes and by which rule, e.g. "pawn on d6 (withdrawal), king on e6 (chameleon as king)".
+//Empty if it doesn't capture anything.
+pub fn describe_captures(mv: &MoveData) -> String {
+    let described: Vec<String> = mv.capture_details.iter().map(|capture| {
+        format!("{} on {} ({})", capture.piece.piece_type.name(), square_to_string(capture.square), capture.kind)
+    }).collect();
+    described.join(", ")
+}
+
+//Looks the move up among the legal moves, so the captures don't have to be written out.
+//If they are, they have to match.
+pub fn parse_move(board: &GameBoard, whose_turn: PlayerColor, s: &str, rules: &RuleSet) -> Option<MoveData> {
+    let mut parts = s.trim().split('x');
+    let (start, end) = parts.next()?.split_once('-')?;
+    let mv = move_validation::find_legal_move(board, whose_turn, parse_square(start)?, parse_square(end)?, rules)?;
+    let written: Vec<&str> = parts.collect();
+    if !written.is_empty() {
+        let mut expected: Vec<String> = move_to_string(board, &mv).split('x').skip(1).map(String::from).collect();
+        let mut written: Vec<String> = written.iter().map(|s| s.to_lowercase()).collect();
+        expected.sort();
+        written.sort();
+        if expected != written {
+            return None;
+        }
+    }
+    Some(mv)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FenError {
+    WrongNumberOfFields,
+    WrongNumberOfRanks,
+    BadRankLength(usize),
+    BadPiece(char),
+    BadSideToMove(String)
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::WrongNumberOfFields => write!(f, "expected a board and a side to move"),
+            FenError::WrongNumberOfRanks => write!(f, "wrong number of ranks"),
+            FenError::BadRankLength(rank) => write!(f, "rank {rank} has the wrong number of squares"),
+            FenError::BadPiece(c) => write!(f, "unknown piece {c:?}"),
+            FenError::BadSideToMove(s) => write!(f, "side to move should be w or b, not {s:?}")
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+//FEN as in chess, using the cli's piece letters (uppercase white, lowercase black),
+//followed by the side to move: e.g. "olcwkcli/pppppppp/8/8/8/8/PPPPPPPP/ILCKWCLO w".
+//Other board shapes just have more or fewer ranks, or longer or shorter ones: the first rank sets the width.
+pub fn board_to_fen(board: &GameBoard, whose_turn: PlayerColor) -> String {
+    let mut out = String::new();
+    let geometry = board.geometry();
+    for r in (0..geometry.ranks()).rev() {
+        let mut empty = 0;
+        for f in 0..geometry.files() {
+            match board.get_square_from_coords(r, f) {
+                None => empty += 1,
+                Some(piece) => {
+                    if empty > 0 {
+                        out += &empty.to_string();
+                        empty = 0;
+                    }
+                    let c = piece.piece_type.to_char();
+                    out.push(match piece.color {
+                        PlayerColor::White => c,
+                        PlayerColor::Black => c.to_ascii_lowercase()
+                    });
+                }
+            }
+        }
+        if empty > 0 {
+            out += &empty.to_string();
+        }
+        if r > 0 {
+            out.push('/');
+        }
+    }
+    out += match whose_turn {
+        PlayerColor::White => " w",
+        PlayerColor::Black => " b"
+    };
+    out
+}
+
+pub fn board_from_fen(fen: &str) -> Result<(GameBoard, PlayerColor), FenError> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    let [placement, side] = fields[..] else {
+        return Err(FenError::WrongNumberOfFields);
+    };
+    let ranks: Vec<&str> = placement.split('/').collect();
+    let mut squares: Vec<Vec<Square>> = vec![];
+    for (i, rank) in ranks.iter().enumerate() {
+        let rank_number = ranks.len() - i;
+        let mut row = vec![];
+        let mut empty = 0;
+        for c in rank.chars() {
+            if let Some(digit) = c.to_digit(10) {
+                //Runs of digits are one number, for the wider boards.
+                empty = empty * 10 + digit as usize;
+                if empty > MAX_BOARD_SIZE as usize {
+                    return Err(FenError::BadRankLength(rank_number));
+                }
+                continue;
+            }
+            row.extend((0..empty).map(|_| None));
+            empty = 0;
+            let piece_type = UltimaPieceType::from_char(c).ok_or(FenError::BadPiece(c))?;
+            let color = if c.is_ascii_uppercase() {PlayerColor::White} else {PlayerColor::Black};
+            row.push(Some(UltimaPiece {piece_type, color}));
+        }
+        row.extend((0..empty).map(|_| None));
+        if squares.first().is_some_and(|first| first.len() != row.len()) {
+            return Err(FenError::BadRankLength(rank_number));
+        }
+        squares.push(row);
+    }
+    let size = 2..=MAX_BOARD_SIZE as usize;
+    if !size.contains(&ranks.len()) {
+        return Err(FenError::WrongNumberOfRanks);
+    }
+    let files = squares[0].len();
+    if !size.contains(&files) {
+        return Err(FenError::BadRankLength(ranks.len()));
+    }
+    let geometry = Geometry::new(ranks.len() as i8, files as i8).expect("size was checked");
+    let mut board = GameBoard::new_empty_with_geometry(geometry);
+    for (i, row) in squares.into_iter().enumerate() {
+        let r = geometry.ranks() - 1 - i as i8;
+        for (f, square) in row.into_iter().enumerate() {
+            if let (Some(piece), Some(rf)) = (square, geometry.rankfile(r, f as i8)) {
+                board.place_piece(rf, piece);
+            }
+        }
+    }
+    let whose_turn = match side {
+        "w" | "W" => PlayerColor::White,
+        "b" | "B" => PlayerColor::Black,
+        _ => return Err(FenError::BadSideToMove(side.to_string()))
+    };
+    Ok((board, whose_turn))
+}
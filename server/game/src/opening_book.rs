@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use super::datatypes::{
+        board::{GameBoard,
+            rankfile::Rankfile
+        },
+        piece::PlayerColor,
+        moves::MoveData
+    };
+use super::logic::{move_validation, execute_move};
+use super::evaluation::EvaluationBackend;
+use super::zobrist::zobrist_hash;
+use super::rng::Rng;
+use super::rules::RuleSet;
+use super::game_record::GameRecord;
+
+//A book is only meaningful under the rules it was built with, so probe it under those too.
+//Books only cover the standard board: each square is stored in a byte, as rank * 8 + file.
+
+//Book moves only store the start and end squares.
+//Captures are rebuilt from the position when probing, which also guards against hash collisions:
+//a book move that isn't legal in the probed position is never played.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct BookMove {
+    pub start: Rankfile,
+    pub end: Rankfile,
+    pub weight: u32
+}
+
+#[derive(Debug)]
+pub enum BookLoadError {
+    Io(std::io::Error),
+    BadMagic,
+    UnsupportedVersion(u32),
+    UnexpectedEof,
+    BadSquare(u8)
+}
+
+impl fmt::Display for BookLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookLoadError::Io(e) => write!(f, "couldn't read book file: {e}"),
+            BookLoadError::BadMagic => write!(f, "not an ultima opening book"),
+            BookLoadError::UnsupportedVersion(v) => write!(f, "unsupported book version {v}"),
+            BookLoadError::UnexpectedEof => write!(f, "book file is truncated"),
+            BookLoadError::BadSquare(sq) => write!(f, "book file contains invalid square {sq}")
+        }
+    }
+}
+
+impl std::error::Error for BookLoadError {}
+
+impl From<std::io::Error> for BookLoadError {
+    fn from(e: std::io::Error) -> Self {
+        BookLoadError::Io(e)
+    }
+}
+
+//Settings for `OpeningBook::from_search_tree`.
+pub struct SearchTreeBookSettings {
+    //How many plies deep the book goes.
+    pub book_plies: u32,
+    //How deep each candidate move is searched to score it.
+    pub search_depth: u32,
+    //Moves scoring worse than the best move by more than this are left out of the book.
+    //A negative margin counts as 0, which keeps only the moves tied for best.
+    pub margin: i32,
+    //Keeps the tree from exploding in quiet positions where many moves score the same.
+    pub max_moves_per_position: usize
+}
+
+impl Default for SearchTreeBookSettings {
+    fn default() -> Self {
+        SearchTreeBookSettings {
+            book_plies: 4,
+            search_depth: 2,
+            margin: 50,
+            max_moves_per_position: 4
+        }
+    }
+}
+
+const MAGIC: &[u8; 4] = b"ULBK";
+const VERSION: u32 = 1;
+
+fn fits_in_a_byte(rf: Rankfile) -> bool {
+    let (r, f) = rf.to_unsigned_coords();
+    r < 8 && f < 8
+}
+
+fn square_to_byte(rf: Rankfile) -> u8 {
+    let (r, f) = rf.to_unsigned_coords();
+    (r * 8 + f) as u8
+}
+
+fn byte_to_square(b: u8) -> Result<Rankfile, BookLoadError> {
+    Rankfile::from((b / 8) as i8, (b % 8) as i8).ok_or(BookLoadError::BadSquare(b))
+}
+
+//Plain fixed-depth negamax, just enough to rank candidate book moves.
+fn negamax(board: &GameBoard, whose_turn: PlayerColor, depth: u32, rules: &RuleSet, backend: &EvaluationBackend) -> i32 {
+    if depth == 0 
+        || board.get_king_locs(whose_turn).is_empty() 
+        || board.get_king_locs(whose_turn.opposite()).is_empty() 
+    {
+        return backend.evaluate(board, whose_turn);
+    }
+    let moves = move_validation::get_all_legal_moves_for_color(board, whose_turn, rules);
+    if moves.is_empty() {
+        return backend.evaluate(board, whose_turn);
+    }
+    let mut best = i32::MIN;
+    for mv in moves {
+        let mut child = board.clone();
+        execute_move(&mut child, mv, whose_turn);
+        best = best.max(-negamax(&child, whose_turn.opposite(), depth - 1, rules, backend));
+    }
+    best
+}
+
+#[derive(Clone, Default)]
+pub struct OpeningBook {
+    entries: HashMap<u64, Vec<BookMove>>
+}
+
+impl OpeningBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    //Adding a move that's already in the book for this position adds to its weight.
+    //Moves with squares off the standard board are left out.
+    pub fn add_move(&mut self, hash: u64, start: Rankfile, end: Rankfile, weight: u32) {
+        if !fits_in_a_byte(start) || !fits_in_a_byte(end) {
+            return;
+        }
+        let moves = self.entries.entry(hash).or_default();
+        for book_move in moves.iter_mut() {
+            if book_move.start == start && book_move.end == end {
+                book_move.weight = book_move.weight.saturating_add(weight);
+                return;
+            }
+        }
+        moves.push(BookMove {start, end, weight});
+    }
+
+    //Nothing for boards other than the standard one.
+    pub fn moves_for(&self, board: &GameBoard, whose_turn: PlayerColor) -> &[BookMove] {
+        if !board.geometry().is_standard() {
+            return &[];
+        }
+        match self.entries.get(&zobrist_hash(board, whose_turn)) {
+            Some(moves) => moves,
+            None => &[]
+        }
+    }
+
+    //Picks one of the book moves for this position at random, in proportion to the moves' weights.
+    pub fn probe(&self, board: &GameBoard, whose_turn: PlayerColor, rules: &RuleSet, rng: &mut Rng) -> Option<MoveData> {
+        let candidates: Vec<(MoveData, u32)> = self.moves_for(board, whose_turn).iter()
+            .filter(|book_move| book_move.weight > 0)
+            .filter_map(|book_move| {
+                let mv = move_validation::find_legal_move(board, whose_turn, book_move.start, book_move.end, rules)?;
+                Some((mv, book_move.weight))
+            }).collect();
+        let total: u64 = candidates.iter().map(|(_, weight)| *weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rng.below(total);
+        for (mv, weight) in candidates {
+            if pick < weight as u64 {
+                return Some(mv);
+            }
+            pick -= weight as u64;
+        }
+        None
+    }
+
+    //Every game is played out from its record's start position, and each move played counts once towards its weight,
+    //so moves that are played more often are also picked more often.
+    //Games played under other rules, or on another board, are skipped. A game stops contributing at its first illegal move.
+    pub fn from_games(games: &[GameRecord], max_plies: usize, rules: &RuleSet) -> Self {
+        let mut book = Self::new();
+        for game in games {
+            let mut board = game.start_position().board();
+            if game.rules() != *rules || !board.geometry().is_standard() {
+                continue;
+            }
+            let mut whose_turn = PlayerColor::White;
+            for recorded in game.moves.iter().take(max_plies) {
+                let Some(mv) = move_validation::find_legal_move(&board, whose_turn, recorded.start, recorded.end, rules) else {break};
+                book.add_move(zobrist_hash(&board, whose_turn), mv.start, mv.end, 1);
+                execute_move(&mut board, mv, whose_turn);
+                whose_turn = whose_turn.opposite();
+            }
+        }
+        book
+    }
+
+    //Scores every move with a shallow search, and keeps the ones close enough to the best,
+    //weighting them by how close they are. Empty for boards other than the standard one.
+    pub fn from_search_tree(board: &GameBoard, whose_turn: PlayerColor, settings: &SearchTreeBookSettings, rules: &RuleSet, backend: &EvaluationBackend) -> Self {
+        let mut book = Self::new();
+        if board.geometry().is_standard() {
+            book.extend_from_search_tree(board, whose_turn, settings.book_plies, settings, rules, backend);
+        }
+        book
+    }
+
+    fn extend_from_search_tree(&mut self, board: &GameBoard, whose_turn: PlayerColor, plies_left: u32, settings: &SearchTreeBookSettings, rules: &RuleSet, backend: &EvaluationBackend) {
+        if plies_left == 0 {
+            return;
+        }
+        let hash = zobrist_hash(board, whose_turn);
+        if self.entries.contains_key(&hash) {
+            return; //transposition, already expanded.
+        }
+        let mut scored: Vec<(MoveData, GameBoard, i32)> = move_validation::get_all_legal_moves_for_color(board, whose_turn, rules)
+            .into_iter().map(|mv| {
+                let mut child = board.clone();
+                execute_move(&mut child, mv.clone(), whose_turn);
+                let score = -negamax(&child, whose_turn.opposite(), settings.search_depth.saturating_sub(1), rules, backend);
+                (mv, child, score)
+            }).collect();
+        let Some(best) = scored.iter().map(|(_, _, score)| *score).max() else {return};
+        let margin = settings.margin.max(0);
+        scored.retain(|(_, _, score)| best - score <= margin);
+        scored.sort_by_key(|(_, _, score)| -score);
+        scored.truncate(settings.max_moves_per_position);
+        for (mv, child, score) in scored {
+            let weight = (margin - (best - score)) as u32 + 1;
+            self.add_move(hash, mv.start, mv.end, weight);
+            if child.get_king_locs(whose_turn.opposite()).is_empty() {
+                continue;
+            }
+            self.extend_from_search_tree(&child, whose_turn.opposite(), plies_left - 1, settings, rules, backend);
+        }
+    }
+
+    //Book file layout (all little-endian):
+    //  b"ULBK", version: u32, number of moves: u32,
+    //  then for each move: hash: u64, start: u8, end: u8, weight: u32
+    //  where squares are rank * 8 + file on the standard board.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let count: usize = self.entries.values().map(|moves| moves.len()).sum();
+        let mut out = Vec::with_capacity(12 + count * 14);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&(count as u32).to_le_bytes());
+        for (hash, moves) in &self.entries {
+            for book_move in moves {
+                out.extend_from_slice(&hash.to_le_bytes());
+                out.push(square_to_byte(book_move.start));
+                out.push(square_to_byte(book_move.end));
+                out.extend_from_slice(&book_move.weight.to_le_bytes());
+            }
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BookLoadError> {
+        fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Result<&'a [u8], BookLoadError> {
+            if bytes.len() < n {
+                return Err(BookLoadError::UnexpectedEof);
+            }
+            let (head, tail) = bytes.split_at(n);
+            *bytes = tail;
+            Ok(head)
+        }
+        let mut bytes = bytes;
+        if take(&mut bytes, 4)? != MAGIC {
+            return Err(BookLoadError::BadMagic);
+        }
+        let version = u32::from_le_bytes(take(&mut bytes, 4)?.try_into().unwrap());
+        if version != VERSION {
+            return Err(BookLoadError::UnsupportedVersion(version));
+        }
+        let count = u32::from_le_bytes(take(&mut bytes, 4)?.try_into().unwrap());
+        let mut book = Self::new();
+        for _ in 0..count {
+            let hash = u64::from_le_bytes(take(&mut bytes, 8)?.try_into().unwrap());
+            let squares = take(&mut bytes, 2)?;
+            let (start, end) = (byte_to_square(squares[0])?, byte_to_square(squares[1])?);
+            let weight = u32::from_le_bytes(take(&mut bytes, 4)?.try_into().unwrap());
+            book.add_move(hash, start, end, weight);
+        }
+        Ok(book)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BookLoadError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+}
@@ -0,0 +1,470 @@
+use std::fmt;
+
+use super::datatypes::{
+        board::{GameBoard,
+            rankfile::Rankfile
+        },
+        piece::{UltimaPiece, UltimaPieceType, PlayerColor},
+        moves::{MoveData, MoveKind, Capture, CaptureKind}
+    };
+use super::logic::move_validation;
+use super::pieces::PieceSet;
+use super::rules::RuleSet;
+
+//A move in 32 bits, for transposition tables and move generation that doesn't allocate.
+//
+//  bits 0-5    start square, rank * 8 + file
+//  bits 6-11   end square
+//  bits 12-19  custodian captures: one bit per square next to the end, in `NEIGHBOURS` order
+//  bits 20-25  leap captures: one bit per square between start and end, nearest the start first
+//  bit 26      withdrawal: the square behind the start, away from the end
+//  bit 27      coordination on the end's rank and the king's file
+//  bit 28      coordination on the king's rank and the end's file
+//  bit 29      displacement: the piece on the end square
+//  bit 30      suicide
+//
+//Which pieces are taken, and how, comes back from the position the move is played in: see `to_move`.
+//Only boards up to 8x8 fit, with at most one king a side: see `fits`.
+
+const NEIGHBOURS: [(i8, i8); 8] = [(1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (-1, 1), (0, 1), (1, 1)];
+
+const SQUARE_BITS: u32 = 6;
+const SQUARE_MASK: u32 = (1 << SQUARE_BITS) - 1;
+const CUSTODIAN_SHIFT: u32 = 12;
+const LEAP_SHIFT: u32 = 20;
+const WITHDRAWAL: u32 = 1 << 26;
+const COORDINATION_RANK: u32 = 1 << 27;
+const COORDINATION_FILE: u32 = 1 << 28;
+const DISPLACEMENT: u32 = 1 << 29;
+const SUICIDE: u32 = 1 << 30;
+
+//At most 27 destinations for each piece, plus a suicide.
+pub const MAX_PACKED_MOVES: usize = 64 * 28;
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct PackedMove(u32);
+
+impl PackedMove {
+    //Coordination bits name a square by the end's rank or file and the king's, so with two kings
+    //the same bits would mean different captures. Boards like that don't fit, any more than big ones.
+    pub fn fits(board: &GameBoard) -> bool {
+        let geometry = board.geometry();
+        geometry.ranks() <= 8 && geometry.files() <= 8
+            && [PlayerColor::White, PlayerColor::Black].iter().all(|&color| board.get_king_locs(color).len() <= 1)
+    }
+
+    fn new(start: Rankfile, end: Rankfile) -> Self {
+        PackedMove(square_index(start) | (square_index(end) << SQUARE_BITS))
+    }
+
+    pub fn from_bits(bits: u32) -> Self {
+        PackedMove(bits)
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub fn start(&self) -> Rankfile {
+        square_at(self.0 & SQUARE_MASK)
+    }
+
+    pub fn end(&self) -> Rankfile {
+        square_at((self.0 >> SQUARE_BITS) & SQUARE_MASK)
+    }
+
+    pub fn is_suicide(&self) -> bool {
+        self.0 & SUICIDE != 0
+    }
+
+    //Whether the move takes anything.
+    pub fn is_capture(&self) -> bool {
+        self.0 & !(SQUARE_MASK | (SQUARE_MASK << SQUARE_BITS) | SUICIDE) != 0
+    }
+
+    //The squares this move captures on in `board`, each with the rule it's captured by
+    //as it would be for a piece that isn't a chameleon.
+    fn capture_squares<'board>(self, board: &'board GameBoard) -> impl Iterator<Item = (Rankfile, CaptureKind)> + 'board {
+        let (start_r, start_f) = self.start().to_signed_coords();
+        let (end_r, end_f) = self.end().to_signed_coords();
+        let dir = ((end_r - start_r).signum(), (end_f - start_f).signum());
+        let color = board.get_square(self.start()).map(|p| p.color);
+        let king = color.and_then(|color| only_king(board, color)).map(|king| king.to_signed_coords());
+
+        let custodian = NEIGHBOURS.iter().enumerate()
+            .filter(move |&(i, _)| (self.0 >> (CUSTODIAN_SHIFT + i as u32)) & 1 != 0)
+            .map(move |(_, (dr, df))| ((end_r + dr, end_f + df), CaptureKind::Custodian));
+        let leaps = (0..6)
+            .filter(move |&i| (self.0 >> (LEAP_SHIFT + i)) & 1 != 0)
+            .map(move |i| {
+                let distance = i as i8 + 1;
+                ((start_r + dir.0 * distance, start_f + dir.1 * distance), CaptureKind::Leap)
+            });
+        let withdrawal = (self.0 & WITHDRAWAL != 0)
+            .then_some(((start_r - dir.0, start_f - dir.1), CaptureKind::Withdrawal));
+        let coordination_rank = king.filter(|_| self.0 & COORDINATION_RANK != 0)
+            .map(|(_, king_f)| ((end_r, king_f), CaptureKind::Coordination));
+        let coordination_file = king.filter(|_| self.0 & COORDINATION_FILE != 0)
+            .map(|(king_r, _)| ((king_r, end_f), CaptureKind::Coordination));
+        let displacement = (self.0 & DISPLACEMENT != 0)
+            .then_some(((end_r, end_f), CaptureKind::KingDisplacement));
+
+        custodian.chain(leaps).chain(withdrawal).chain(coordination_rank).chain(coordination_file).chain(displacement)
+            .filter_map(|((r, f), kind)| Some((board.rankfile(r, f)?, kind)))
+    }
+
+    //The squares of the pieces this move takes in `board`, without allocating. `board` should `fit`.
+    pub fn captures<'board>(self, board: &'board GameBoard) -> impl Iterator<Item = Rankfile> + 'board {
+        self.capture_squares(board).map(|(square, _)| square)
+    }
+
+    //The full move in `board`. `captures` lists only the pieces actually taken.
+    //None if the board doesn't fit, there's no piece on the start square, or a capture square is empty.
+    pub fn to_move(self, board: &GameBoard) -> Option<MoveData> {
+        if !Self::fits(board) {
+            return None;
+        }
+        let piece = board.get_square(self.start())?;
+        if self.is_suicide() {
+            return Some(MoveData::suicide(piece, self.start()));
+        }
+        let mut mv = MoveData::new(piece, self.start(), self.end());
+        for (square, kind) in self.capture_squares(board) {
+            let captured = board.get_square(square)?;
+            let kind = if piece.piece_type == UltimaPieceType::Chameleon {
+                CaptureKind::ChameleonAs(imitated(kind))
+            } else {
+                kind
+            };
+            mv.capture_details.push(Capture {square, piece: captured, kind});
+        }
+        Some(mv)
+    }
+
+    //Packs a move from `move_validation`, played in `board`.
+    //None if the board doesn't fit, or a capture isn't one the encoding has room for,
+    //such as a king's coordination under `RuleSet::king_coordinates`.
+    pub fn from_move(mv: &MoveData, board: &GameBoard) -> Option<Self> {
+        if !Self::fits(board) {
+            return None;
+        }
+        let mut packed = PackedMove::new(mv.start, mv.end);
+        match mv.kind {
+            MoveKind::Normal => {},
+            MoveKind::Suicide => {
+                packed.0 |= SUICIDE;
+                return Some(packed);
+            },
+            MoveKind::Swap | MoveKind::MutualDestruction => return None
+        }
+        let (start_r, start_f) = mv.start.to_signed_coords();
+        let (end_r, end_f) = mv.end.to_signed_coords();
+        let (dr, df) = (end_r - start_r, end_f - start_f);
+        let dir = (dr.signum(), df.signum());
+        let king = only_king(board, mv.piece.color).map(|king| king.to_signed_coords());
+        for capture in &mv.capture_details {
+            let (r, f) = capture.square.to_signed_coords();
+            let kind = match capture.kind {
+                CaptureKind::ChameleonAs(piece_type) => imitated_kind(piece_type)?,
+                kind => kind
+            };
+            packed.0 |= match kind {
+                CaptureKind::Custodian => {
+                    let i = NEIGHBOURS.iter().position(|&(nr, nf)| (end_r + nr, end_f + nf) == (r, f))?;
+                    1 << (CUSTODIAN_SHIFT + i as u32)
+                },
+                CaptureKind::Leap => {
+                    let straight = dr == 0 || df == 0 || dr.abs() == df.abs();
+                    let distance = (r - start_r).abs().max((f - start_f).abs());
+                    let on_path = (start_r + dir.0 * distance, start_f + dir.1 * distance) == (r, f);
+                    if !straight || !on_path || distance < 1 || distance >= dr.abs().max(df.abs()) {
+                        return None;
+                    }
+                    1 << (LEAP_SHIFT + distance as u32 - 1)
+                },
+                CaptureKind::Withdrawal if (start_r - dir.0, start_f - dir.1) == (r, f) => WITHDRAWAL,
+                CaptureKind::Coordination if mv.piece.piece_type == UltimaPieceType::King => return None,
+                CaptureKind::Coordination if king.is_some_and(|(_, king_f)| (end_r, king_f) == (r, f)) => COORDINATION_RANK,
+                CaptureKind::Coordination if king.is_some_and(|(king_r, _)| (king_r, end_f) == (r, f)) => COORDINATION_FILE,
+                CaptureKind::KingDisplacement if mv.end == capture.square => DISPLACEMENT,
+                _ => return None
+            };
+        }
+        Some(packed)
+    }
+}
+
+impl fmt::Debug for PackedMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PackedMove({:?} -> {:?}, {:#010x})", self.start().to_signed_coords(), self.end().to_signed_coords(), self.0)
+    }
+}
+
+fn square_index(rf: Rankfile) -> u32 {
+    let (r, f) = rf.to_unsigned_coords();
+    (r * 8 + f) as u32
+}
+
+fn square_at(index: u32) -> Rankfile {
+    Rankfile::from((index / 8) as i8, (index % 8) as i8).expect("six bits is always a square")
+}
+
+//The side's king, on a board that `fits`.
+fn only_king(board: &GameBoard, color: PlayerColor) -> Option<Rankfile> {
+    match board.get_king_locs(color)[..] {
+        [king] => Some(king),
+        _ => None
+    }
+}
+
+//The piece a chameleon imitates to capture by `kind`.
+fn imitated(kind: CaptureKind) -> UltimaPieceType {
+    match kind {
+        CaptureKind::Custodian => UltimaPieceType::Pawn,
+        CaptureKind::Leap => UltimaPieceType::Longleaper,
+        CaptureKind::Withdrawal => UltimaPieceType::Withdrawer,
+        CaptureKind::Coordination => UltimaPieceType::Coordinator,
+        CaptureKind::KingDisplacement | CaptureKind::ChameleonAs(_) => UltimaPieceType::King,
+        CaptureKind::Approach | CaptureKind::MutualDestruction => unreachable!("{kind} isn't packed")
+    }
+}
+
+fn imitated_kind(piece_type: UltimaPieceType) -> Option<CaptureKind> {
+    match piece_type {
+        UltimaPieceType::Pawn => Some(CaptureKind::Custodian),
+        UltimaPieceType::Longleaper => Some(CaptureKind::Leap),
+        UltimaPieceType::Withdrawer => Some(CaptureKind::Withdrawal),
+        UltimaPieceType::Coordinator => Some(CaptureKind::Coordination),
+        UltimaPieceType::King => Some(CaptureKind::KingDisplacement),
+        UltimaPieceType::Immobilizer | UltimaPieceType::Chameleon | UltimaPieceType::Custom(_) => None
+    }
+}
+
+//A fixed-size list, so that generating into it never allocates.
+pub struct PackedMoveList {
+    moves: [PackedMove; MAX_PACKED_MOVES],
+    len: usize
+}
+
+impl PackedMoveList {
+    pub fn new() -> Self {
+        PackedMoveList {moves: [PackedMove::default(); MAX_PACKED_MOVES], len: 0}
+    }
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+    //Adds `mv`, or returns false if the list is already full.
+    //The packed generator can't fill it, but a house piece set's moves can.
+    pub fn push(&mut self, mv: PackedMove) -> bool {
+        let Some(slot) = self.moves.get_mut(self.len) else {return false};
+        *slot = mv;
+        self.len += 1;
+        true
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn as_slice(&self) -> &[PackedMove] {
+        &self.moves[..self.len]
+    }
+    pub fn iter(&self) -> impl Iterator<Item = PackedMove> + '_ {
+        self.as_slice().iter().copied()
+    }
+}
+
+impl Default for PackedMoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//Replaces the contents of `list` with every legal move for `color`, in the same order as
+//`move_validation::get_all_legal_moves_for_color`'s starting squares.
+//Only the standard pieces are generated without allocating: a rule set with house pieces or coordinating
+//kings goes through `move_validation` and packs the result.
+//Returns false, leaving `list` empty, if the moves can't all be packed: the board doesn't `fit`, or one of
+//`move_validation`'s moves has no packed form, or there are more than `MAX_PACKED_MOVES` of them.
+pub fn generate_packed_moves(board: &GameBoard, color: PlayerColor, rules: &RuleSet, list: &mut PackedMoveList) -> bool {
+    list.clear();
+    if !PackedMove::fits(board) {
+        return false;
+    }
+    if rules.pieces != PieceSet::ultima() || rules.king_coordinates {
+        for mv in move_validation::get_all_legal_moves_for_color(board, color, rules) {
+            if !PackedMove::from_move(&mv, board).is_some_and(|packed| list.push(packed)) {
+                list.clear();
+                return false;
+            }
+        }
+        return true;
+    }
+    for start in board.squares() {
+        let Some(piece) = board.get_square(start) else {continue};
+        if piece.color == color {
+            generate_piece(board, start, piece, rules, list);
+        }
+    }
+    true
+}
+
+//For `generate_piece`, which stays within `MAX_PACKED_MOVES`: each piece has at most 27 moves and a suicide.
+fn add(list: &mut PackedMoveList, mv: PackedMove) {
+    let added = list.push(mv);
+    debug_assert!(added, "the packed generator made more than MAX_PACKED_MOVES moves");
+}
+
+fn enemy_at(board: &GameBoard, r: i8, f: i8, color: PlayerColor) -> Option<UltimaPiece> {
+    board.get_square_from_coords(r, f).filter(|p| p.color != color)
+}
+
+fn friend_at(board: &GameBoard, r: i8, f: i8, color: PlayerColor) -> bool {
+    board.get_square_from_coords(r, f).is_some_and(|p| p.color == color)
+}
+
+//Enemies a pawn (or, with `only`, a chameleon hunting pawns) would sandwich by stopping on `end`.
+fn custodian_bits(board: &GameBoard, end: Rankfile, color: PlayerColor, rules: &RuleSet, only: Option<UltimaPieceType>) -> u32 {
+    let (r, f) = end.to_signed_coords();
+    let mut bits = 0;
+    for (i, &(dr, df)) in NEIGHBOURS.iter().enumerate() {
+        if !rules.pawns_capture_diagonally && dr != 0 && df != 0 {continue}
+        let Some(enemy) = enemy_at(board, r + dr, f + df, color) else {continue};
+        if only.is_some_and(|piece_type| enemy.piece_type != piece_type) {continue}
+        if friend_at(board, r + 2 * dr, f + 2 * df, color) {
+            bits |= 1 << (CUSTODIAN_SHIFT + i as u32);
+        }
+    }
+    bits
+}
+
+fn coordination_bits(board: &GameBoard, end: Rankfile, color: PlayerColor, only: Option<UltimaPieceType>) -> u32 {
+    let Some(king) = only_king(board, color) else {return 0};
+    let (king_r, king_f) = king.to_signed_coords();
+    let (r, f) = end.to_signed_coords();
+    let takes = |r, f| enemy_at(board, r, f, color).is_some_and(|p| only.is_none_or(|piece_type| p.piece_type == piece_type));
+    let mut bits = 0;
+    if takes(r, king_f) {
+        bits |= COORDINATION_RANK;
+    }
+    if takes(king_r, f) {
+        bits |= COORDINATION_FILE;
+    }
+    bits
+}
+
+fn withdrawal_bit(board: &GameBoard, start: Rankfile, dir: (i8, i8), color: PlayerColor, only: Option<UltimaPieceType>) -> u32 {
+    let (r, f) = start.to_signed_coords();
+    match enemy_at(board, r - dir.0, f - dir.1, color) {
+        Some(p) if only.is_none_or(|piece_type| p.piece_type == piece_type) => WITHDRAWAL,
+        _ => 0
+    }
+}
+
+//Calls `add` with each landing square in `dir` and the leap bits for what was jumped to get there.
+//With `only`, every piece jumped has to be of that type.
+fn leaps(board: &GameBoard, start: Rankfile, dir: (i8, i8), color: PlayerColor, only: Option<UltimaPieceType>, mut add: impl FnMut(Rankfile, u32)) {
+    let (r, f) = start.to_signed_coords();
+    let mut bits = 0;
+    let mut jumping = false;
+    for distance in 1..8 {
+        let Some(rf) = board.rankfile(r + dir.0 * distance, f + dir.1 * distance) else {break};
+        match board.get_square(rf) {
+            None => {
+                jumping = false;
+                add(rf, bits);
+            },
+            Some(p) if p.color == color || jumping => break,
+            Some(p) => {
+                if only.is_some_and(|piece_type| p.piece_type != piece_type) {
+                    break;
+                }
+                jumping = true;
+                bits |= 1 << (LEAP_SHIFT + distance as u32 - 1);
+            }
+        }
+    }
+}
+
+fn generate_piece(board: &GameBoard, start: Rankfile, piece: UltimaPiece, rules: &RuleSet, list: &mut PackedMoveList) {
+    use UltimaPieceType::*;
+    let color = piece.color;
+
+    let mut freezers = move_validation::immobilizers(board, start, piece, rules);
+    if let Some(freezer) = freezers.next() {
+        let only_one = freezers.next().is_none();
+        if piece.piece_type == Chameleon && rules.chameleon_withdraws_from_immobilizer && only_one {
+            let (r, f) = start.to_signed_coords();
+            let (ir, i_f) = freezer.to_signed_coords();
+            for end in board.los(start, (r - ir, f - i_f)) {
+                add(list, PackedMove(PackedMove::new(start, end).0 | WITHDRAWAL));
+            }
+        }
+        if move_validation::can_commit_suicide(piece, rules) {
+            add(list, PackedMove(PackedMove::new(start, start).0 | SUICIDE));
+        }
+        return;
+    }
+
+    for &dir in Rankfile::all_directions() {
+        let orthogonal = dir.0 == 0 || dir.1 == 0;
+        match piece.piece_type {
+            Pawn => if orthogonal {
+                for end in board.los(start, dir) {
+                    add(list, PackedMove(PackedMove::new(start, end).0 | custodian_bits(board, end, color, rules, None)));
+                }
+            },
+            Immobilizer => for end in board.los(start, dir) {
+                add(list, PackedMove::new(start, end));
+            },
+            Coordinator => for end in board.los(start, dir) {
+                add(list, PackedMove(PackedMove::new(start, end).0 | coordination_bits(board, end, color, None)));
+            },
+            Longleaper => leaps(board, start, dir, color, None, |end, bits| {
+                add(list, PackedMove(PackedMove::new(start, end).0 | bits));
+            }),
+            Withdrawer => {
+                let withdrawal = withdrawal_bit(board, start, dir, color, None);
+                for end in board.los(start, dir) {
+                    add(list, PackedMove(PackedMove::new(start, end).0 | withdrawal));
+                }
+            },
+            King => {
+                let (r, f) = start.to_signed_coords();
+                let Some(end) = board.rankfile(r + dir.0, f + dir.1) else {continue};
+                match board.get_square(end) {
+                    None => add(list, PackedMove::new(start, end)),
+                    Some(p) if p.color != color => add(list, PackedMove(PackedMove::new(start, end).0 | DISPLACEMENT)),
+                    Some(_) => {}
+                }
+            },
+            Chameleon => {
+                let withdrawal = withdrawal_bit(board, start, dir, color, Some(Withdrawer));
+                for end in board.los(start, dir) {
+                    let mut bits = withdrawal | coordination_bits(board, end, color, Some(Coordinator));
+                    if orthogonal {
+                        bits |= custodian_bits(board, end, color, rules, Some(Pawn));
+                    }
+                    add(list, PackedMove(PackedMove::new(start, end).0 | bits));
+                }
+                //Plain slides were added above; only leaps that take something land anywhere new.
+                leaps(board, start, dir, color, Some(Longleaper), |end, bits| {
+                    if bits != 0 {
+                        add(list, PackedMove(PackedMove::new(start, end).0 | bits));
+                    }
+                });
+                if rules.chameleon_captures_king {
+                    let (r, f) = start.to_signed_coords();
+                    if let Some(end) = board.rankfile(r + dir.0, f + dir.1) {
+                        if enemy_at(board, r + dir.0, f + dir.1, color).is_some_and(|p| p.piece_type == King) {
+                            add(list, PackedMove(PackedMove::new(start, end).0 | DISPLACEMENT));
+                        }
+                    }
+                }
+            },
+            //Ultima's piece set has nothing registered for house pieces, so they can't move.
+            Custom(_) => {}
+        }
+    }
+}
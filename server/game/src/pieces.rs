@@ -0,0 +1,196 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use super::datatypes::{
+        board::{GameBoard,
+            rankfile::Rankfile
+        },
+        piece::{UltimaPieceType, PlayerColor},
+        moves::MoveData
+    };
+use super::logic::move_validation::piece_checkers::*;
+use super::rules::RuleSet;
+
+//How each kind of piece moves, captures and interacts with immobilizers and chameleons.
+//`move_validation` looks pieces up in the `RuleSet`'s `PieceSet` rather than matching on the piece type,
+//so a house variant can give a piece different powers without touching the move generator:
+//
+//  struct ApproachingWithdrawer;
+//  impl PieceBehavior for ApproachingWithdrawer { ... }
+//  let rules = RuleSet {pieces: PieceSet::ultima().with(&ApproachingWithdrawer), ..RuleSet::default()};
+
+pub trait PieceBehavior: Sync {
+    fn piece_type(&self) -> UltimaPieceType;
+
+    //Tells piece sets apart, so two different behaviours shouldn't share a name.
+    fn name(&self) -> &'static str;
+
+    //Every move from `start`, ignoring immobilization, which `move_validation` handles.
+    //Capturing moves come from `MoveData::capturing`, which records how each piece is taken.
+    fn generate_moves(&self, board: &GameBoard, start: Rankfile, color: PlayerColor, rules: &RuleSet) -> Vec<MoveData>;
+
+    //Only the moves that take something.
+    fn generate_captures(&self, board: &GameBoard, start: Rankfile, color: PlayerColor, rules: &RuleSet) -> Vec<MoveData> {
+        self.generate_moves(board, start, color, rules).into_iter().filter(|mv| !mv.capture_details.is_empty()).collect()
+    }
+
+    //Whether this piece freezes an adjacent enemy of type `target`.
+    fn immobilizes(&self, _target: UltimaPieceType) -> bool {
+        false
+    }
+
+    //Whether a chameleon may capture pieces of this type by moving the way they do.
+    fn chameleon_can_copy(&self, _rules: &RuleSet) -> bool {
+        false
+    }
+}
+
+pub struct PawnBehavior;
+pub struct ImmobilizerBehavior;
+pub struct CoordinatorBehavior;
+pub struct LongleaperBehavior;
+pub struct ChameleonBehavior;
+pub struct WithdrawerBehavior;
+pub struct KingBehavior;
+
+impl PieceBehavior for PawnBehavior {
+    fn piece_type(&self) -> UltimaPieceType {UltimaPieceType::Pawn}
+    fn name(&self) -> &'static str {"pawn"}
+    fn generate_moves(&self, board: &GameBoard, start: Rankfile, color: PlayerColor, rules: &RuleSet) -> Vec<MoveData> {
+        pawn::move_generator_iter(board, start, color, rules).collect()
+    }
+    fn chameleon_can_copy(&self, _rules: &RuleSet) -> bool {true}
+}
+
+impl PieceBehavior for ImmobilizerBehavior {
+    fn piece_type(&self) -> UltimaPieceType {UltimaPieceType::Immobilizer}
+    fn name(&self) -> &'static str {"immobilizer"}
+    fn generate_moves(&self, board: &GameBoard, start: Rankfile, color: PlayerColor, _rules: &RuleSet) -> Vec<MoveData> {
+        immobilizer::move_generator_iter(board, start, color).collect()
+    }
+    fn immobilizes(&self, _target: UltimaPieceType) -> bool {true}
+}
+
+impl PieceBehavior for CoordinatorBehavior {
+    fn piece_type(&self) -> UltimaPieceType {UltimaPieceType::Coordinator}
+    fn name(&self) -> &'static str {"coordinator"}
+    fn generate_moves(&self, board: &GameBoard, start: Rankfile, color: PlayerColor, _rules: &RuleSet) -> Vec<MoveData> {
+        coordinator::move_generator_iter(board, start, color).collect()
+    }
+    fn chameleon_can_copy(&self, _rules: &RuleSet) -> bool {true}
+}
+
+impl PieceBehavior for LongleaperBehavior {
+    fn piece_type(&self) -> UltimaPieceType {UltimaPieceType::Longleaper}
+    fn name(&self) -> &'static str {"longleaper"}
+    fn generate_moves(&self, board: &GameBoard, start: Rankfile, color: PlayerColor, _rules: &RuleSet) -> Vec<MoveData> {
+        longleaper::generate_moves(board, start, color)
+    }
+    fn chameleon_can_copy(&self, _rules: &RuleSet) -> bool {true}
+}
+
+impl PieceBehavior for ChameleonBehavior {
+    fn piece_type(&self) -> UltimaPieceType {UltimaPieceType::Chameleon}
+    fn name(&self) -> &'static str {"chameleon"}
+    fn generate_moves(&self, board: &GameBoard, start: Rankfile, color: PlayerColor, rules: &RuleSet) -> Vec<MoveData> {
+        chameleon::generate_moves(board, start, color, rules)
+    }
+    //A chameleon imitates the immobilizer it's next to.
+    fn immobilizes(&self, target: UltimaPieceType) -> bool {target == UltimaPieceType::Immobilizer}
+}
+
+impl PieceBehavior for WithdrawerBehavior {
+    fn piece_type(&self) -> UltimaPieceType {UltimaPieceType::Withdrawer}
+    fn name(&self) -> &'static str {"withdrawer"}
+    fn generate_moves(&self, board: &GameBoard, start: Rankfile, color: PlayerColor, _rules: &RuleSet) -> Vec<MoveData> {
+        withdrawer::move_generator_iter(board, start, color).collect()
+    }
+    fn chameleon_can_copy(&self, _rules: &RuleSet) -> bool {true}
+}
+
+impl PieceBehavior for KingBehavior {
+    fn piece_type(&self) -> UltimaPieceType {UltimaPieceType::King}
+    fn name(&self) -> &'static str {"king"}
+    fn generate_moves(&self, board: &GameBoard, start: Rankfile, color: PlayerColor, rules: &RuleSet) -> Vec<MoveData> {
+        king::generate_moves_naive(board, start, color, rules)
+    }
+    fn chameleon_can_copy(&self, rules: &RuleSet) -> bool {rules.chameleon_captures_king}
+}
+
+//One behaviour per piece type, Ultima's or custom (`UltimaPieceType::Custom`). Copy, so that `RuleSet` can stay Copy:
+//behaviours are `'static`, which a unit struct like the ones above is for free.
+#[derive(Clone, Copy)]
+pub struct PieceSet {
+    //Indexed by `UltimaPieceType::as_index`. None for the pieces the set doesn't have.
+    behaviors: [Option<&'static dyn PieceBehavior>; UltimaPieceType::COUNT]
+}
+
+impl PieceSet {
+    pub fn ultima() -> Self {
+        let ultima: [&'static dyn PieceBehavior; 7] = [
+            &PawnBehavior,
+            &ImmobilizerBehavior,
+            &CoordinatorBehavior,
+            &LongleaperBehavior,
+            &ChameleonBehavior,
+            &WithdrawerBehavior,
+            &KingBehavior
+        ];
+        ultima.into_iter().fold(PieceSet {behaviors: [None; UltimaPieceType::COUNT]}, PieceSet::with)
+    }
+
+    //None if the set has no such piece, which then can't move.
+    pub fn behavior(&self, piece_type: UltimaPieceType) -> Option<&'static dyn PieceBehavior> {
+        self.behaviors[piece_type.as_index()]
+    }
+
+    //Registers `behavior` for its piece type, replacing the one that was there.
+    //Registering a custom piece type adds it to the set.
+    pub fn with(mut self, behavior: &'static dyn PieceBehavior) -> Self {
+        self.behaviors[behavior.piece_type().as_index()] = Some(behavior);
+        self
+    }
+
+    //Takes `piece_type` out of the set, for variants that don't have it.
+    pub fn without(mut self, piece_type: UltimaPieceType) -> Self {
+        self.behaviors[piece_type.as_index()] = None;
+        self
+    }
+
+    pub fn behaviors(&self) -> impl Iterator<Item = &'static dyn PieceBehavior> + '_ {
+        self.behaviors.iter().flatten().copied()
+    }
+
+    fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.behaviors().map(|behavior| behavior.name())
+    }
+}
+
+impl Default for PieceSet {
+    fn default() -> Self {
+        Self::ultima()
+    }
+}
+
+//Piece sets compare by their behaviours' names, which is what lets `RuleSet` derive its comparisons.
+impl PartialEq for PieceSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.names().eq(other.names())
+    }
+}
+
+impl Eq for PieceSet {}
+
+impl Hash for PieceSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for name in self.names() {
+            name.hash(state);
+        }
+    }
+}
+
+impl fmt::Debug for PieceSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
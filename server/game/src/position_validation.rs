@@ -0,0 +1,135 @@
+use std::fmt;
+
+use super::datatypes::{
+        board::{GameBoard,
+            rankfile::Rankfile
+        },
+        piece::{UltimaPiece, UltimaPieceType, PlayerColor}
+    };
+use super::notation::square_to_string;
+use super::rules::RuleSet;
+use super::setups::StartPosition;
+
+//Checks on positions that come from outside (FENs, records, clients), which the rest of the engine
+//assumes are sane. A position with problems can still be searched, but results may be meaningless.
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum PositionProblemKind {
+    //Every rule set plays with one king a side.
+    MissingKing {color: PlayerColor},
+    //Reported on each king past the first.
+    TooManyKings {color: PlayerColor},
+    //More of this piece than the start position has, reported on each one past `allowed`.
+    TooManyPieces {piece: UltimaPiece, allowed: usize},
+    //The side not to move has its king en prise, so the side to move could just take it.
+    KingCapturable {attackers: Vec<Rankfile>},
+    //A king on the board that `GameBoard::get_king_locs` doesn't know about.
+    KingNotCached,
+    //`GameBoard::get_king_locs` lists this square, but there's no king of that colour on it.
+    CachedKingMissing {color: PlayerColor},
+    DuplicateCachedKing {color: PlayerColor}
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct PositionProblem {
+    //None when there's no square to point at, like a missing king.
+    pub square: Option<Rankfile>,
+    pub kind: PositionProblemKind
+}
+
+impl fmt::Display for PositionProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(square) = self.square {
+            write!(f, "{}: ", square_to_string(square))?;
+        }
+        match &self.kind {
+            PositionProblemKind::MissingKing {color} => write!(f, "{} has no king", color_name(*color)),
+            PositionProblemKind::TooManyKings {color} => write!(f, "{} has more than one king", color_name(*color)),
+            PositionProblemKind::TooManyPieces {piece, allowed} => {
+                write!(f, "{} has more than {allowed} {}s", color_name(piece.color), piece.piece_type.name())
+            },
+            PositionProblemKind::KingCapturable {attackers} => {
+                let attackers: Vec<String> = attackers.iter().map(|&square| square_to_string(square)).collect();
+                write!(f, "the side to move can take this king from {}", attackers.join(", "))
+            },
+            PositionProblemKind::KingNotCached => write!(f, "king missing from the board's king cache"),
+            PositionProblemKind::CachedKingMissing {color} => {
+                write!(f, "the board's king cache has a {} king here, but there isn't one", color_name(*color))
+            },
+            PositionProblemKind::DuplicateCachedKing {color} => {
+                write!(f, "the board's king cache lists this {} king more than once", color_name(*color))
+            }
+        }
+    }
+}
+
+fn color_name(color: PlayerColor) -> &'static str {
+    match color {
+        PlayerColor::White => "white",
+        PlayerColor::Black => "black"
+    }
+}
+
+//How many of a piece each side starts with. Every setup has the same material.
+fn starting_count(piece_type: UltimaPieceType) -> usize {
+    if piece_type == UltimaPieceType::Pawn {
+        return 8;
+    }
+    let (back_rank, _) = StartPosition::default().back_ranks();
+    back_rank.iter().filter(|&&p| p == piece_type).count()
+}
+
+//Everything wrong with the position: king caches first, then material, then kings en prise.
+//Empty if it's fine.
+pub fn validate_position(board: &GameBoard, whose_turn: PlayerColor, rules: &RuleSet) -> Vec<PositionProblem> {
+    let mut problems = vec![];
+    let problem = |square: Option<Rankfile>, kind: PositionProblemKind| PositionProblem {square, kind};
+
+    for color in [PlayerColor::White, PlayerColor::Black] {
+        let cached = board.get_king_locs(color);
+        for (i, &square) in cached.iter().enumerate() {
+            if board.get_square(square) != Some(UltimaPiece {piece_type: UltimaPieceType::King, color}) {
+                problems.push(problem(Some(square), PositionProblemKind::CachedKingMissing {color}));
+            } else if cached[..i].contains(&square) {
+                problems.push(problem(Some(square), PositionProblemKind::DuplicateCachedKing {color}));
+            }
+        }
+    }
+    for square in board.squares() {
+        let Some(piece) = board.get_square(square) else {continue};
+        if piece.piece_type == UltimaPieceType::King && !board.get_king_locs(piece.color).contains(&square) {
+            problems.push(problem(Some(square), PositionProblemKind::KingNotCached));
+        }
+    }
+
+    for color in [PlayerColor::White, PlayerColor::Black] {
+        for piece_type in UltimaPieceType::ALL {
+            let piece = UltimaPiece {piece_type, color};
+            let squares: Vec<Rankfile> = board.squares().filter(|&square| board.get_square(square) == Some(piece)).collect();
+            if piece_type == UltimaPieceType::King {
+                if squares.is_empty() {
+                    problems.push(problem(None, PositionProblemKind::MissingKing {color}));
+                }
+                for &square in squares.iter().skip(1) {
+                    problems.push(problem(Some(square), PositionProblemKind::TooManyKings {color}));
+                }
+                continue;
+            }
+            let allowed = starting_count(piece_type);
+            for &square in squares.iter().skip(allowed) {
+                problems.push(problem(Some(square), PositionProblemKind::TooManyPieces {piece, allowed}));
+            }
+        }
+    }
+
+    let threats = board.threats_against(whose_turn.opposite(), rules);
+    for square in board.squares() {
+        if board.get_square(square) != Some(UltimaPiece {piece_type: UltimaPieceType::King, color: whose_turn.opposite()}) {continue}
+        let Some(threat) = threats.threat_at(square) else {continue};
+        let mut attackers: Vec<Rankfile> = threat.moves.iter().map(|mv| mv.start).collect();
+        attackers.sort_by_key(|square| square.to_unsigned_coords());
+        attackers.dedup();
+        problems.push(problem(Some(square), PositionProblemKind::KingCapturable {attackers}));
+    }
+    problems
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+
+use super::datatypes::{
+        board::GameBoard,
+        piece::{UltimaPieceType, PlayerColor},
+        moves::{MoveData, CaptureKind}
+    };
+use super::logic::{move_validation, execute_move};
+use super::logic::move_validation::is_immobilized;
+use super::mate_search::{mating_moves, find_mate};
+use super::notation::{board_to_fen, move_to_string};
+use super::rules::RuleSet;
+use super::game_record::GameRecord;
+
+//Puzzles are positions with exactly one move that forces mate, mined from finished games.
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum PuzzleTheme {
+    Withdrawal,
+    CoordinatorCrossfire,
+    ImmobilizerLock,
+    LongleaperJump,
+    CustodianCapture,
+    KingDisplacement,
+    ChameleonMimicry
+}
+
+impl PuzzleTheme {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PuzzleTheme::Withdrawal => "withdrawal",
+            PuzzleTheme::CoordinatorCrossfire => "coordinator crossfire",
+            PuzzleTheme::ImmobilizerLock => "immobilizer lock",
+            PuzzleTheme::LongleaperJump => "longleaper jump",
+            PuzzleTheme::CustodianCapture => "custodian capture",
+            PuzzleTheme::KingDisplacement => "king displacement",
+            PuzzleTheme::ChameleonMimicry => "chameleon mimicry"
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Puzzle {
+    pub fen: String,
+    //Both sides' moves, starting with the solver's.
+    pub solution: Vec<String>,
+    pub themes: Vec<PuzzleTheme>
+}
+
+//One puzzle per line: fen; solution moves; themes
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let themes: Vec<&str> = self.themes.iter().map(|t| t.name()).collect();
+        write!(f, "{}; {}; {}", self.fen, self.solution.join(" "), themes.join(", "))
+    }
+}
+
+fn count_immobilized(board: &GameBoard, color: PlayerColor, rules: &RuleSet) -> usize {
+    board.squares().filter(|&rf| {
+        board.get_square(rf).is_some_and(|p| p.color == color && is_immobilized(board, rf, p, rules))
+    }).count()
+}
+
+fn move_themes(board: &GameBoard, mv: &MoveData, whose_turn: PlayerColor, rules: &RuleSet) -> Vec<PuzzleTheme> {
+    let mut themes = vec![];
+    for capture in &mv.capture_details {
+        let theme = match capture.kind {
+            CaptureKind::Withdrawal => PuzzleTheme::Withdrawal,
+            CaptureKind::Coordination => PuzzleTheme::CoordinatorCrossfire,
+            CaptureKind::Leap => PuzzleTheme::LongleaperJump,
+            CaptureKind::Custodian => PuzzleTheme::CustodianCapture,
+            CaptureKind::KingDisplacement => PuzzleTheme::KingDisplacement,
+            CaptureKind::ChameleonAs(_) => PuzzleTheme::ChameleonMimicry,
+            //Rococo's captures; puzzles are Ultima's.
+            CaptureKind::Approach | CaptureKind::MutualDestruction => continue
+        };
+        if !themes.contains(&theme) {
+            themes.push(theme);
+        }
+    }
+    if mv.piece.piece_type == UltimaPieceType::Immobilizer {
+        let mut after = board.clone();
+        execute_move(&mut after, mv.clone(), whose_turn);
+        let opponent = whose_turn.opposite();
+        if count_immobilized(&after, opponent, rules) > count_immobilized(board, opponent, rules) {
+            themes.push(PuzzleTheme::ImmobilizerLock);
+        }
+    }
+    themes
+}
+
+//A puzzle if the side to move has exactly one move that forces mate within `max_moves` moves.
+pub fn puzzle_from_position(board: &GameBoard, whose_turn: PlayerColor, max_moves: u32, rules: &RuleSet) -> Option<Puzzle> {
+    let mating = mating_moves(board, whose_turn, max_moves, rules);
+    if mating.len() != 1 {
+        return None;
+    }
+    let line = find_mate(board, whose_turn, max_moves, rules)?;
+    let mut solution = vec![];
+    let mut themes = vec![];
+    let mut position = board.clone();
+    let mut mover = whose_turn;
+    for mv in line {
+        solution.push(move_to_string(&position, &mv));
+        if mover == whose_turn {
+            for theme in move_themes(&position, &mv, mover, rules) {
+                if !themes.contains(&theme) {
+                    themes.push(theme);
+                }
+            }
+        }
+        execute_move(&mut position, mv, mover);
+        mover = mover.opposite();
+    }
+    Some(Puzzle {
+        fen: board_to_fen(board, whose_turn),
+        solution,
+        themes
+    })
+}
+
+//Plays every game out from its record's start position and checks each position along the way.
+//Puzzles don't say which rules they're for, so games played under other rules than `rules` are skipped.
+//A game stops being mined at its first illegal move.
+pub fn mine_puzzles(games: &[GameRecord], max_moves: u32, rules: &RuleSet) -> Vec<Puzzle> {
+    let mut puzzles = vec![];
+    for game in games {
+        if game.rules() != *rules {
+            continue;
+        }
+        let mut board = game.start_position().board();
+        let mut whose_turn = PlayerColor::White;
+        for recorded in &game.moves {
+            if let Some(puzzle) = puzzle_from_position(&board, whose_turn, max_moves, rules) {
+                if !puzzles.iter().any(|p: &Puzzle| p.fen == puzzle.fen) {
+                    puzzles.push(puzzle);
+                }
+            }
+            let Some(mv) = move_validation::find_legal_move(&board, whose_turn, recorded.start, recorded.end, rules) else {break};
+            execute_move(&mut board, mv, whose_turn);
+            whose_turn = whose_turn.opposite();
+        }
+    }
+    puzzles
+}
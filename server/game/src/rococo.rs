@@ -0,0 +1,204 @@
+use super::datatypes::{
+        board::{GameBoard,
+            rankfile::{Rankfile, Geometry}
+        },
+        piece::{UltimaPiece, UltimaPieceType, PlayerColor},
+        moves::{MoveData, MoveKind, CaptureKind}
+    };
+use super::logic::{move_validation, execute_move, captures_king};
+use super::notation::{move_to_string, board_from_fen, FenError};
+use super::pieces::{PieceBehavior, PieceSet};
+use super::rules::RuleSet;
+
+//Rococo, Ultima's best-known successor. It's played on a 10x10 board: the pieces start on the inner 8x8,
+//and the outer ring of squares can only be entered by a move that captures something.
+//
+//The pieces:
+//  - King, immobilizer, withdrawer, long leaper: as in Ultima. The long leaper may jump several
+//    enemies in a line as long as there's an empty square after each one.
+//  - Chameleon: as in Ultima, imitating every piece below as well, and freezing immobilizers.
+//  - Advancer: moves like a queen and captures by approach, taking the enemy piece on the square
+//    just past where it stops.
+//  - Swapper: moves like a queen, or trades places with the first piece (of either colour) along any line.
+//    It can also take an adjacent enemy by removing itself from the board along with it.
+//  - Cannon pawn: steps one square in any direction, or jumps over an adjacent piece of either colour
+//    to the empty square behind. It captures by sandwiching an enemy against a friendly piece,
+//    orthogonally or diagonally. It doesn't promote.
+//
+//As with Ultima, a game is won by taking the king. Frozen pieces can't move at all.
+//Positions are `GameBoard`s with `ROCOCO_GEOMETRY`, and the rules are Ultima's `move_validation` with
+//the `PieceBehavior`s below swapped in (`rules`). The cannon pawn takes the pawn's letter,
+//and the swapper and advancer are custom pieces. Only the edge rule is Rococo's own (`legal_moves_from`).
+
+pub const ROCOCO_GEOMETRY: Geometry = Geometry::new(10, 10).unwrap();
+
+pub const CANNON_PAWN: UltimaPieceType = UltimaPieceType::Pawn;
+pub const SWAPPER: UltimaPieceType = UltimaPieceType::custom('S').unwrap();
+pub const ADVANCER: UltimaPieceType = UltimaPieceType::custom('A').unwrap();
+
+//Whether the square is on the outer ring.
+pub fn is_edge(square: Rankfile) -> bool {
+    let (r, f) = square.to_signed_coords();
+    let last = ROCOCO_GEOMETRY.ranks() - 1;
+    r == 0 || f == 0 || r == last || f == last
+}
+
+//White's pieces on the inner ring's first rank, b2 to i2. Black's mirror them on rank 9,
+//so the kings face each other on the f-file. Cannon pawns fill the rank in front.
+const BACK_RANK: [UltimaPieceType; 8] = [
+    UltimaPieceType::Immobilizer, UltimaPieceType::Longleaper, UltimaPieceType::Chameleon, ADVANCER,
+    UltimaPieceType::King, UltimaPieceType::Withdrawer, UltimaPieceType::Chameleon, SWAPPER
+];
+
+pub fn new_in_start_position() -> GameBoard {
+    let mut board = GameBoard::new_empty_with_geometry(ROCOCO_GEOMETRY);
+    for (i, &piece_type) in BACK_RANK.iter().enumerate() {
+        let file = i as i8 + 1;
+        for (rank, pawn_rank, color) in [(1, 2, PlayerColor::White), (8, 7, PlayerColor::Black)] {
+            board.place_piece(Rankfile::from(rank, file).expect("on the board"), UltimaPiece {piece_type, color});
+            board.place_piece(Rankfile::from(pawn_rank, file).expect("on the board"), UltimaPiece {piece_type: CANNON_PAWN, color});
+        }
+    }
+    board
+}
+
+//`board_from_fen`, for the 10x10 board only. `board_to_fen` writes them back.
+pub fn board_from_rococo_fen(fen: &str) -> Result<(GameBoard, PlayerColor), FenError> {
+    let (board, whose_turn) = board_from_fen(fen)?;
+    if board.geometry() != ROCOCO_GEOMETRY {
+        return Err(FenError::WrongNumberOfRanks);
+    }
+    Ok((board, whose_turn))
+}
+
+pub struct CannonPawnBehavior;
+pub struct SwapperBehavior;
+pub struct AdvancerBehavior;
+
+impl PieceBehavior for CannonPawnBehavior {
+    fn piece_type(&self) -> UltimaPieceType {CANNON_PAWN}
+    fn name(&self) -> &'static str {"cannon pawn"}
+    fn generate_moves(&self, board: &GameBoard, start: Rankfile, color: PlayerColor, _rules: &RuleSet) -> Vec<MoveData> {
+        let piece = UltimaPiece {piece_type: CANNON_PAWN, color};
+        let (r, f) = start.to_signed_coords();
+        let ends = Rankfile::all_directions().filter_map(|&(dr, df)| {
+            let next = board.rankfile(r + dr, f + df)?;
+            if board.get_square(next).is_none() {
+                return Some(next);
+            }
+            board.rankfile(r + 2 * dr, f + 2 * df).filter(|&landing| board.get_square(landing).is_none())
+        });
+        ends.map(|end| {
+            let (r, f) = end.to_signed_coords();
+            let captures = Rankfile::all_directions().filter_map(|&(dr, df)| {
+                let sandwiched = board.rankfile(r + dr, f + df)?;
+                let partner = board.rankfile(r + 2 * dr, f + 2 * df)?;
+                //The pawn has left `start`, so it can't be its own partner.
+                (partner != start && board.get_square(partner).is_some_and(|p| p.color == color)).then_some(sandwiched)
+            });
+            MoveData::capturing(board, piece, start, end, captures, CaptureKind::Custodian)
+        }).collect()
+    }
+    fn chameleon_can_copy(&self, _rules: &RuleSet) -> bool {true}
+}
+
+impl PieceBehavior for SwapperBehavior {
+    fn piece_type(&self) -> UltimaPieceType {SWAPPER}
+    fn name(&self) -> &'static str {"swapper"}
+    fn generate_moves(&self, board: &GameBoard, start: Rankfile, color: PlayerColor, _rules: &RuleSet) -> Vec<MoveData> {
+        let piece = UltimaPiece {piece_type: SWAPPER, color};
+        let (r, f) = start.to_signed_coords();
+        let mut moves = vec![];
+        for &dir in Rankfile::all_directions() {
+            moves.extend(board.los(start, dir).map(|end| MoveData::new(piece, start, end)));
+            //The first piece along the line, if the line doesn't run off the board first.
+            let distance = board.los(start, dir).count() as i8 + 1;
+            if let Some(other) = board.rankfile(r + dir.0 * distance, f + dir.1 * distance) {
+                moves.push(MoveData::swap(piece, start, other));
+            }
+        }
+        //A chameleon borrowing these moves only swaps, so it never destroys itself.
+        if board.get_square(start) == Some(piece) {
+            moves.extend(start.surrounding_rankfiles().filter_map(|rf| {
+                let enemy = board.get_square(rf).filter(|p| p.color != color)?;
+                Some(MoveData::mutual_destruction(piece, start, rf, enemy))
+            }));
+        }
+        moves
+    }
+    fn chameleon_can_copy(&self, _rules: &RuleSet) -> bool {true}
+}
+
+impl PieceBehavior for AdvancerBehavior {
+    fn piece_type(&self) -> UltimaPieceType {ADVANCER}
+    fn name(&self) -> &'static str {"advancer"}
+    fn generate_moves(&self, board: &GameBoard, start: Rankfile, color: PlayerColor, _rules: &RuleSet) -> Vec<MoveData> {
+        let piece = UltimaPiece {piece_type: ADVANCER, color};
+        Rankfile::all_directions().flat_map(|&dir| {
+            board.los(start, dir).map(move |end| {
+                let (r, f) = end.to_signed_coords();
+                MoveData::capturing(board, piece, start, end, board.rankfile(r + dir.0, f + dir.1), CaptureKind::Approach)
+            })
+        }).collect()
+    }
+    fn chameleon_can_copy(&self, _rules: &RuleSet) -> bool {true}
+}
+
+//Ultima's pieces without the coordinator, and with the cannon pawn in the pawn's place.
+pub fn pieces() -> PieceSet {
+    PieceSet::ultima()
+        .without(UltimaPieceType::Coordinator)
+        .with(&CannonPawnBehavior)
+        .with(&SwapperBehavior)
+        .with(&AdvancerBehavior)
+}
+
+//The default options, so frozen pieces can't take themselves off the board, with Rococo's pieces.
+pub fn rules() -> RuleSet {
+    RuleSet {pieces: pieces(), ..RuleSet::default()}
+}
+
+//Every move for the piece on `start`, or none if it's frozen or there's no piece there.
+pub fn legal_moves_from(board: &GameBoard, start: Rankfile) -> Vec<MoveData> {
+    let Some(piece) = board.get_square(start) else {return vec![]};
+    //Only capturing moves may end on the edge. A swapper that swaps onto it doesn't capture anything.
+    move_validation::get_all_legal_moves(board, start, piece, &rules()).into_iter().filter(|mv| {
+        let lands_on_edge = mv.kind != MoveKind::MutualDestruction && is_edge(mv.end);
+        !lands_on_edge || (mv.kind == MoveKind::Normal && !mv.capture_details.is_empty())
+    }).collect()
+}
+
+pub fn legal_moves(board: &GameBoard, color: PlayerColor) -> Vec<MoveData> {
+    board.squares()
+        .filter(|&square| board.get_square(square).is_some_and(|p| p.color == color))
+        .flat_map(|square| legal_moves_from(board, square))
+        .collect()
+}
+
+//The legal move written as `s`, in the notation from `move_to_string`.
+//As in Ultima, the captures don't have to be written out.
+pub fn parse_move(board: &GameBoard, color: PlayerColor, s: &str) -> Option<MoveData> {
+    let s = s.trim().to_lowercase();
+    legal_moves(board, color).into_iter().find(|mv| {
+        let written = move_to_string(board, mv);
+        written == s || written.split('x').next() == Some(s.as_str())
+    })
+}
+
+//The number of move sequences `depth` plies long, stopping at king captures. For testing the generator.
+pub fn perft(board: &GameBoard, color: PlayerColor, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut total = 0;
+    for mv in legal_moves(board, color) {
+        if depth == 1 || captures_king(board, &mv, color) {
+            total += 1;
+            continue;
+        }
+        let mut child = board.clone();
+        execute_move(&mut child, mv, color);
+        total += perft(&child, color.opposite(), depth - 1);
+    }
+    total
+}
@@ -0,0 +1,105 @@
+use super::pieces::PieceSet;
+
+//The rules of Ultima were never settled: Abbott's original write-up left questions open,
+//and Baroque chess players answered some of them differently. Everything that varies goes here.
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct RuleSet {
+    //Whether a piece frozen by an enemy immobilizer may remove itself from the board as its move.
+    pub immobilized_suicide: bool,
+    //Pawns capture by sandwiching an enemy against a friendly piece along a rank or file.
+    //With this, diagonal sandwiches count too.
+    pub pawns_capture_diagonally: bool,
+    //Whether a chameleon may take a king, by moving onto it like a king.
+    pub chameleon_captures_king: bool,
+    //An immobilizer and a chameleon next to each other freeze each other. With this, the chameleon
+    //may still move directly away from the immobilizer, capturing it like a withdrawer would.
+    pub chameleon_withdraws_from_immobilizer: bool,
+    //The coordinator captures where its rank and file cross its own king's file and rank.
+    //With this, the partnership works both ways: a king's move captures on the squares where
+    //its new rank and file cross a friendly coordinator's.
+    pub king_coordinates: bool,
+    //How each piece moves and captures. House variants can swap in their own.
+    pub pieces: PieceSet
+}
+
+impl RuleSet {
+    //Abbott's original Ultima.
+    pub fn abbott() -> Self {
+        RuleSet {
+            immobilized_suicide: true,
+            pawns_capture_diagonally: false,
+            chameleon_captures_king: true,
+            chameleon_withdraws_from_immobilizer: false,
+            king_coordinates: false,
+            pieces: PieceSet::ultima()
+        }
+    }
+}
+
+type Flag = fn(&mut RuleSet) -> &mut bool;
+
+//The options a rule set identifier can name, and the flag each one sets.
+const OPTIONS: [(&str, Flag); 5] = [
+    ("immobilized-suicide", |rules| &mut rules.immobilized_suicide),
+    ("diagonal-pawns", |rules| &mut rules.pawns_capture_diagonally),
+    ("chameleon-captures-king", |rules| &mut rules.chameleon_captures_king),
+    ("chameleon-withdraws", |rules| &mut rules.chameleon_withdraws_from_immobilizer),
+    ("king-coordinates", |rules| &mut rules.king_coordinates)
+];
+
+impl RuleSet {
+    //An identifier like "default" or "abbott,diagonal-pawns,no-chameleon-captures-king":
+    //the nearest of `abbott` and the default rules, then each option that's set differently from it.
+    //It only covers the flags, not house pieces.
+    pub fn id(&self) -> String {
+        let (mut out, mut preset) = if self.immobilized_suicide {
+            (String::from("abbott"), Self::abbott())
+        } else {
+            (String::from("default"), Self::default())
+        };
+        let mut rules = *self;
+        for (name, flag) in OPTIONS {
+            let value = *flag(&mut rules);
+            if value != *flag(&mut preset) {
+                out += if value {","} else {",no-"};
+                out += name;
+            }
+        }
+        out
+    }
+
+    //Keeps the default pieces.
+    pub fn from_id(id: &str) -> Option<Self> {
+        let mut parts = id.trim().split(',').map(str::trim);
+        let mut rules = match parts.next()? {
+            "abbott" => Self::abbott(),
+            "default" => Self::default(),
+            _ => return None
+        };
+        for part in parts {
+            let (name, value) = match part.strip_prefix("no-") {
+                Some(name) => (name, false),
+                None => (part, true)
+            };
+            let (_, flag) = OPTIONS.iter().find(|(option, _)| *option == name)?;
+            *flag(&mut rules) = value;
+        }
+        Some(rules)
+    }
+}
+
+//Not a published convention: Abbott's rules, except that frozen pieces can't take themselves off the
+//board, which is how the engine played before the rules could be changed.
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet {
+            immobilized_suicide: false,
+            pawns_capture_diagonally: false,
+            chameleon_captures_king: true,
+            chameleon_withdraws_from_immobilizer: false,
+            king_coordinates: false,
+            pieces: PieceSet::ultima()
+        }
+    }
+}
@@ -0,0 +1,595 @@
+use std::collections::HashMap;
+use std::sync::{Arc, mpsc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::datatypes::{
+        board::{GameBoard,
+            rankfile::Rankfile
+        },
+        piece::PlayerColor,
+        moves::MoveData
+    };
+use super::logic::{move_validation, execute_move, execute_move_with_accumulator, undo_move, captures_king};
+use super::evaluation::{EvaluationBackend, nnue, hand_written::KING_CAPTURED_SCORE};
+use super::zobrist::zobrist_hash;
+use super::rng::splitmix64;
+use super::time_management::{SearchClock, TimeControl, TimeBudget};
+use super::search_trace::{SearchTrace, TraceSettings, TraceOutcome};
+use super::notation::move_to_string;
+use super::rules::RuleSet;
+use super::tablebase::{Tablebase, TablebaseResult};
+use super::packed_moves::PackedMove;
+
+//Iterative deepening alpha-beta (negamax) with a transposition table.
+//Scores are from the point of view of the side to move.
+//Taking the king ends the game, and scores `MATE_SCORE` minus the number of plies it took,
+//so that quicker wins score higher.
+
+pub const MATE_SCORE: i32 = KING_CAPTURED_SCORE;
+//Scores past this are forced king captures.
+pub const MATE_THRESHOLD: i32 = MATE_SCORE - 1000;
+//How deep timed and ponder searches are allowed to go, i.e. until the clock or a stop says otherwise.
+pub const MAX_SEARCH_DEPTH: u32 = 64;
+
+#[derive(Clone)]
+pub struct SearchResult {
+    pub best_move: Option<MoveData>,
+    pub score: i32,
+    pub principal_variation: Vec<MoveData>,
+    pub depth: u32,
+    pub nodes: u64
+}
+
+//Turns a score into something a person can read: pawns for normal scores, "#n" for a forced king capture in n moves.
+pub fn format_score(score: i32) -> String {
+    if score > MATE_THRESHOLD {
+        format!("#{}", (MATE_SCORE - score + 1) / 2)
+    } else if score < -MATE_THRESHOLD {
+        format!("#-{}", (MATE_SCORE + score + 1) / 2)
+    } else {
+        format!("{:+.2}", score as f64 / 100.0)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper
+}
+
+#[derive(Clone, Copy)]
+struct TtEntry {
+    depth: u32,
+    score: i32,
+    bound: Bound,
+    best: Option<PackedMove>
+}
+
+//One line of a multi-pv search: a root move with its score and the expected continuation.
+#[derive(Clone)]
+pub struct PvLine {
+    pub score: i32,
+    pub moves: Vec<MoveData>
+}
+
+//Sent after every completed iteration of `Searcher::search_multi_pv`.
+#[derive(Clone)]
+pub struct SearchProgress {
+    pub depth: u32,
+    //Best first.
+    pub lines: Vec<PvLine>,
+    pub nodes: u64,
+    pub elapsed: Duration
+}
+
+//How often (in nodes) the stop flag is checked.
+const STOP_CHECK_INTERVAL: u64 = 1024;
+
+pub struct Searcher<'a> {
+    backend: &'a EvaluationBackend,
+    rules: RuleSet,
+    //With an nnue backend, the accumulator for each position on the current line, kept up to date
+    //as moves are made and unmade. Empty for other backends, and boards the network doesn't know.
+    accumulators: Vec<nnue::Accumulator>,
+    tablebase: Option<&'a Tablebase>,
+    transposition_table: HashMap<u64, TtEntry>,
+    nodes: u64,
+    stop_flag: Option<Arc<AtomicBool>>,
+    stopped: bool,
+    node_limit: Option<u64>,
+    clock: Option<SearchClock>,
+    //The node and time limits only kick in once the first iteration is done, so there's always a move to play.
+    limits_armed: bool,
+    evaluation_noise: Option<(i32, u64)>,
+    trace_settings: Option<TraceSettings>,
+    //The iteration being searched, and the last one that finished.
+    trace: SearchTrace,
+    completed_trace: Option<SearchTrace>,
+    //The node being recorded, if the current one is being recorded at all.
+    trace_current: Option<usize>
+}
+
+impl<'a> Searcher<'a> {
+    pub fn new(backend: &'a EvaluationBackend) -> Self {
+        Searcher {
+            backend,
+            rules: RuleSet::default(),
+            accumulators: vec![],
+            tablebase: None,
+            transposition_table: HashMap::new(),
+            nodes: 0,
+            stop_flag: None,
+            stopped: false,
+            node_limit: None,
+            clock: None,
+            limits_armed: false,
+            evaluation_noise: None,
+            trace_settings: None,
+            trace: SearchTrace::default(),
+            completed_trace: None,
+            trace_current: None
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.transposition_table.clear();
+    }
+
+    //The rules to search under. Clears the transposition table, since its scores assumed the old rules.
+    pub fn set_rules(&mut self, rules: RuleSet) {
+        if rules != self.rules {
+            self.clear();
+        }
+        self.rules = rules;
+    }
+
+    //Positions the tablebase covers are scored from it instead of being searched,
+    //as long as it was generated under the rules being searched.
+    pub fn set_tablebase(&mut self, tablebase: Option<&'a Tablebase>) {
+        self.tablebase = tablebase;
+    }
+
+    //Setting the flag (from any thread) makes the search return as soon as possible,
+    //with the results of the last iteration it completed.
+    pub fn set_stop_flag(&mut self, stop_flag: Arc<AtomicBool>) {
+        self.stop_flag = Some(stop_flag);
+    }
+
+    //Stops the search once it has visited this many nodes.
+    pub fn set_node_limit(&mut self, node_limit: Option<u64>) {
+        self.node_limit = node_limit;
+    }
+
+    //Puts the search on a clock: it stops once the clock's budget is used up.
+    //A clock that hasn't been started yet doesn't limit anything, which is how pondering works.
+    pub fn set_clock(&mut self, clock: Option<SearchClock>) {
+        self.clock = clock;
+    }
+
+    //Adds up to `amplitude` centipawns of noise to every evaluation, to weaken the engine.
+    //The noise is a fixed function of the position and `seed`, so the transposition table stays consistent.
+    pub fn set_evaluation_noise(&mut self, amplitude: i32, seed: u64) {
+        self.evaluation_noise = if amplitude > 0 {Some((amplitude, seed))} else {None};
+    }
+
+    //Records the tree each search explores, within the given limits. See `search_trace`.
+    pub fn set_trace(&mut self, settings: Option<TraceSettings>) {
+        self.trace_settings = settings;
+    }
+
+    //The tree of the last completed iteration of the last search, if tracing is on.
+    pub fn take_trace(&mut self) -> Option<SearchTrace> {
+        self.completed_trace.take()
+    }
+
+    fn trace_enter_root(&mut self, depth: u32) {
+        self.trace_current = None;
+        let Some(settings) = self.trace_settings else {return};
+        if self.trace.nodes.len() < settings.max_nodes {
+            self.trace_current = Some(self.trace.push(None, None, 0, depth, -MATE_SCORE - 1, MATE_SCORE + 1));
+        } else {
+            self.trace.truncated = true;
+        }
+    }
+
+    //Records a node for `mv` under the current one, if it's within the limits, and makes it current.
+    //Returns the node to go back to with `trace_exit`.
+    fn trace_enter(&mut self, board: &GameBoard, mv: &MoveData, ply: u32, depth: u32, alpha: i32, beta: i32) -> Option<usize> {
+        let parent = self.trace_current;
+        let (Some(settings), Some(parent_index)) = (self.trace_settings, parent) else {return parent};
+        self.trace_current = None;
+        if ply <= settings.max_ply {
+            if self.trace.nodes.len() < settings.max_nodes {
+                let notation = move_to_string(board, mv);
+                self.trace_current = Some(self.trace.push(Some(parent_index), Some(notation), ply, depth, alpha, beta));
+            } else {
+                self.trace.truncated = true;
+            }
+        }
+        parent
+    }
+
+    fn trace_exit(&mut self, parent: Option<usize>, score: i32) {
+        if let Some(current) = self.trace_current {
+            self.trace.nodes[current].score = Some(score);
+        }
+        self.trace_current = parent;
+    }
+
+    fn trace_mark(&mut self, outcome: TraceOutcome) {
+        if let Some(current) = self.trace_current {
+            self.trace.nodes[current].outcome = outcome;
+        }
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if !self.stopped && self.nodes.is_multiple_of(STOP_CHECK_INTERVAL) {
+            if let Some(flag) = &self.stop_flag {
+                self.stopped = flag.load(Ordering::Relaxed);
+            }
+        }
+        if let Some(limit) = self.node_limit {
+            if self.limits_armed && self.nodes >= limit {
+                self.stopped = true;
+            }
+        }
+        if self.limits_armed && !self.stopped && self.nodes.is_multiple_of(STOP_CHECK_INTERVAL) {
+            if let Some((started, budget)) = self.clock.as_ref().and_then(SearchClock::budget) {
+                self.stopped = started.elapsed() >= budget.maximum;
+            }
+        }
+        self.stopped
+    }
+
+    fn evaluate(&self, board: &GameBoard, whose_turn: PlayerColor) -> i32 {
+        let score = self.backend.evaluate_incrementally(board, whose_turn, self.accumulators.last());
+        let Some((amplitude, seed)) = self.evaluation_noise else {return score};
+        if score.abs() > MATE_THRESHOLD {
+            return score;
+        }
+        let (_, random) = splitmix64(zobrist_hash(board, whose_turn) ^ seed);
+        score + (random % (2 * amplitude as u64 + 1)) as i32 - amplitude
+    }
+
+    //Searches to every depth from 1 up to `depth`, each iteration seeding the next one's move ordering.
+    pub fn search(&mut self, board: &GameBoard, whose_turn: PlayerColor, depth: u32) -> SearchResult {
+        let progress = self.search_multi_pv(board, whose_turn, depth, 1, |_| {});
+        let line = progress.lines.into_iter().next();
+        SearchResult {
+            best_move: line.as_ref().and_then(|line| line.moves.first().cloned()),
+            score: line.as_ref().map_or(0, |line| line.score),
+            principal_variation: line.map_or(vec![], |line| line.moves),
+            depth: progress.depth,
+            nodes: progress.nodes
+        }
+    }
+
+    //Searches for as long as the time control allows, starting the clock now.
+    pub fn search_timed(&mut self, board: &GameBoard, whose_turn: PlayerColor, time_control: &TimeControl) -> SearchResult {
+        let clock = SearchClock::new();
+        clock.start(TimeBudget::new(time_control));
+        self.set_clock(Some(clock));
+        let result = self.search(board, whose_turn, MAX_SEARCH_DEPTH);
+        self.set_clock(None);
+        result
+    }
+
+    //Like `search`, but finds the best `multi_pv` root moves, each with its own score and line.
+    //`on_progress` gets the lines after every completed iteration.
+    //Returns the last completed iteration, which is all that's left if the search gets stopped.
+    pub fn search_multi_pv(
+        &mut self,
+        board: &GameBoard,
+        whose_turn: PlayerColor,
+        depth: u32,
+        multi_pv: usize,
+        mut on_progress: impl FnMut(&SearchProgress)
+    ) -> SearchProgress {
+        let start_time = Instant::now();
+        self.nodes = 0;
+        self.stopped = false;
+        self.limits_armed = false;
+        //Goes up every time the best move changes and decays every iteration.
+        //The more the search is changing its mind, the longer it's allowed to think.
+        let mut best_move_changes = 0.0;
+        let mut progress = SearchProgress {
+            depth: 0,
+            lines: vec![],
+            nodes: 0,
+            elapsed: Duration::ZERO
+        };
+        for d in 1..=depth.max(1) {
+            self.trace = SearchTrace::default();
+            let mut lines: Vec<PvLine> = vec![];
+            let mut excluded = vec![];
+            while lines.len() < multi_pv.max(1) {
+                let Some(line) = self.search_root(board, whose_turn, d, &excluded) else {break};
+                excluded.push((line.moves[0].start, line.moves[0].end));
+                lines.push(line);
+            }
+            if self.stopped {
+                break;
+            }
+            lines.sort_by_key(|line| -line.score);
+            if self.trace_settings.is_some() {
+                self.completed_trace = Some(std::mem::take(&mut self.trace));
+            }
+            best_move_changes /= 2.0;
+            let first_move = |lines: &[PvLine]| lines.first().map(|line| (line.moves[0].start, line.moves[0].end));
+            if d > 1 && first_move(&lines) != first_move(&progress.lines) {
+                best_move_changes += 1.0;
+            }
+            progress = SearchProgress {
+                depth: d,
+                lines,
+                nodes: self.nodes,
+                elapsed: start_time.elapsed()
+            };
+            on_progress(&progress);
+            self.limits_armed = true;
+            if progress.lines.iter().all(|line| line.score.abs() > MATE_THRESHOLD) {
+                break; //can't do any better than a forced mate.
+            }
+            if let Some((started, budget)) = self.clock.as_ref().and_then(SearchClock::budget) {
+                if started.elapsed() >= budget.optimum_with_instability(best_move_changes) {
+                    break;
+                }
+            }
+        }
+        progress.nodes = self.nodes;
+        progress.elapsed = start_time.elapsed();
+        progress
+    }
+
+    //The best root move that isn't in `excluded`, or None if there's no such move or the search was stopped.
+    fn search_root(
+        &mut self,
+        board: &GameBoard,
+        whose_turn: PlayerColor,
+        depth: u32,
+        excluded: &[(Rankfile, Rankfile)]
+    ) -> Option<PvLine> {
+        self.nodes += 1;
+        let hash = zobrist_hash(board, whose_turn);
+        self.accumulators.clear();
+        if let EvaluationBackend::Nnue(network) = self.backend {
+            self.accumulators.extend(nnue::Accumulator::new(network, board));
+        }
+        let mut position = board.clone();
+        let mut alpha = -MATE_SCORE - 1;
+        let beta = MATE_SCORE + 1;
+        let mut best: Option<PvLine> = None;
+        let mut child_pv = vec![];
+        self.trace_enter_root(depth);
+        for mv in self.ordered_moves(board, whose_turn, hash) {
+            if excluded.contains(&(mv.start, mv.end)) {continue}
+            let traced = self.trace_enter(board, &mv, 1, depth - 1, -beta, -alpha);
+            let score = if captures_king(board, &mv, whose_turn) {
+                child_pv.clear();
+                self.trace_mark(TraceOutcome::KingCaptured);
+                MATE_SCORE - 1
+            } else {
+                self.make_move(&mut position, &mv, whose_turn);
+                let score = -self.alpha_beta(&mut position, whose_turn.opposite(), depth - 1, -beta, -alpha, 1, &mut child_pv);
+                self.unmake_move(&mut position, &mv);
+                score
+            };
+            self.trace_exit(traced, -score);
+            if self.stopped {
+                return None;
+            }
+            if best.as_ref().is_none_or(|best| score > best.score) {
+                let mut moves = vec![mv];
+                moves.extend(child_pv.iter().cloned());
+                best = Some(PvLine {score, moves});
+                alpha = alpha.max(score);
+            }
+        }
+        if let Some(best) = &best {
+            self.trace_exit(None, best.score);
+        }
+        if excluded.is_empty() {
+            if let Some(best) = &best {
+                if best.score.abs() <= MATE_THRESHOLD {
+                    self.transposition_table.insert(hash, TtEntry {
+                        depth,
+                        score: best.score,
+                        bound: Bound::Exact,
+                        best: PackedMove::from_move(&best.moves[0], board)
+                    });
+                }
+            }
+        }
+        best
+    }
+
+    //Scored like the search would score the same line: a king captured `plies_to_mate` plies from here.
+    fn probe_tablebase(&self, board: &GameBoard, whose_turn: PlayerColor, ply: u32) -> Option<i32> {
+        let tablebase = self.tablebase.filter(|tablebase| *tablebase.rules() == self.rules)?;
+        Some(match tablebase.probe(board, whose_turn)? {
+            TablebaseResult::Win {plies_to_mate} => MATE_SCORE - ply as i32 - plies_to_mate as i32,
+            TablebaseResult::Loss {plies_to_mate} => -(MATE_SCORE - ply as i32 - plies_to_mate as i32),
+            TablebaseResult::Draw => 0
+        })
+    }
+
+    fn make_move(&mut self, board: &mut GameBoard, mv: &MoveData, whose_turn: PlayerColor) {
+        match (self.backend, self.accumulators.last()) {
+            (EvaluationBackend::Nnue(network), Some(accumulator)) => {
+                let mut accumulator = accumulator.clone();
+                execute_move_with_accumulator(board, mv.clone(), whose_turn, &mut accumulator, network);
+                self.accumulators.push(accumulator);
+            },
+            _ => execute_move(board, mv.clone(), whose_turn)
+        }
+    }
+
+    fn unmake_move(&mut self, board: &mut GameBoard, mv: &MoveData) {
+        undo_move(board, mv);
+        self.accumulators.pop();
+    }
+
+    //King captures first, then the transposition table's move, then the rest by number of captures.
+    fn ordered_moves(&self, board: &GameBoard, whose_turn: PlayerColor, hash: u64) -> Vec<MoveData> {
+        let mut moves = move_validation::get_all_legal_moves_for_color(board, whose_turn, &self.rules);
+        let tt_move = self.transposition_table.get(&hash).and_then(|entry| entry.best);
+        moves.sort_by_cached_key(|mv| {
+            if captures_king(board, mv, whose_turn) {
+                0
+            } else if tt_move.is_some() && tt_move == PackedMove::from_move(mv, board) {
+                1
+            } else {
+                100 - mv.capture_details.len() as i32
+            }
+        });
+        moves
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn alpha_beta(
+        &mut self,
+        board: &mut GameBoard,
+        whose_turn: PlayerColor,
+        depth: u32,
+        mut alpha: i32,
+        beta: i32,
+        ply: u32,
+        pv: &mut Vec<MoveData>
+    ) -> i32 {
+        self.nodes += 1;
+        pv.clear();
+        if self.should_stop() {
+            return 0;
+        }
+        if let Some(score) = self.probe_tablebase(board, whose_turn, ply) {
+            self.trace_mark(TraceOutcome::TablebaseHit);
+            return score;
+        }
+        if depth == 0 {
+            self.trace_mark(TraceOutcome::Leaf);
+            return self.evaluate(board, whose_turn);
+        }
+        let hash = zobrist_hash(board, whose_turn);
+        if let Some(entry) = self.transposition_table.get(&hash).copied() {
+            if entry.depth >= depth {
+                let usable = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => entry.score >= beta,
+                    Bound::Upper => entry.score <= alpha
+                };
+                if usable {
+                    self.trace_mark(TraceOutcome::TranspositionHit);
+                    return entry.score;
+                }
+            }
+        }
+        let moves = self.ordered_moves(board, whose_turn, hash);
+        if moves.is_empty() {
+            self.trace_mark(TraceOutcome::NoMoves);
+            return 0; //nothing can move: scored as a draw.
+        }
+        let original_alpha = alpha;
+        let mut best_score = -MATE_SCORE - 1;
+        let mut best_move = None;
+        let mut child_pv = vec![];
+        let move_count = moves.len();
+        for (i, mv) in moves.into_iter().enumerate() {
+            let traced = self.trace_enter(board, &mv, ply + 1, depth - 1, -beta, -alpha);
+            let score = if captures_king(board, &mv, whose_turn) {
+                child_pv.clear();
+                self.trace_mark(TraceOutcome::KingCaptured);
+                MATE_SCORE - ply as i32 - 1
+            } else {
+                self.make_move(board, &mv, whose_turn);
+                let score = -self.alpha_beta(board, whose_turn.opposite(), depth - 1, -beta, -alpha, ply + 1, &mut child_pv);
+                self.unmake_move(board, &mv);
+                score
+            };
+            self.trace_exit(traced, -score);
+            if score > best_score {
+                best_score = score;
+                best_move = PackedMove::from_move(&mv, board);
+                pv.clear();
+                pv.push(mv);
+                pv.extend(child_pv.iter().cloned());
+            }
+            alpha = alpha.max(score);
+            if self.stopped {
+                break;
+            }
+            if alpha >= beta {
+                self.trace_mark(TraceOutcome::Cutoff {skipped: move_count - i - 1});
+                break;
+            }
+        }
+        if self.stopped {
+            return 0;
+        }
+        let bound = if best_score <= original_alpha {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        //Mate scores depend on the ply they were found at, so they aren't reused from the table.
+        if best_score.abs() <= MATE_THRESHOLD {
+            self.transposition_table.insert(hash, TtEntry {depth, score: best_score, bound, best: best_move});
+        }
+        best_score
+    }
+}
+
+//One-off search with a fresh transposition table.
+pub fn search(board: &GameBoard, whose_turn: PlayerColor, depth: u32, backend: &EvaluationBackend) -> SearchResult {
+    Searcher::new(backend).search(board, whose_turn, depth)
+}
+
+//A multi-pv search running on its own thread.
+//Progress arrives on `updates` as each iteration completes, and `stop` cuts the search short.
+pub struct AnalysisHandle {
+    pub updates: mpsc::Receiver<SearchProgress>,
+    stop_flag: Arc<AtomicBool>,
+    thread: thread::JoinHandle<SearchProgress>
+}
+
+impl AnalysisHandle {
+    pub fn stop(&self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    //Waits for the search to finish (or stop) and returns its final lines.
+    pub fn join(self) -> SearchProgress {
+        self.thread.join().expect("analysis thread panicked")
+    }
+}
+
+pub fn spawn_analysis(
+    board: GameBoard,
+    whose_turn: PlayerColor,
+    depth: u32,
+    multi_pv: usize,
+    rules: RuleSet,
+    backend: Arc<EvaluationBackend>
+) -> AnalysisHandle {
+    let (sender, updates) = mpsc::channel();
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let thread_stop_flag = stop_flag.clone();
+    let thread = thread::spawn(move || {
+        let mut searcher = Searcher::new(&backend);
+        searcher.set_rules(rules);
+        searcher.set_stop_flag(thread_stop_flag);
+        searcher.search_multi_pv(&board, whose_turn, depth, multi_pv, |progress| {
+            //Nobody listening any more is fine.
+            let _ = sender.send(progress.clone());
+        })
+    });
+    AnalysisHandle {updates, stop_flag, thread}
+}
@@ -0,0 +1,169 @@
+use super::search::format_score;
+
+//A record of the tree a search explored, for finding out why it played (or pruned) what it did.
+//Turned on with `Searcher::set_trace`; it holds the last completed iteration of iterative deepening.
+//
+//Windows and scores are from the point of view of the side to move at each node,
+//so a cutoff is a node whose score reached its beta.
+
+#[derive(Clone, Copy, Debug)]
+pub struct TraceSettings {
+    //Nodes deeper than this many plies aren't recorded.
+    pub max_ply: u32,
+    //Nodes past this many aren't recorded.
+    pub max_nodes: usize
+}
+
+impl Default for TraceSettings {
+    fn default() -> Self {
+        TraceSettings {max_ply: 3, max_nodes: 10_000}
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TraceOutcome {
+    //Every move was searched.
+    Searched,
+    //Scored by the evaluation.
+    Leaf,
+    //Scored from the transposition table without searching.
+    TranspositionHit,
+    //A move reached beta, so the `skipped` moves after it weren't searched.
+    Cutoff {skipped: usize},
+    //The move into this node took the king.
+    KingCaptured,
+    //The side to move had nothing to play.
+    NoMoves,
+    //Scored from the endgame tablebase.
+    TablebaseHit
+}
+
+impl TraceOutcome {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TraceOutcome::Searched => "searched",
+            TraceOutcome::Leaf => "leaf",
+            TraceOutcome::TranspositionHit => "tt hit",
+            TraceOutcome::Cutoff {..} => "cutoff",
+            TraceOutcome::KingCaptured => "king captured",
+            TraceOutcome::NoMoves => "no moves",
+            TraceOutcome::TablebaseHit => "tablebase hit"
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TraceNode {
+    //None for the root.
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    //The move into this node, in the notation from `notation`. None for the root.
+    pub mv: Option<String>,
+    pub ply: u32,
+    //Depth left to search.
+    pub depth: u32,
+    pub alpha: i32,
+    pub beta: i32,
+    pub score: Option<i32>,
+    pub outcome: TraceOutcome
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SearchTrace {
+    //Parents always come before their children. There's one root per root search,
+    //so a multi-pv search has one for each line.
+    pub nodes: Vec<TraceNode>,
+    //Whether the node cap cut the recording short.
+    pub truncated: bool
+}
+
+impl SearchTrace {
+    pub fn roots(&self) -> impl Iterator<Item = usize> + '_ {
+        self.nodes.iter().enumerate().filter(|(_, node)| node.parent.is_none()).map(|(i, _)| i)
+    }
+
+    //Adds a node under `parent`, returning its index.
+    pub(crate) fn push(&mut self, parent: Option<usize>, mv: Option<String>, ply: u32, depth: u32, alpha: i32, beta: i32) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(TraceNode {
+            parent,
+            children: vec![],
+            mv,
+            ply,
+            depth,
+            alpha,
+            beta,
+            score: None,
+            outcome: TraceOutcome::Searched
+        });
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(index);
+        }
+        index
+    }
+
+    //Graphviz. Cutoffs are red, transposition table and tablebase hits blue and king captures bold.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph search {\n    node [shape=box, fontname=monospace];\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            let mut label = node.mv.clone().unwrap_or_else(|| String::from("root"));
+            label += &format!("\\n[{}, {}] d{}", format_window_bound(node.alpha), format_window_bound(node.beta), node.depth);
+            if let Some(score) = node.score {
+                label += &format!("\\n{} {}", format_score(score), node.outcome.name());
+            } else {
+                label += &format!("\\n{}", node.outcome.name());
+            }
+            if let TraceOutcome::Cutoff {skipped} = node.outcome {
+                label += &format!(" ({skipped} skipped)");
+            }
+            let style = match node.outcome {
+                TraceOutcome::Cutoff {..} => ", color=red",
+                TraceOutcome::TranspositionHit | TraceOutcome::TablebaseHit => ", color=blue",
+                TraceOutcome::KingCaptured => ", style=bold",
+                _ => ""
+            };
+            out += &format!("    n{i} [label=\"{label}\"{style}];\n");
+        }
+        for (i, node) in self.nodes.iter().enumerate() {
+            for child in &node.children {
+                out += &format!("    n{i} -> n{child};\n");
+            }
+        }
+        out += "}\n";
+        out
+    }
+
+    //{"truncated": false, "nodes": [{"id": 0, "parent": null, "children": [1, 2], "move": null, ...}, ...]}
+    pub fn to_json(&self) -> String {
+        let nodes: Vec<String> = self.nodes.iter().enumerate().map(|(i, node)| {
+            let children: Vec<String> = node.children.iter().map(|child| child.to_string()).collect();
+            let skipped = match node.outcome {
+                TraceOutcome::Cutoff {skipped} => skipped.to_string(),
+                _ => String::from("null")
+            };
+            format!(
+                "{{\"id\": {i}, \"parent\": {}, \"children\": [{}], \"move\": {}, \"ply\": {}, \"depth\": {}, \
+                \"alpha\": {}, \"beta\": {}, \"score\": {}, \"outcome\": \"{}\", \"skipped\": {skipped}}}",
+                node.parent.map_or(String::from("null"), |parent| parent.to_string()),
+                children.join(", "),
+                node.mv.as_ref().map_or(String::from("null"), |mv| format!("\"{mv}\"")),
+                node.ply,
+                node.depth,
+                node.alpha,
+                node.beta,
+                node.score.map_or(String::from("null"), |score| score.to_string()),
+                node.outcome.name()
+            )
+        }).collect();
+        format!("{{\"truncated\": {}, \"nodes\": [\n    {}\n]}}\n", self.truncated, nodes.join(",\n    "))
+    }
+}
+
+//The root window is one past the mate scores, which reads better as infinity.
+fn format_window_bound(bound: i32) -> String {
+    if bound.abs() > super::search::MATE_SCORE {
+        String::from(if bound > 0 {"inf"} else {"-inf"})
+    } else {
+        format_score(bound)
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+
+use super::datatypes::{
+        board::{GameBoard,
+            rankfile::Rankfile
+        },
+        piece::{UltimaPiece, UltimaPieceType::{self, *}, PlayerColor}
+    };
+use super::rng::Rng;
+
+//The ways a game can start. Pawns always fill each side's second rank; only the back ranks differ.
+//
+//Every setup has an identifier, e.g. "rotational" or "shuffle-42", which is what game records
+//use to name their start position.
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Default)]
+pub enum StartPosition {
+    //The usual setup, and what `GameBoard::new_in_start_position` gives. Black's back rank is white's
+    //turned around, so each side has its immobilizer in its own left-hand corner.
+    #[default]
+    Rotational,
+    //Abbott's original: black's back rank is white's mirrored across the board,
+    //so the kings face each other on the e-file and the withdrawers on the d-file.
+    Abbott,
+    //A random back rank picked from the seed, mirrored for black like `Abbott`.
+    //See `shuffled_back_rank` for the constraints.
+    Shuffled(u64)
+}
+
+//White's back rank, a1 first.
+const ROTATIONAL_BACK_RANK: [UltimaPieceType; 8] = [
+    Immobilizer, Longleaper, Chameleon, King, Withdrawer, Chameleon, Longleaper, Coordinator
+];
+const ABBOTT_BACK_RANK: [UltimaPieceType; 8] = [
+    Immobilizer, Longleaper, Chameleon, Withdrawer, King, Chameleon, Longleaper, Coordinator
+];
+
+impl StartPosition {
+    pub fn id(&self) -> String {
+        match self {
+            StartPosition::Rotational => String::from("rotational"),
+            StartPosition::Abbott => String::from("abbott"),
+            StartPosition::Shuffled(seed) => format!("shuffle-{seed}")
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id.trim() {
+            "rotational" => Some(StartPosition::Rotational),
+            "abbott" => Some(StartPosition::Abbott),
+            other => other.strip_prefix("shuffle-")?.parse().ok().map(StartPosition::Shuffled)
+        }
+    }
+
+    //Each side's back rank, from the a-file to the h-file.
+    pub fn back_ranks(&self) -> ([UltimaPieceType; 8], [UltimaPieceType; 8]) {
+        match self {
+            StartPosition::Rotational => {
+                let mut black = ROTATIONAL_BACK_RANK;
+                black.reverse();
+                (ROTATIONAL_BACK_RANK, black)
+            },
+            StartPosition::Abbott => (ABBOTT_BACK_RANK, ABBOTT_BACK_RANK),
+            StartPosition::Shuffled(seed) => {
+                let rank = shuffled_back_rank(*seed);
+                (rank, rank)
+            }
+        }
+    }
+
+    pub fn board(&self) -> GameBoard {
+        let (white, black) = self.back_ranks();
+        let mut board = GameBoard::new_empty();
+        for file in 0..8 {
+            let square = |rank: i8| Rankfile::from(rank, file as i8).expect("start squares are on the board");
+            board.place_piece(square(0), UltimaPiece {piece_type: white[file], color: PlayerColor::White});
+            board.place_piece(square(1), UltimaPiece {piece_type: Pawn, color: PlayerColor::White});
+            board.place_piece(square(6), UltimaPiece {piece_type: Pawn, color: PlayerColor::Black});
+            board.place_piece(square(7), UltimaPiece {piece_type: black[file], color: PlayerColor::Black});
+        }
+        board
+    }
+}
+
+impl fmt::Display for StartPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id())
+    }
+}
+
+//Shuffles the rotational setup's back rank until:
+//  - the king isn't in a corner, where it would be too easy to smother,
+//  - the two longleapers stand on different coloured squares, and so do the two chameleons,
+//    so neither pair is stuck jumping or imitating along the same diagonals.
+//The same seed always gives the same rank.
+pub fn shuffled_back_rank(seed: u64) -> [UltimaPieceType; 8] {
+    let mut rng = Rng::new(seed);
+    loop {
+        let mut rank = ROTATIONAL_BACK_RANK;
+        for i in (1..rank.len()).rev() {
+            let j = rng.below(i as u64 + 1) as usize;
+            rank.swap(i, j);
+        }
+        let on_different_colours = |piece_type: UltimaPieceType| {
+            let files: Vec<usize> = (0..8).filter(|&file| rank[file] == piece_type).collect();
+            files[0] % 2 != files[1] % 2
+        };
+        let king_cornered = rank[0] == King || rank[7] == King;
+        if !king_cornered && on_different_colours(Longleaper) && on_different_colours(Chameleon) {
+            return rank;
+        }
+    }
+}
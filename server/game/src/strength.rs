@@ -0,0 +1,113 @@
+use super::datatypes::{
+        board::GameBoard,
+        piece::PlayerColor,
+        moves::MoveData
+    };
+use super::evaluation::EvaluationBackend;
+use super::search::Searcher;
+use super::rng::Rng;
+use super::rules::RuleSet;
+use super::tablebase::Tablebase;
+
+//Engine strength levels for playing against people.
+//Lower levels search shallower and fewer nodes, see a noisier evaluation,
+//and pick among their best few moves by a softmax instead of always playing the best one.
+
+pub const MAX_SKILL_LEVEL: u8 = 20;
+
+//Measured by the ignored `calibrate_approximate_elo` test in tests/strength.rs, which plays 20-game matches
+//(150 plies max) between neighbouring even levels, anchored at level 0 = 400, with odd levels interpolated.
+//Levels 0 and 2 came out even, and from level 8 up most games were drawn, so treat these as rough.
+//Re-run it after changing `limits` or the evaluation.
+const APPROXIMATE_ELO: [u32; MAX_SKILL_LEVEL as usize + 1] = [
+    400, 400, 400, 590, 780, 1035, 1295, 1430, 1565, 1580,
+    1595, 1640, 1685, 1740, 1795, 1845, 1900, 1975, 2050, 2215,
+    2385
+];
+
+pub struct StrengthLimits {
+    pub max_depth: u32,
+    pub max_nodes: Option<u64>,
+    //Centipawns of noise added to every evaluation, either way.
+    pub evaluation_noise: i32,
+    //How many of the best moves the softmax picks among.
+    pub candidate_moves: usize,
+    //Softmax temperature in centipawns: a move scoring this much worse than the best is e times less likely.
+    //0 always plays the best move.
+    pub temperature: f64
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct SkillLevel {
+    level: u8
+}
+
+impl SkillLevel {
+    //Levels above `MAX_SKILL_LEVEL` are treated as `MAX_SKILL_LEVEL`.
+    pub fn new(level: u8) -> Self {
+        SkillLevel {level: level.min(MAX_SKILL_LEVEL)}
+    }
+
+    pub fn strongest() -> Self {
+        Self::new(MAX_SKILL_LEVEL)
+    }
+
+    //The strongest level whose approximate elo doesn't exceed `elo`.
+    pub fn from_elo(elo: u32) -> Self {
+        let level = APPROXIMATE_ELO.iter().rposition(|&level_elo| level_elo <= elo).unwrap_or(0);
+        Self::new(level as u8)
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    pub fn approximate_elo(&self) -> u32 {
+        APPROXIMATE_ELO[self.level as usize]
+    }
+
+    pub fn limits(&self) -> StrengthLimits {
+        let weakness = (MAX_SKILL_LEVEL - self.level) as i32;
+        StrengthLimits {
+            max_depth: 1 + self.level as u32 / 5,
+            max_nodes: if self.level == MAX_SKILL_LEVEL {None} else {Some(200 << (self.level / 2))},
+            evaluation_noise: weakness * 15,
+            candidate_moves: if self.level == MAX_SKILL_LEVEL {1} else {4},
+            temperature: weakness as f64 * 10.0
+        }
+    }
+}
+
+//Picks a move for the side to move, playing at the given strength.
+pub fn choose_move(
+    board: &GameBoard,
+    whose_turn: PlayerColor,
+    skill: SkillLevel,
+    rules: &RuleSet,
+    backend: &EvaluationBackend,
+    tablebase: Option<&Tablebase>,
+    rng: &mut Rng
+) -> Option<MoveData> {
+    let limits = skill.limits();
+    let mut searcher = Searcher::new(backend);
+    searcher.set_rules(*rules);
+    searcher.set_tablebase(tablebase);
+    searcher.set_node_limit(limits.max_nodes);
+    searcher.set_evaluation_noise(limits.evaluation_noise, rng.next_u64());
+    let progress = searcher.search_multi_pv(board, whose_turn, limits.max_depth, limits.candidate_moves, |_| {});
+    let best_score = progress.lines.first()?.score;
+    if limits.temperature <= 0.0 {
+        return progress.lines.into_iter().next()?.moves.into_iter().next();
+    }
+    let weights: Vec<f64> = progress.lines.iter()
+        .map(|line| ((line.score - best_score) as f64 / limits.temperature).exp())
+        .collect();
+    let mut pick = rng.next_f64() * weights.iter().sum::<f64>();
+    for (line, weight) in progress.lines.iter().zip(&weights) {
+        if pick < *weight {
+            return line.moves.first().cloned();
+        }
+        pick -= weight;
+    }
+    progress.lines.into_iter().next()?.moves.into_iter().next()
+}
@@ -0,0 +1,90 @@
+use super::datatypes::{
+        board::{GameBoard,
+            rankfile::{Rankfile, Geometry}
+        },
+        piece::{UltimaPiece, PlayerColor},
+        moves::{MoveData, Capture}
+    };
+
+//Ultima's pieces have no forward direction, so the rules look the same from any side of the board.
+//These transforms map a position onto an equivalent one, which is handy for testing the move
+//generators and for getting more out of self-play data.
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Symmetry {
+    //Rank 1 swaps with the last rank, and white with black, so the side to move changes too.
+    FlipVertical,
+    //The a-file swaps with the last file.
+    MirrorHorizontal,
+    //Both of the above, but keeping the colours.
+    Rotate180
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 3] = [Symmetry::FlipVertical, Symmetry::MirrorHorizontal, Symmetry::Rotate180];
+
+    pub fn swaps_colors(&self) -> bool {
+        *self == Symmetry::FlipVertical
+    }
+
+    pub fn color(&self, color: PlayerColor) -> PlayerColor {
+        if self.swaps_colors() {color.opposite()} else {color}
+    }
+
+    pub fn piece(&self, piece: UltimaPiece) -> UltimaPiece {
+        UltimaPiece {color: self.color(piece.color), ..piece}
+    }
+
+    //Where `rf` ends up on a board of shape `geometry`.
+    pub fn square(&self, rf: Rankfile, geometry: Geometry) -> Rankfile {
+        let (r, f) = rf.to_signed_coords();
+        let flipped_r = geometry.ranks() - 1 - r;
+        let mirrored_f = geometry.files() - 1 - f;
+        let (r, f) = match self {
+            Symmetry::FlipVertical => (flipped_r, f),
+            Symmetry::MirrorHorizontal => (r, mirrored_f),
+            Symmetry::Rotate180 => (flipped_r, mirrored_f)
+        };
+        geometry.rankfile(r, f).expect("transformed squares stay on the board")
+    }
+}
+
+impl GameBoard {
+    pub fn transformed(&self, symmetry: Symmetry) -> GameBoard {
+        let geometry = self.geometry();
+        let mut out = GameBoard::new_empty_with_geometry(geometry);
+        for rf in self.squares() {
+            if let Some(piece) = self.get_square(rf) {
+                out.place_piece(symmetry.square(rf, geometry), symmetry.piece(piece));
+            }
+        }
+        out
+    }
+    pub fn flipped_vertically(&self) -> GameBoard {
+        self.transformed(Symmetry::FlipVertical)
+    }
+    pub fn mirrored_horizontally(&self) -> GameBoard {
+        self.transformed(Symmetry::MirrorHorizontal)
+    }
+    pub fn rotated_180(&self) -> GameBoard {
+        self.transformed(Symmetry::Rotate180)
+    }
+}
+
+impl MoveData {
+    //The same move on the transformed board. `geometry` is the board's, which the transforms don't change.
+    pub fn transformed(&self, symmetry: Symmetry, geometry: Geometry) -> MoveData {
+        let square = |rf: Rankfile| symmetry.square(rf, geometry);
+        MoveData {
+            start: square(self.start),
+            end: square(self.end),
+            piece: symmetry.piece(self.piece),
+            capture_details: self.capture_details.iter().map(|capture| Capture {
+                square: square(capture.square),
+                piece: symmetry.piece(capture.piece),
+                kind: capture.kind
+            }).collect(),
+            kind: self.kind
+        }
+    }
+}
@@ -0,0 +1,722 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use super::datatypes::{
+        board::{GameBoard,
+            rankfile::Rankfile
+        },
+        piece::{UltimaPiece, UltimaPieceType, PlayerColor},
+        moves::{MoveData, MoveKind}
+    };
+use super::logic::{move_validation, execute_move, captures_king};
+use super::rules::RuleSet;
+use super::pieces::PieceSet;
+use super::packed_moves::{PackedMoveList, generate_packed_moves};
+
+//Endgame tablebases for positions with a handful of pieces, kings included.
+//
+//Games are played by capture-the-king rules, so "mate" here means the king gets captured,
+//and distance to mate is counted in plies up to and including the capture.
+//A side with no legal moves at all (everything immobilized) is scored as a draw.
+//Tables are generated under the tablebase's rules, and each file records which.
+//
+//Ultima's pieces move the same way from every side of the board, so a position is worth the same
+//after any of the eight turns and reflections of the board that keep the colours. Tables only store
+//the positions with the white king in the a1-d1-d4 triangle, and index each other piece among the
+//squares the earlier ones leave free: 10 * 63 * 62 * 61 * 2 entries for four pieces, against 64^4 * 2.
+//House pieces that move differently on different sides of the board would need the full index.
+//Generation keeps every move within the table in memory, which for four pieces is several hundred megabytes.
+//
+//Values are stored as a single u16 per position, which is the distance to mate:
+//odd means the side to move wins, even (and nonzero) means it loses, and 0 is a draw.
+//(The side to move always makes the capturing move of a win, so a win is always an odd number of plies away.)
+
+pub const MAX_PIECES: usize = 4;
+
+//Each piece's square, rank * 8 + file, in the material's piece order. Only the first `pieces.len()` are used.
+type Squares = [u8; MAX_PIECES];
+
+//Where the white king can stand in a stored position: a1, b1, c1, d1, b2, c2, d2, c3, d3, d4.
+const KING_SQUARES: [u8; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+//A turn or reflection of the board, keeping the colours.
+#[derive(Clone, Copy)]
+struct Reflection {
+    flip_ranks: bool,
+    flip_files: bool,
+    //Swaps ranks and files, after the flips.
+    transpose: bool
+}
+
+impl Reflection {
+    fn apply(self, square: u8) -> u8 {
+        let (mut r, mut f) = (square / 8, square % 8);
+        if self.flip_ranks {
+            r = 7 - r;
+        }
+        if self.flip_files {
+            f = 7 - f;
+        }
+        if self.transpose {
+            (r, f) = (f, r);
+        }
+        r * 8 + f
+    }
+}
+
+const DRAW: u16 = 0;
+const UNRESOLVED: u16 = u16::MAX;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TablebaseResult {
+    Win {plies_to_mate: u16},
+    Loss {plies_to_mate: u16},
+    Draw
+}
+
+impl TablebaseResult {
+    fn from_value(value: u16) -> Self {
+        if value == DRAW {
+            TablebaseResult::Draw
+        } else if value % 2 == 1 {
+            TablebaseResult::Win {plies_to_mate: value}
+        } else {
+            TablebaseResult::Loss {plies_to_mate: value}
+        }
+    }
+}
+
+//Kings first, then the rest in `UltimaPieceType::ALL` order, white before black.
+fn piece_order(piece: &UltimaPiece) -> (usize, usize) {
+    let type_order = match piece.piece_type {
+        UltimaPieceType::King => 0,
+        other => other.as_index() + 1
+    };
+    (piece.color.as_index(), type_order)
+}
+
+//The set of pieces on the board, e.g. "KWvK" for king and withdrawer against a lone king.
+#[derive(PartialEq, Eq, Hash, Clone)]
+pub struct Material {
+    pieces: Vec<UltimaPiece>
+}
+
+impl Material {
+    //Needs exactly one king on each side, and at most `MAX_PIECES` pieces in total.
+    pub fn new(mut pieces: Vec<UltimaPiece>) -> Option<Self> {
+        if pieces.len() > MAX_PIECES {
+            return None;
+        }
+        for color in [PlayerColor::White, PlayerColor::Black] {
+            let kings = pieces.iter()
+                .filter(|p| p.color == color && p.piece_type == UltimaPieceType::King)
+                .count();
+            if kings != 1 {
+                return None;
+            }
+        }
+        pieces.sort_by_key(piece_order);
+        Some(Material {pieces})
+    }
+
+    //None for boards other than the standard one, which is the only one tables are built for.
+    pub fn from_board(board: &GameBoard) -> Option<Self> {
+        if !board.geometry().is_standard() {
+            return None;
+        }
+        let mut pieces = vec![];
+        for rf in Rankfile::all_rankfiles() {
+            if let Some(piece) = board.get_square(rf) {
+                if pieces.len() == MAX_PIECES {
+                    return None;
+                }
+                pieces.push(piece);
+            }
+        }
+        Self::new(pieces)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (white, black) = name.split_once('v')?;
+        let mut pieces = vec![];
+        for (side, color) in [(white, PlayerColor::White), (black, PlayerColor::Black)] {
+            for c in side.chars() {
+                pieces.push(UltimaPiece {
+                    piece_type: UltimaPieceType::from_char(c)?,
+                    color
+                });
+            }
+        }
+        Self::new(pieces)
+    }
+
+    pub fn name(&self) -> String {
+        let side = |color: PlayerColor| -> String {
+            self.pieces.iter()
+                .filter(|p| p.color == color)
+                .map(|p| p.piece_type.to_char())
+                .collect()
+        };
+        format!("{}v{}", side(PlayerColor::White), side(PlayerColor::Black))
+    }
+
+    pub fn pieces(&self) -> &[UltimaPiece] {
+        &self.pieces
+    }
+
+    //Every material reachable by one capture that doesn't end the game.
+    fn after_captures(&self) -> Vec<Material> {
+        let mut out: Vec<Material> = vec![];
+        for (i, piece) in self.pieces.iter().enumerate() {
+            if piece.piece_type == UltimaPieceType::King {continue}
+            let mut pieces = self.pieces.clone();
+            pieces.remove(i);
+            let material = Material {pieces};
+            if !out.contains(&material) {
+                out.push(material);
+            }
+        }
+        out
+    }
+
+    fn num_positions(&self) -> usize {
+        KING_SQUARES.len() * (1..self.pieces.len()).map(|i| 64 - i).product::<usize>() * 2
+    }
+
+    //The index of the position with the pieces on `squares`, once the white king has been reflected into
+    //`KING_SQUARES`. A king on the a1-d4 diagonal leaves two reflections to choose from, and the smaller
+    //index is the one that's stored.
+    fn canonical_index(&self, squares: &Squares, whose_turn: PlayerColor) -> usize {
+        let n = self.pieces.len();
+        let (r, f) = (squares[0] / 8, squares[0] % 8);
+        let (flip_ranks, flip_files) = (r > 3, f > 3);
+        let (r, f) = (if flip_ranks {7 - r} else {r}, if flip_files {7 - f} else {f});
+        let reflected = |transpose| {
+            let reflection = Reflection {flip_ranks, flip_files, transpose};
+            let mut out = *squares;
+            for square in &mut out[..n] {
+                *square = reflection.apply(*square);
+            }
+            out
+        };
+        let mut index = self.reduced_index(&mut reflected(r > f));
+        if r == f {
+            index = index.min(self.reduced_index(&mut reflected(true)));
+        }
+        index * 2 + whose_turn.as_index()
+    }
+
+    //For squares with the white king already in `KING_SQUARES`. Identical pieces are put in board order.
+    fn reduced_index(&self, squares: &mut Squares) -> usize {
+        let n = self.pieces.len();
+        let mut i = 0;
+        while i < n {
+            let run = self.pieces[i..n].iter().take_while(|&&p| p == self.pieces[i]).count();
+            squares[i..i + run].sort_unstable();
+            i += run;
+        }
+        let king = KING_SQUARES.iter().position(|&sq| sq == squares[0]).expect("the king has been reflected into the triangle");
+        (1..n).fold(king, |index, i| {
+            let below = squares[..i].iter().filter(|&&sq| sq < squares[i]).count();
+            index * (64 - i) + squares[i] as usize - below
+        })
+    }
+
+    //Identical pieces are assigned squares in board order.
+    fn index_of(&self, board: &GameBoard, whose_turn: PlayerColor) -> Option<usize> {
+        let mut squares = [0; MAX_PIECES];
+        for (i, &piece) in self.pieces.iter().enumerate() {
+            squares[i] = (0..64).find(|&sq| {
+                !squares[..i].contains(&sq) && board.get_square_from_coords((sq / 8) as i8, (sq % 8) as i8) == Some(piece)
+            })?;
+        }
+        Some(self.canonical_index(&squares, whose_turn))
+    }
+
+    //The index after the piece on `start` moves to `end` without taking anything, with the other side to move.
+    fn index_after_quiet_move(&self, squares: &Squares, whose_turn: PlayerColor, start: Rankfile, end: Rankfile) -> Option<usize> {
+        let square = |rf: Rankfile| {
+            let (r, f) = rf.to_unsigned_coords();
+            (r * 8 + f) as u8
+        };
+        let mut moved = *squares;
+        let slot = moved[..self.pieces.len()].iter().position(|&sq| sq == square(start))?;
+        moved[slot] = square(end);
+        Some(self.canonical_index(&moved, whose_turn.opposite()))
+    }
+
+    //None for indices that aren't stored positions' own, e.g. the second reflection of a king on the diagonal.
+    fn board_at(&self, index: usize) -> Option<(GameBoard, PlayerColor, Squares)> {
+        let n = self.pieces.len();
+        let whose_turn = if index % 2 == 1 {PlayerColor::Black} else {PlayerColor::White};
+        let mut rest = index / 2;
+        let mut slots = [0; MAX_PIECES];
+        for i in (1..n).rev() {
+            slots[i] = rest % (64 - i);
+            rest /= 64 - i;
+        }
+        let mut squares = [0; MAX_PIECES];
+        squares[0] = *KING_SQUARES.get(rest)?;
+        for i in 1..n {
+            squares[i] = (0..64).filter(|sq| !squares[..i].contains(sq)).nth(slots[i])?;
+        }
+        if self.canonical_index(&squares, whose_turn) != index {
+            return None;
+        }
+        let mut board = GameBoard::new_empty();
+        for (&piece, &square) in self.pieces.iter().zip(&squares) {
+            board.place_piece(Rankfile::from((square / 8) as i8, (square % 8) as i8)?, piece);
+        }
+        Some((board, whose_turn, squares))
+    }
+}
+
+#[derive(Debug)]
+pub enum TablebaseLoadError {
+    Io(std::io::Error),
+    BadMagic,
+    UnsupportedVersion(u32),
+    BadMaterial(String),
+    BadRules(String),
+    //A table generated under other rules than the tablebase it's going into.
+    WrongRules {material: String, rules: String},
+    UnexpectedEof,
+    WrongSize {expected: usize, found: usize}
+}
+
+impl fmt::Display for TablebaseLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TablebaseLoadError::Io(e) => write!(f, "couldn't read tablebase file: {e}"),
+            TablebaseLoadError::BadMagic => write!(f, "not an ultima tablebase file"),
+            TablebaseLoadError::UnsupportedVersion(v) => write!(f, "unsupported tablebase version {v}"),
+            TablebaseLoadError::BadMaterial(name) => write!(f, "tablebase file has invalid material {name:?}"),
+            TablebaseLoadError::BadRules(id) => write!(f, "tablebase file has unknown rules {id:?}"),
+            TablebaseLoadError::WrongRules {material, rules} => {
+                write!(f, "tablebase file for {material} was generated under other rules ({rules})")
+            },
+            TablebaseLoadError::UnexpectedEof => write!(f, "tablebase file is truncated"),
+            TablebaseLoadError::WrongSize {expected, found} => {
+                write!(f, "tablebase file has {found} positions, expected {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TablebaseLoadError {}
+
+impl From<std::io::Error> for TablebaseLoadError {
+    fn from(e: std::io::Error) -> Self {
+        TablebaseLoadError::Io(e)
+    }
+}
+
+const MAGIC: &[u8; 4] = b"ULTB";
+const VERSION: u32 = 3;
+pub const FILE_EXTENSION: &str = "ultb";
+
+#[derive(Clone)]
+pub struct Table {
+    material: Material,
+    rules: RuleSet,
+    values: Vec<u16>
+}
+
+impl Table {
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    pub fn probe(&self, board: &GameBoard, whose_turn: PlayerColor) -> Option<TablebaseResult> {
+        let index = self.material.index_of(board, whose_turn)?;
+        Some(TablebaseResult::from_value(self.values[index]))
+    }
+
+    //File layout (all little-endian):
+    //  b"ULTB", version: u32, rules id length: u8, rules id (see `RuleSet::id`),
+    //  material name length: u8, material name, number of positions: u64,
+    //  value width in bytes: u8 (1 if every distance fits in a byte, else 2), compressed length: u64,
+    //  then the values, packbits compressed.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let width: u8 = if self.values.iter().all(|&v| v <= u8::MAX as u16) {1} else {2};
+        let raw: Vec<u8> = if width == 1 {
+            self.values.iter().map(|&v| v as u8).collect()
+        } else {
+            self.values.iter().flat_map(|v| v.to_le_bytes()).collect()
+        };
+        let compressed = packbits::compress(&raw);
+        let name = self.material.name();
+        let rules = self.rules.id();
+        let mut out = Vec::with_capacity(27 + rules.len() + name.len() + compressed.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.push(rules.len() as u8);
+        out.extend_from_slice(rules.as_bytes());
+        out.push(name.len() as u8);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(&(self.values.len() as u64).to_le_bytes());
+        out.push(width);
+        out.extend_from_slice(&(compressed.len() as u64).to_le_bytes());
+        out.extend_from_slice(&compressed);
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TablebaseLoadError> {
+        fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Result<&'a [u8], TablebaseLoadError> {
+            if bytes.len() < n {
+                return Err(TablebaseLoadError::UnexpectedEof);
+            }
+            let (head, tail) = bytes.split_at(n);
+            *bytes = tail;
+            Ok(head)
+        }
+        let mut bytes = bytes;
+        if take(&mut bytes, 4)? != MAGIC {
+            return Err(TablebaseLoadError::BadMagic);
+        }
+        let version = u32::from_le_bytes(take(&mut bytes, 4)?.try_into().unwrap());
+        if version != VERSION {
+            return Err(TablebaseLoadError::UnsupportedVersion(version));
+        }
+        let rules_len = take(&mut bytes, 1)?[0] as usize;
+        let rules_id = String::from_utf8_lossy(take(&mut bytes, rules_len)?).into_owned();
+        let Some(rules) = RuleSet::from_id(&rules_id) else {
+            return Err(TablebaseLoadError::BadRules(rules_id));
+        };
+        let name_len = take(&mut bytes, 1)?[0] as usize;
+        let name = String::from_utf8_lossy(take(&mut bytes, name_len)?).into_owned();
+        let Some(material) = Material::from_name(&name) else {
+            return Err(TablebaseLoadError::BadMaterial(name));
+        };
+        let num_positions = u64::from_le_bytes(take(&mut bytes, 8)?.try_into().unwrap()) as usize;
+        if num_positions != material.num_positions() {
+            return Err(TablebaseLoadError::WrongSize {expected: material.num_positions(), found: num_positions});
+        }
+        let width = take(&mut bytes, 1)?[0] as usize;
+        let compressed_len = u64::from_le_bytes(take(&mut bytes, 8)?.try_into().unwrap()) as usize;
+        let raw = packbits::decompress(take(&mut bytes, compressed_len)?, num_positions * width)
+            .ok_or(TablebaseLoadError::UnexpectedEof)?;
+        let values: Vec<u16> = match width {
+            1 => raw.iter().map(|&b| b as u16).collect(),
+            _ => raw.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect()
+        };
+        if values.len() != num_positions {
+            return Err(TablebaseLoadError::WrongSize {expected: num_positions, found: values.len()});
+        }
+        Ok(Table {material, rules, values})
+    }
+}
+
+//Tables are long stretches of draws broken up by fairly noisy distances,
+//which packbits handles well: runs are collapsed, and noise costs at most one extra byte per 128.
+pub mod packbits {
+    //Header byte n, read as an i8:
+    //  0..=127: the next n + 1 bytes are literal.
+    //  -127..=-1: the next byte is repeated 1 - n times.
+    pub fn compress(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len() / 4);
+        let mut i = 0;
+        while i < data.len() {
+            let mut run = 1;
+            while i + run < data.len() && run < 128 && data[i + run] == data[i] {
+                run += 1;
+            }
+            if run >= 3 {
+                out.push((1 - run as i16) as i8 as u8);
+                out.push(data[i]);
+                i += run;
+                continue;
+            }
+            let start = i;
+            while i < data.len() && i - start < 128 {
+                if i + 2 < data.len() && data[i] == data[i + 1] && data[i] == data[i + 2] {
+                    break;
+                }
+                i += 1;
+            }
+            out.push((i - start - 1) as u8);
+            out.extend_from_slice(&data[start..i]);
+        }
+        out
+    }
+
+    //None if the data is truncated or doesn't decompress to exactly `len` bytes.
+    pub fn decompress(data: &[u8], len: usize) -> Option<Vec<u8>> {
+        let mut out = Vec::with_capacity(len);
+        let mut i = 0;
+        while i < data.len() {
+            let header = data[i] as i8;
+            i += 1;
+            if header >= 0 {
+                let n = header as usize + 1;
+                out.extend_from_slice(data.get(i..i + n)?);
+                i += n;
+            } else if header != -128 {
+                let byte = *data.get(i)?;
+                out.extend(std::iter::repeat_n(byte, (1 - header as i16) as usize));
+                i += 1;
+            }
+            if out.len() > len {
+                return None;
+            }
+        }
+        if out.len() == len {Some(out)} else {None}
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Tablebase {
+    rules: RuleSet,
+    tables: HashMap<Material, Table>
+}
+
+impl Tablebase {
+    pub fn new(rules: RuleSet) -> Self {
+        Tablebase {rules, tables: HashMap::new()}
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    pub fn materials(&self) -> impl Iterator<Item = &Material> {
+        self.tables.keys()
+    }
+
+    //None if the position's material hasn't been generated or loaded.
+    pub fn probe(&self, board: &GameBoard, whose_turn: PlayerColor) -> Option<TablebaseResult> {
+        let material = Material::from_board(board)?;
+        self.tables.get(&material)?.probe(board, whose_turn)
+    }
+
+    //Also generates every smaller table this one can reach by captures, if they aren't there already.
+    pub fn generate(&mut self, material: &Material) {
+        if self.tables.contains_key(material) {
+            return;
+        }
+        for smaller in material.after_captures() {
+            self.generate(&smaller);
+        }
+        let table = self.generate_table(material);
+        self.tables.insert(material.clone(), table);
+    }
+
+    //Fills `quiet` with the start and end squares of the moves that don't take anything, and `others` with
+    //the rest. The packed generator is much quicker, and covers everything unless the rules have house pieces
+    //or coordinating kings, or the position can't be packed.
+    fn moves_from(
+        &self,
+        board: &GameBoard,
+        whose_turn: PlayerColor,
+        packed: &mut PackedMoveList,
+        quiet: &mut Vec<(Rankfile, Rankfile)>,
+        others: &mut Vec<MoveData>
+    ) {
+        quiet.clear();
+        others.clear();
+        if self.rules.pieces == PieceSet::ultima() && !self.rules.king_coordinates
+            && generate_packed_moves(board, whose_turn, &self.rules, packed)
+        {
+            for mv in packed.iter() {
+                if mv.is_capture() || mv.is_suicide() {
+                    others.extend(mv.to_move(board));
+                } else {
+                    quiet.push((mv.start(), mv.end()));
+                }
+            }
+            return;
+        }
+        for mv in move_validation::get_all_legal_moves_for_color(board, whose_turn, &self.rules) {
+            if mv.kind == MoveKind::Normal && mv.capture_details.is_empty() {
+                quiet.push((mv.start, mv.end));
+            } else {
+                others.push(mv);
+            }
+        }
+    }
+
+    //Retrograde analysis over the table's move graph.
+    //One forward pass generates every position's moves. King captures and moves into smaller tables are
+    //scored straight away, and moves within the table are kept as edges. Positions are then resolved
+    //in order of distance to mate, each passing its result back along the edges to the positions that
+    //lead to it: a loss makes every parent a win one ply further on, and a parent whose moves all turn out
+    //to be wins for the opponent loses one ply after the longest of them.
+    //Generating un-moves would save storing the edges, but Ultima's captures make un-moves very awkward.
+    fn generate_table(&self, material: &Material) -> Table {
+        let size = material.num_positions();
+        let mut values = vec![UNRESOLVED; size];
+        //Moves within the table whose result isn't known yet.
+        let mut unresolved_children = vec![0u32; size];
+        //The longest of the children's wins known so far.
+        let mut longest_child_win = vec![0u16; size];
+        //Positions with a move to a draw or a win can't lose.
+        let mut cannot_lose = vec![false; size];
+        //Positions to resolve, by distance to mate. The first entry for a position is the right one.
+        let mut queue: Vec<Vec<u32>> = vec![];
+        fn push(queue: &mut Vec<Vec<u32>>, index: usize, ply: u16) {
+            if queue.len() <= ply as usize {
+                queue.resize(ply as usize + 1, vec![]);
+            }
+            queue[ply as usize].push(index as u32);
+        }
+
+        let mut packed = PackedMoveList::new();
+        let mut quiet = vec![];
+        let mut others = vec![];
+        let mut child_offsets: Vec<u32> = Vec::with_capacity(size + 1);
+        let mut children: Vec<u32> = vec![];
+        child_offsets.push(0);
+        for index in 0..size {
+            let first_child = children.len();
+            match material.board_at(index) {
+                None => values[index] = DRAW, //(never probed)
+                Some((board, whose_turn, squares)) => {
+                    self.moves_from(&board, whose_turn, &mut packed, &mut quiet, &mut others);
+                    if quiet.is_empty() && others.is_empty() {
+                        values[index] = DRAW;
+                    }
+                    for &(start, end) in &quiet {
+                        if let Some(child_index) = material.index_after_quiet_move(&squares, whose_turn, start, end) {
+                            children.push(child_index as u32);
+                        }
+                    }
+                    let mut shortest_win = None;
+                    for mv in others.drain(..) {
+                        if captures_king(&board, &mv, whose_turn) {
+                            shortest_win = Some(1);
+                            break;
+                        }
+                        let mut child = board.clone();
+                        execute_move(&mut child, mv, whose_turn);
+                        let Some(child_material) = Material::from_board(&child) else {
+                            cannot_lose[index] = true;
+                            continue;
+                        };
+                        if child_material == *material {
+                            if let Some(child_index) = material.index_of(&child, whose_turn.opposite()) {
+                                children.push(child_index as u32);
+                            }
+                            continue;
+                        }
+                        let value = self.tables.get(&child_material)
+                            .and_then(|table| Some(table.values[child_material.index_of(&child, whose_turn.opposite())?]))
+                            .unwrap_or(DRAW);
+                        if value == DRAW {
+                            cannot_lose[index] = true;
+                        } else if value % 2 == 1 {
+                            longest_child_win[index] = longest_child_win[index].max(value);
+                        } else {
+                            shortest_win = Some(shortest_win.map_or(value + 1, |win: u16| win.min(value + 1)));
+                        }
+                    }
+                    if shortest_win == Some(1) {
+                        children.truncate(first_child);
+                    }
+                    children[first_child..].sort_unstable();
+                    let mut unique = first_child;
+                    for i in first_child..children.len() {
+                        if i == first_child || children[i] != children[unique - 1] {
+                            children[unique] = children[i];
+                            unique += 1;
+                        }
+                    }
+                    children.truncate(unique);
+                    unresolved_children[index] = (unique - first_child) as u32;
+                    if let Some(win) = shortest_win {
+                        cannot_lose[index] = true;
+                        push(&mut queue, index, win);
+                    } else if values[index] == UNRESOLVED && unresolved_children[index] == 0 && !cannot_lose[index] {
+                        push(&mut queue, index, longest_child_win[index] + 1);
+                    }
+                }
+            }
+            child_offsets.push(children.len() as u32);
+        }
+
+        //The same edges, from each child back to its parents.
+        let mut parent_offsets = vec![0u32; size + 1];
+        for &child in &children {
+            parent_offsets[child as usize + 1] += 1;
+        }
+        for i in 0..size {
+            parent_offsets[i + 1] += parent_offsets[i];
+        }
+        let mut parents = vec![0u32; children.len()];
+        let mut next = parent_offsets.clone();
+        for parent in 0..size {
+            for &child in &children[child_offsets[parent] as usize..child_offsets[parent + 1] as usize] {
+                parents[next[child as usize] as usize] = parent as u32;
+                next[child as usize] += 1;
+            }
+        }
+        drop(children);
+
+        let mut ply = 1;
+        while ply < queue.len() {
+            for index in std::mem::take(&mut queue[ply]) {
+                let index = index as usize;
+                if values[index] != UNRESOLVED {continue}
+                let value = ply as u16;
+                values[index] = value;
+                for &parent in &parents[parent_offsets[index] as usize..parent_offsets[index + 1] as usize] {
+                    let parent = parent as usize;
+                    if values[parent] != UNRESOLVED {continue}
+                    if value.is_multiple_of(2) {
+                        cannot_lose[parent] = true;
+                        push(&mut queue, parent, value + 1);
+                    } else {
+                        longest_child_win[parent] = longest_child_win[parent].max(value);
+                        unresolved_children[parent] -= 1;
+                        if unresolved_children[parent] == 0 && !cannot_lose[parent] {
+                            push(&mut queue, parent, longest_child_win[parent] + 1);
+                        }
+                    }
+                }
+            }
+            ply += 1;
+        }
+        for value in values.iter_mut() {
+            if *value == UNRESOLVED {
+                *value = DRAW;
+            }
+        }
+        Table {material: material.clone(), rules: self.rules, values}
+    }
+
+    //Tables generated under other rules are turned away.
+    pub fn insert(&mut self, table: Table) -> Result<(), TablebaseLoadError> {
+        if table.rules != self.rules {
+            return Err(TablebaseLoadError::WrongRules {material: table.material.name(), rules: table.rules.id()});
+        }
+        self.tables.insert(table.material.clone(), table);
+        Ok(())
+    }
+
+    //One file per material, named after it, e.g. `KWvK.ultb`.
+    pub fn save_dir(&self, dir: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::create_dir_all(&dir)?;
+        for (material, table) in &self.tables {
+            let path = dir.as_ref().join(format!("{}.{}", material.name(), FILE_EXTENSION));
+            std::fs::write(path, table.to_bytes())?;
+        }
+        Ok(())
+    }
+
+    //Every table has to have been generated under `rules`.
+    pub fn load_dir(dir: impl AsRef<Path>, rules: RuleSet) -> Result<Self, TablebaseLoadError> {
+        let mut tablebase = Self::new(rules);
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(FILE_EXTENSION) {
+                continue;
+            }
+            tablebase.insert(Table::from_bytes(&std::fs::read(path)?)?)?;
+        }
+        Ok(tablebase)
+    }
+}
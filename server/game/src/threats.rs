@@ -0,0 +1,162 @@
+use super::datatypes::{
+        board::{GameBoard,
+            rankfile::Rankfile
+        },
+        piece::{UltimaPiece, UltimaPieceType, PlayerColor},
+        moves::MoveData
+    };
+use super::logic::move_validation;
+use super::notation::square_to_string;
+use super::rules::RuleSet;
+
+//Which pieces each side could capture next move, and which pieces can't move at all.
+//Each piece captures in its own way, so the only reliable way to find threats is to generate the moves.
+
+//A piece that can be captured, and every move that would capture it.
+#[derive(Clone)]
+pub struct Threat {
+    pub square: Rankfile,
+    pub piece: UltimaPiece,
+    pub moves: Vec<MoveData>
+}
+
+//Everything `attacker` could capture if it were `attacker`'s move.
+#[derive(Clone)]
+pub struct ThreatMap {
+    pub attacker: PlayerColor,
+    //In board order, a1 first.
+    pub threats: Vec<Threat>
+}
+
+impl ThreatMap {
+    pub fn threat_at(&self, square: Rankfile) -> Option<&Threat> {
+        self.threats.iter().find(|threat| threat.square == square)
+    }
+
+    pub fn is_threatened(&self, square: Rankfile) -> bool {
+        self.threat_at(square).is_some()
+    }
+
+    pub fn threatened_squares(&self) -> impl Iterator<Item = Rankfile> + '_ {
+        self.threats.iter().map(|threat| threat.square)
+    }
+}
+
+//How the freezing piece's `PieceBehavior::immobilizes` applies.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ImmobilizationRule {
+    //It freezes every adjacent enemy, like an immobilizer.
+    FreezesNeighbours,
+    //It only freezes adjacent enemies of this type, like a chameleon imitating an immobilizer.
+    FreezesOnly(UltimaPieceType)
+}
+
+impl ImmobilizationRule {
+    //`by` is the freezing piece.
+    pub fn description(&self, by: UltimaPieceType) -> String {
+        match self {
+            ImmobilizationRule::FreezesNeighbours => format!("next to an enemy {}", by.name()),
+            ImmobilizationRule::FreezesOnly(piece_type) => format!("{} {} next to an enemy {}", article(piece_type.name()), piece_type.name(), by.name())
+        }
+    }
+}
+
+fn article(word: &str) -> &'static str {
+    if word.starts_with(['a', 'e', 'i', 'o', 'u']) {"an"} else {"a"}
+}
+
+//One of the pieces freezing an immobilized piece.
+#[derive(Clone, Copy)]
+pub struct ImmobilizingPiece {
+    pub square: Rankfile,
+    pub piece: UltimaPiece,
+    pub rule: ImmobilizationRule
+}
+
+//A frozen piece, and why it's frozen.
+#[derive(Clone)]
+pub struct Immobilization {
+    pub square: Rankfile,
+    pub piece: UltimaPiece,
+    pub immobilized_by: Vec<ImmobilizingPiece>,
+    //Whether the rules let it remove itself from the board instead (`RuleSet::immobilized_suicide`).
+    pub can_commit_suicide: bool,
+    //Whether it can still capture the only piece freezing it by moving directly away
+    //(`RuleSet::chameleon_withdraws_from_immobilizer`). Those moves are its only ones besides suicide.
+    pub can_withdraw: bool
+}
+
+impl Immobilization {
+    //E.g. "The pawn on c4 can't move: it's next to an enemy immobilizer (immobilizer on d5)."
+    pub fn explanation(&self) -> String {
+        let can = if self.can_withdraw {"can only withdraw from the piece freezing it"} else {"can't move"};
+        let reasons: Vec<String> = self.immobilized_by.iter().map(|by| {
+            format!("{} ({} on {})", by.rule.description(by.piece.piece_type), by.piece.piece_type.name(), square_to_string(by.square))
+        }).collect();
+        let mut out = format!(
+            "The {} on {} {}: it's {}.",
+            self.piece.piece_type.name(),
+            square_to_string(self.square),
+            can,
+            reasons.join(", and ")
+        );
+        if self.can_commit_suicide {
+            out += " It can still remove itself from the board.";
+        }
+        out
+    }
+}
+
+impl GameBoard {
+    pub fn threats_by(&self, attacker: PlayerColor, rules: &RuleSet) -> ThreatMap {
+        let mut threats: Vec<Threat> = vec![];
+        for mv in move_validation::get_all_legal_moves_for_color(self, attacker, rules) {
+            for capture in &mv.capture_details {
+                match threats.iter_mut().find(|threat| threat.square == capture.square) {
+                    Some(threat) => threat.moves.push(mv.clone()),
+                    None => threats.push(Threat {square: capture.square, piece: capture.piece, moves: vec![mv.clone()]})
+                }
+            }
+        }
+        threats.sort_by_key(|threat| threat.square.to_unsigned_coords());
+        ThreatMap {attacker, threats}
+    }
+
+    //Pieces of `color` that the opponent could capture if it were the opponent's move.
+    pub fn threats_against(&self, color: PlayerColor, rules: &RuleSet) -> ThreatMap {
+        self.threats_by(color.opposite(), rules)
+    }
+
+    //Why the piece on `square` is frozen, or None if it isn't (or there's no piece there).
+    //A frozen chameleon may still have withdrawals; see `Immobilization::can_withdraw`.
+    pub fn immobilization(&self, square: Rankfile, rules: &RuleSet) -> Option<Immobilization> {
+        let piece = self.get_square(square)?;
+        let immobilized_by: Vec<ImmobilizingPiece> = move_validation::immobilizers(self, square, piece, rules).filter_map(|by| {
+            let by_piece = self.get_square(by)?;
+            let behavior = rules.pieces.behavior(by_piece.piece_type)?;
+            let rule = if rules.pieces.behaviors().all(|target| behavior.immobilizes(target.piece_type())) {
+                ImmobilizationRule::FreezesNeighbours
+            } else {
+                ImmobilizationRule::FreezesOnly(piece.piece_type)
+            };
+            Some(ImmobilizingPiece {square: by, piece: by_piece, rule})
+        }).collect();
+        if immobilized_by.is_empty() {
+            return None;
+        }
+        Some(Immobilization {
+            square,
+            piece,
+            immobilized_by,
+            can_commit_suicide: move_validation::can_commit_suicide(piece, rules),
+            can_withdraw: move_validation::can_withdraw_from_immobilizer(self, square, piece, rules)
+        })
+    }
+
+    pub fn immobilized_pieces(&self, color: PlayerColor, rules: &RuleSet) -> Vec<Immobilization> {
+        self.squares()
+            .filter(|&square| self.get_square(square).is_some_and(|piece| piece.color == color))
+            .filter_map(|square| self.immobilization(square, rules))
+            .collect()
+    }
+}
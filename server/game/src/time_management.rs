@@ -0,0 +1,154 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::datatypes::{
+        board::GameBoard,
+        piece::PlayerColor,
+        moves::MoveData
+    };
+use super::logic::{execute_move, captures_king};
+use super::evaluation::EvaluationBackend;
+use super::search::{Searcher, SearchResult, MAX_SEARCH_DEPTH};
+use super::zobrist::zobrist_hash;
+use super::rules::RuleSet;
+
+//Deciding how long to think on a clock, and thinking on the opponent's time.
+
+//The mover's side of the clock.
+#[derive(Clone, Copy, Debug)]
+pub struct TimeControl {
+    pub remaining: Duration,
+    //Added after every move.
+    pub increment: Duration,
+    //Moves left until the next time control, if there is one.
+    pub moves_to_go: Option<u32>
+}
+
+impl TimeControl {
+    //A fixed amount of time for this one move.
+    pub fn move_time(time: Duration) -> Self {
+        TimeControl {remaining: time, increment: Duration::ZERO, moves_to_go: Some(1)}
+    }
+}
+
+//With no time control coming, plan as if the game lasts this many more moves.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+//Kept back for getting the move out, so the flag never falls while the engine is "done".
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+//How many times the optimum a search may take when it keeps changing its mind.
+const MAXIMUM_OVER_OPTIMUM: u32 = 5;
+
+#[derive(Clone, Copy, Debug)]
+pub struct TimeBudget {
+    //When to stop after a completed iteration, if the best move has been stable.
+    pub optimum: Duration,
+    //When to stop no matter what.
+    pub maximum: Duration
+}
+
+impl TimeBudget {
+    pub fn new(time_control: &TimeControl) -> Self {
+        let usable = time_control.remaining.saturating_sub(MOVE_OVERHEAD);
+        let moves_to_go = time_control.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let optimum = (usable / moves_to_go + time_control.increment * 3 / 4).min(usable);
+        //Never bet more than most of what's left on one move, unless it's the last one before the time control.
+        let maximum = (optimum * MAXIMUM_OVER_OPTIMUM).min(usable.mul_f64(0.8)).max(optimum);
+        TimeBudget {optimum, maximum}
+    }
+
+    //The optimum, stretched by how often the best move changed recently (see `Searcher::search_multi_pv`).
+    pub fn optimum_with_instability(&self, best_move_changes: f64) -> Duration {
+        self.optimum.mul_f64(1.0 + best_move_changes).min(self.maximum)
+    }
+}
+
+//A search's time budget and when it started counting down.
+//Shared so that a ponder search can be put on the clock from another thread.
+#[derive(Clone, Default)]
+pub struct SearchClock {
+    started: Arc<Mutex<Option<(Instant, TimeBudget)>>>
+}
+
+impl SearchClock {
+    //A clock that hasn't started yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start(&self, budget: TimeBudget) {
+        *self.started.lock().expect("search clock poisoned") = Some((Instant::now(), budget));
+    }
+
+    pub fn budget(&self) -> Option<(Instant, TimeBudget)> {
+        *self.started.lock().expect("search clock poisoned")
+    }
+}
+
+//A search of the position after the reply we expect, run while the opponent thinks.
+//If they play it, `ponderhit` puts the search on our clock and it carries on from where it got to;
+//if they don't, `stop` it and search the real position.
+pub struct PonderHandle {
+    pub ponder_move: MoveData,
+    //Of the position after the ponder move.
+    expected_hash: u64,
+    stop_flag: Arc<AtomicBool>,
+    clock: SearchClock,
+    thread: thread::JoinHandle<SearchResult>
+}
+
+impl PonderHandle {
+    //The opponent played the ponder move. The search now has until `time_control` runs out.
+    pub fn ponderhit(&self, time_control: &TimeControl) {
+        self.clock.start(TimeBudget::new(time_control));
+    }
+
+    //Whether this is the position the search is pondering, i.e. whether the opponent played the ponder move.
+    pub fn is_hit(&self, board: &GameBoard, whose_turn: PlayerColor) -> bool {
+        zobrist_hash(board, whose_turn) == self.expected_hash
+    }
+
+    pub fn stop(&self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    //Waits for the search to run out of time (after a ponderhit) or stop.
+    pub fn join(self) -> SearchResult {
+        self.thread.join().expect("ponder thread panicked")
+    }
+}
+
+//`board` is the position after our move, with the opponent (`opponent`) to play `ponder_move`,
+//usually the second move of our principal variation.
+//None if the ponder move takes our king, since then there's nothing left to think about.
+pub fn spawn_ponder(
+    board: &GameBoard,
+    opponent: PlayerColor,
+    ponder_move: MoveData,
+    rules: RuleSet,
+    backend: Arc<EvaluationBackend>
+) -> Option<PonderHandle> {
+    if captures_king(board, &ponder_move, opponent) {
+        return None;
+    }
+    let mut position = board.clone();
+    execute_move(&mut position, ponder_move.clone(), opponent);
+    let expected_hash = zobrist_hash(&position, opponent.opposite());
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let clock = SearchClock::new();
+    let thread_stop_flag = stop_flag.clone();
+    let thread_clock = clock.clone();
+    let thread = thread::spawn(move || {
+        let mut searcher = Searcher::new(&backend);
+        searcher.set_rules(rules);
+        searcher.set_stop_flag(thread_stop_flag);
+        searcher.set_clock(Some(thread_clock));
+        searcher.search(&position, opponent.opposite(), MAX_SEARCH_DEPTH)
+    });
+    Some(PonderHandle {ponder_move, expected_hash, stop_flag, clock, thread})
+}
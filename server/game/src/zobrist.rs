@@ -0,0 +1,94 @@
+use super::datatypes::{
+        board::{GameBoard,
+            rankfile::{Rankfile, MAX_BOARD_SIZE}
+        },
+        piece::{UltimaPiece, UltimaPieceType, PlayerColor}
+    };
+use super::rng::splitmix64;
+
+const NUM_PIECE_KEYS: usize = 2 * 7 * 64;
+const MAX_SIZE: usize = MAX_BOARD_SIZE as usize;
+//For the squares of larger boards that aren't on the standard one.
+const NUM_WIDE_PIECE_KEYS: usize = 2 * 7 * MAX_SIZE * MAX_SIZE;
+const NUM_CUSTOM_TYPES: usize = UltimaPieceType::COUNT - UltimaPieceType::ALL.len();
+//For house pieces, on any square.
+const NUM_CUSTOM_PIECE_KEYS: usize = 2 * NUM_CUSTOM_TYPES * MAX_SIZE * MAX_SIZE;
+
+struct Keys {
+    pieces: [u64; NUM_PIECE_KEYS],
+    side: u64,
+    wide_pieces: [u64; NUM_WIDE_PIECE_KEYS],
+    custom_pieces: [u64; NUM_CUSTOM_PIECE_KEYS]
+}
+
+//Keys are generated at compile time from a fixed seed, so hashes are stable across builds
+//and can be stored in files (opening books, etc).
+//The keys for larger boards and then house pieces come last, so adding them didn't change any standard board's hash.
+const fn generate_keys() -> Keys {
+    let mut keys = Keys {
+        pieces: [0; NUM_PIECE_KEYS],
+        side: 0,
+        wide_pieces: [0; NUM_WIDE_PIECE_KEYS],
+        custom_pieces: [0; NUM_CUSTOM_PIECE_KEYS]
+    };
+    let mut state = 0x756C_7469_6D61; //"ultima"
+    let mut i = 0;
+    while i < NUM_PIECE_KEYS {
+        let (next_state, key) = splitmix64(state);
+        keys.pieces[i] = key;
+        state = next_state;
+        i += 1;
+    }
+    let (next_state, side_key) = splitmix64(state);
+    keys.side = side_key;
+    state = next_state;
+    i = 0;
+    while i < NUM_WIDE_PIECE_KEYS {
+        let (next_state, key) = splitmix64(state);
+        keys.wide_pieces[i] = key;
+        state = next_state;
+        i += 1;
+    }
+    i = 0;
+    while i < NUM_CUSTOM_PIECE_KEYS {
+        let (next_state, key) = splitmix64(state);
+        keys.custom_pieces[i] = key;
+        state = next_state;
+        i += 1;
+    }
+    keys
+}
+
+const KEYS: Keys = generate_keys();
+
+pub fn piece_key(piece: UltimaPiece, location: Rankfile) -> u64 {
+    let (r, f) = location.to_unsigned_coords();
+    if piece.piece_type.is_custom() {
+        let custom_index = piece.color.as_index() * NUM_CUSTOM_TYPES + piece.piece_type.as_index() - UltimaPieceType::ALL.len();
+        return KEYS.custom_pieces[(custom_index * MAX_SIZE + r) * MAX_SIZE + f];
+    }
+    let piece_index = piece.color.as_index() * 7 + piece.piece_type.as_index();
+    if r < 8 && f < 8 {
+        KEYS.pieces[piece_index * 64 + r * 8 + f]
+    } else {
+        KEYS.wide_pieces[(piece_index * MAX_SIZE + r) * MAX_SIZE + f]
+    }
+}
+
+//Xored in when black is to move.
+pub fn side_to_move_key() -> u64 {
+    KEYS.side
+}
+
+pub fn zobrist_hash(board: &GameBoard, whose_turn: PlayerColor) -> u64 {
+    let mut hash = 0;
+    for rf in board.squares() {
+        if let Some(piece) = board.get_square(rf) {
+            hash ^= piece_key(piece, rf);
+        }
+    }
+    if whose_turn == PlayerColor::Black {
+        hash ^= side_to_move_key();
+    }
+    hash
+}
@@ -0,0 +1,105 @@
+use game::*;
+use game::UltimaPieceType::*;
+use game::PlayerColor::*;
+
+mod common;
+use common::*;
+
+#[test]
+fn places_pieces_and_registers_kings() {
+    let board = BoardBuilder::new()
+        .place("e1", piece(King, White))
+        .place("E8", piece(King, Black))
+        .place("d4", piece(Pawn, White))
+        .build()
+        .expect("position should build");
+    assert!(board.get_king_locs(White) == &vec![square("e1")]);
+    assert!(board.get_king_locs(Black) == &vec![square("e8")]);
+    assert_eq!(board.get_square(square("d4")), Some(piece(Pawn, White)));
+}
+
+#[test]
+fn replacing_a_king_unregisters_it() {
+    let board = BoardBuilder::new()
+        .place("e1", piece(King, White))
+        .place("e8", piece(King, Black))
+        .place("a1", piece(King, White))
+        .place("e1", piece(Withdrawer, White))
+        .build()
+        .expect("position should build");
+    assert!(board.get_king_locs(White) == &vec![square("a1")]);
+}
+
+#[test]
+fn reports_the_first_mistake() {
+    let result = BoardBuilder::new()
+        .place("i9", piece(King, White))
+        .place("z", piece(King, Black))
+        .build();
+    assert_eq!(result.err(), Some(BoardBuildError::BadSquare(String::from("i9"))));
+}
+
+#[test]
+fn checks_king_counts() {
+    let missing = BoardBuilder::new().place("e1", piece(King, White)).build();
+    assert_eq!(missing.err(), Some(BoardBuildError::WrongKingCount {color: Black, count: 0}));
+
+    let two = BoardBuilder::new()
+        .place("e1", piece(King, White))
+        .place("d1", piece(King, White))
+        .place("e8", piece(King, Black))
+        .kings_per_side(Some(2))
+        .build();
+    assert_eq!(two.err(), Some(BoardBuildError::WrongKingCount {color: Black, count: 1}));
+
+    assert!(BoardBuilder::new().kings_per_side(None).build().is_ok());
+}
+
+#[test]
+fn pawns_on_back_ranks_only_when_allowed() {
+    let builder = BoardBuilder::new()
+        .place("e1", piece(King, White))
+        .place("e8", piece(King, Black))
+        .place("c8", piece(Pawn, White));
+    assert!(builder.clone().build().is_ok());
+    assert_eq!(
+        builder.forbid_pawns_on_back_ranks().build().err(),
+        Some(BoardBuildError::PawnOnBackRank(String::from("c8")))
+    );
+}
+
+#[test]
+fn odds_remove_pieces_from_the_back_rank() {
+    let board = BoardBuilder::from_setup(StartPosition::Rotational)
+        .give_odds(White, Handicap::Withdrawer)
+        .give_odds(Black, Handicap::Longleaper)
+        .build()
+        .expect("odds position should build");
+    assert_eq!(board.get_square(square("e1")), None);
+    //Black's immobilizer corner is h8, so the longleaper furthest from it is on b8.
+    assert_eq!(board.get_square(square("b8")), None);
+    assert_eq!(board.get_square(square("g8")), Some(piece(Longleaper, Black)));
+
+    let both = BoardBuilder::from_setup(StartPosition::Rotational)
+        .give_odds(White, Handicap::WithdrawerAndImmobilizer)
+        .build()
+        .expect("odds position should build");
+    assert_eq!(both.get_square(square("a1")), None);
+    assert_eq!(both.get_square(square("e1")), None);
+}
+
+#[test]
+fn odds_need_the_piece() {
+    let result = BoardBuilder::from_setup(StartPosition::Rotational)
+        .give_odds(White, Handicap::Withdrawer)
+        .give_odds(White, Handicap::Withdrawer)
+        .build();
+    assert_eq!(result.err(), Some(BoardBuildError::MissingHandicapPiece {color: White, piece_type: Withdrawer}));
+}
+
+#[test]
+fn handicap_names_round_trip() {
+    for handicap in Handicap::ALL {
+        assert_eq!(Handicap::from_name(handicap.name()), Some(handicap));
+    }
+}
@@ -0,0 +1,48 @@
+//Fixtures shared by the integration tests. Each test file only uses some of them.
+#![allow(dead_code)]
+
+use game::*;
+use game::PlayerColor::*;
+use game::Rng;
+
+pub fn piece(piece_type: UltimaPieceType, color: PlayerColor) -> UltimaPiece {
+    UltimaPiece {piece_type, color}
+}
+
+pub fn square(s: &str) -> Rankfile {
+    parse_square(s).expect("test square should parse")
+}
+
+pub fn position(fen: &str) -> (GameBoard, PlayerColor) {
+    board_from_fen(fen).expect("test position should parse")
+}
+
+//A position reached by `plies` random legal moves from a shuffled start.
+pub fn random_position(seed: u64, plies: usize, rules: &RuleSet) -> (GameBoard, PlayerColor) {
+    let mut board = StartPosition::Shuffled(seed).board();
+    let mut whose_turn = White;
+    let mut rng = Rng::new(seed);
+    for _ in 0..plies {
+        let moves = move_validation::get_all_legal_moves_for_color(&board, whose_turn, rules);
+        if moves.is_empty() {
+            break;
+        }
+        let mv = moves[rng.below(moves.len() as u64) as usize].clone();
+        if captures_king(&board, &mv, whose_turn) {
+            break;
+        }
+        execute_move(&mut board, mv, whose_turn);
+        whose_turn = whose_turn.opposite();
+    }
+    (board, whose_turn)
+}
+
+//The rule sets the move generators are tested under.
+pub fn rule_sets() -> [RuleSet; 4] {
+    [
+        RuleSet::abbott(),
+        RuleSet::default(),
+        RuleSet {pawns_capture_diagonally: true, ..RuleSet::default()},
+        RuleSet {chameleon_captures_king: false, chameleon_withdraws_from_immobilizer: true, ..RuleSet::abbott()}
+    ]
+}
@@ -0,0 +1,105 @@
+use game::*;
+
+//Runs the reference positions in `data/conformance.txt`.
+
+const POSITIONS: &str = include_str!("data/conformance.txt");
+
+//Both presets, and the default with each of the other options flipped, named after the option.
+fn rule_sets() -> [(&'static str, RuleSet); 6] {
+    [
+        ("abbott", RuleSet::abbott()),
+        ("default", RuleSet::default()),
+        ("diagonal-pawns", RuleSet {pawns_capture_diagonally: true, ..RuleSet::default()}),
+        ("no-chameleon-captures-king", RuleSet {chameleon_captures_king: false, ..RuleSet::default()}),
+        ("chameleon-withdraws", RuleSet {chameleon_withdraws_from_immobilizer: true, ..RuleSet::default()}),
+        ("king-coordinates", RuleSet {king_coordinates: true, ..RuleSet::default()})
+    ]
+}
+
+struct Case<'a> {
+    line: usize,
+    fen: &'a str,
+    square: Rankfile,
+    rules: Vec<&'a str>,
+    moves: Vec<String>,
+    citation: &'a str
+}
+
+//Captures can be written in any order.
+fn normalized(mv: &str) -> String {
+    let mut parts = mv.split('x');
+    let movement = parts.next().unwrap_or_default();
+    let mut captures: Vec<&str> = parts.collect();
+    captures.sort();
+    std::iter::once(movement).chain(captures).collect::<Vec<_>>().join("x")
+}
+
+fn cases() -> Vec<Case<'static>> {
+    POSITIONS.lines().enumerate().filter(|(_, line)| {
+        !line.trim().is_empty() && !line.starts_with('#')
+    }).map(|(i, line)| {
+        let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+        let [fen, square, rules, moves, citation] = fields[..] else {
+            panic!("line {}: expected five fields", i + 1);
+        };
+        let rules = if rules == "all" {
+            rule_sets().iter().map(|(name, _)| *name).collect()
+        } else {
+            rules.split(',').map(str::trim).collect()
+        };
+        let mut moves: Vec<String> = moves.split_whitespace().map(normalized).collect();
+        moves.sort();
+        Case {
+            line: i + 1,
+            fen,
+            square: parse_square(square).unwrap_or_else(|| panic!("line {}: bad square {square}", i + 1)),
+            rules,
+            moves,
+            citation
+        }
+    }).collect()
+}
+
+#[test]
+fn rule_set_names_are_known() {
+    let known: Vec<&str> = rule_sets().iter().map(|(name, _)| *name).collect();
+    for case in cases() {
+        for name in &case.rules {
+            assert!(known.contains(name), "line {}: unknown rule set {name}", case.line);
+        }
+    }
+}
+
+#[test]
+fn every_rule_set_is_covered() {
+    let cases = cases();
+    for (name, _) in rule_sets() {
+        assert!(cases.iter().any(|case| case.rules.contains(&name)), "no positions for {name}");
+    }
+}
+
+#[test]
+fn reference_positions() {
+    let mut failures = vec![];
+    for case in cases() {
+        let (board, whose_turn) = board_from_fen(case.fen).unwrap_or_else(|e| panic!("line {}: {e}", case.line));
+        let piece = board.get_square(case.square).unwrap_or_else(|| panic!("line {}: empty square", case.line));
+        assert_eq!(piece.color, whose_turn, "line {}: the piece should be the side to move's", case.line);
+        for (name, rules) in rule_sets() {
+            if !case.rules.contains(&name) {continue}
+            let mut generated: Vec<String> = move_validation::get_all_legal_moves(&board, case.square, piece, &rules).iter()
+                .map(|mv| normalized(&move_to_string(&board, mv)))
+                .collect();
+            generated.sort();
+            generated.dedup();
+            if generated != case.moves {
+                let missing: Vec<&String> = case.moves.iter().filter(|mv| !generated.contains(mv)).collect();
+                let extra: Vec<&String> = generated.iter().filter(|mv| !case.moves.contains(mv)).collect();
+                failures.push(format!(
+                    "line {} under {name} ({}):\n  missing {missing:?}\n  extra {extra:?}", case.line, case.citation
+                ));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,59 @@
+use game::*;
+use game::PlayerColor::*;
+use proptest::prelude::*;
+
+mod common;
+use common::*;
+
+mod reference;
+
+fn production_moves(board: &GameBoard, color: PlayerColor, rules: &RuleSet) -> Vec<reference::ReferenceMove> {
+    let mut moves: Vec<_> = move_validation::get_all_legal_moves_for_color(board, color, rules).iter()
+        .map(reference::ReferenceMove::from_move)
+        .collect();
+    moves.sort();
+    moves
+}
+
+fn check(board: &GameBoard, color: PlayerColor, rules: &RuleSet) -> Result<(), TestCaseError> {
+    let expected = reference::legal_moves(board, color, rules);
+    let generated = production_moves(board, color, rules);
+    let missing: Vec<_> = expected.iter().filter(|mv| !generated.contains(mv)).collect();
+    let extra: Vec<_> = generated.iter().filter(|mv| !expected.contains(mv)).collect();
+    prop_assert!(
+        missing.is_empty() && extra.is_empty() && expected.len() == generated.len(),
+        "{} under {:?}:\n  missing {:?}\n  extra {:?}", board_to_fen(board, color), rules, missing, extra
+    );
+    Ok(())
+}
+
+#[test]
+fn start_positions_agree() {
+    for setup in [StartPosition::Rotational, StartPosition::Abbott, StartPosition::Shuffled(3)] {
+        for rules in rule_sets() {
+            for color in [White, Black] {
+                check(&setup.board(), color, &rules).unwrap();
+            }
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn generators_agree_on_reachable_positions(seed in any::<u64>(), plies in 0usize..80, rules_index in 0usize..4) {
+        let rules = rule_sets()[rules_index];
+        let (board, whose_turn) = random_position(seed, plies, &rules);
+        check(&board, whose_turn, &rules)?;
+        check(&board, whose_turn.opposite(), &rules)?;
+    }
+
+    #[test]
+    fn generators_agree_with_coordinating_kings(seed in any::<u64>(), plies in 0usize..80) {
+        let rules = RuleSet {king_coordinates: true, ..RuleSet::default()};
+        let (board, whose_turn) = random_position(seed, plies, &rules);
+        check(&board, whose_turn, &rules)?;
+        check(&board, whose_turn.opposite(), &rules)?;
+    }
+}
@@ -0,0 +1,119 @@
+use game::*;
+use game::PlayerColor::*;
+
+mod common;
+use common::*;
+
+//Plays `moves` from the setup's start position under `rules`.
+fn record(setup: StartPosition, rules: &RuleSet, moves: &[&str]) -> GameRecord {
+    let mut board = setup.board();
+    let mut whose_turn = White;
+    let mut played = vec![];
+    for text in moves {
+        let mv = parse_move(&board, whose_turn, text, rules).expect("test move should be legal");
+        execute_move(&mut board, mv.clone(), whose_turn);
+        played.push(mv);
+        whose_turn = whose_turn.opposite();
+    }
+    let mut record = GameRecord::new(played);
+    record.set_start_position(setup);
+    record.set_rules(rules);
+    record
+}
+
+#[test]
+fn rule_set_ids_round_trip() {
+    for rules in rule_sets() {
+        assert_eq!(RuleSet::from_id(&rules.id()), Some(rules), "{}", rules.id());
+    }
+    let coordinating = RuleSet {king_coordinates: true, ..RuleSet::default()};
+    assert_eq!(RuleSet::from_id(&coordinating.id()), Some(coordinating));
+    assert_eq!(RuleSet::default().id(), "default");
+    assert_eq!(RuleSet::from_id("abbott,no-chameleon-captures-king").map(|rules| rules.chameleon_captures_king), Some(false));
+    assert_eq!(RuleSet::from_id("default,castling"), None);
+    assert_eq!(RuleSet::from_id("baroque"), None);
+    let withdrawing = RuleSet {chameleon_withdraws_from_immobilizer: true, ..RuleSet::default()};
+    assert_eq!(withdrawing.id(), "default,chameleon-withdraws");
+    assert_eq!(RuleSet {immobilized_suicide: true, ..RuleSet::default()}, RuleSet::abbott());
+}
+
+#[test]
+fn replays_under_the_recorded_rules() {
+    //The immobilizer on f6 freezes black's f-pawn, which then removes itself.
+    //Only Abbott's rules allow that.
+    let game = record(StartPosition::Rotational, &RuleSet::abbott(), &["b2-b5", "h7-h5", "a1-f6", "f7-f7"]);
+    assert!(game.moves[3].kind == MoveKind::Suicide);
+    let text = game.to_text();
+    assert!(text.contains("[Rules \"abbott\"]"));
+
+    let replayed = GameRecord::from_text(&text).expect("record should reload");
+    assert_eq!(replayed.rules(), RuleSet::abbott());
+    assert!(replayed.moves == game.moves);
+
+    let without_rules: String = text.lines().filter(|line| !line.starts_with("[Rules")).collect::<Vec<_>>().join("\n");
+    assert!(matches!(GameRecord::from_text(&without_rules), Err(GameRecordError::IllegalMove {ply: 4, ..})));
+}
+
+#[test]
+fn records_without_rules_use_the_defaults() {
+    let game = GameRecord::from_text("1. b2-b5 h7-h5\n").expect("record should load");
+    assert_eq!(game.rules(), RuleSet::default());
+    assert_eq!(game.moves.len(), 2);
+}
+
+#[test]
+fn unknown_rules_are_rejected() {
+    assert_eq!(
+        GameRecord::from_text("[Rules \"shogi\"]\n\n1. b2-b5\n").err(),
+        Some(GameRecordError::UnknownRules(String::from("shogi")))
+    );
+}
+
+#[test]
+fn records_round_trip_through_text() {
+    let mut game = record(StartPosition::Shuffled(40), &RuleSet {pawns_capture_diagonally: true, ..RuleSet::default()}, &[]);
+    let mut board = game.start_position().board();
+    let mut whose_turn = White;
+    let mut rng = game::Rng::new(30);
+    for _ in 0..12 {
+        let moves = move_validation::get_all_legal_moves_for_color(&board, whose_turn, &game.rules());
+        let mv = moves[rng.below(moves.len() as u64) as usize].clone();
+        execute_move(&mut board, mv.clone(), whose_turn);
+        game.moves.push(mv);
+        whose_turn = whose_turn.opposite();
+    }
+    game.set_tag("White", "alice \"the bold\"");
+    game.set_tag("Black", "bob");
+    game.comments = vec![None; game.moves.len()];
+    game.comments[0] = Some(String::from("an opening {of sorts}"));
+    game.comments[7] = Some(String::from("risky"));
+
+    let text = game.to_text();
+    let reloaded = GameRecord::from_text(&text).expect("record should reload");
+    assert_eq!(reloaded.tag("White"), Some("alice 'the bold'"));
+    assert_eq!(reloaded.start_position(), StartPosition::Shuffled(40));
+    assert_eq!(reloaded.rules(), game.rules());
+    assert!(reloaded.moves == game.moves);
+    assert_eq!(reloaded.comments[0].as_deref(), Some("an opening {of sorts)"));
+    assert_eq!(reloaded.comments[7].as_deref(), Some("risky"));
+    assert_eq!(reloaded.to_text(), text);
+}
+
+#[test]
+fn annotation_flags_a_blunder() {
+    //White's third move puts the immobilizer where black's pawns can take it.
+    let game = record(StartPosition::Rotational, &RuleSet::default(), &["b2-b5", "h7-h5", "a1-f6"]);
+    let settings = AnalysisSettings::default();
+    let backend = EvaluationBackend::HandWritten;
+    let analysis = analyze_game(&game, &settings, &backend);
+    assert_eq!(analysis.len(), 3);
+    assert_eq!(analysis[2].quality, Some(MoveQuality::Blunder));
+    assert!(analysis[2].best_score - analysis[2].played_score >= settings.blunder);
+
+    let annotated = annotate_game(&game, &settings, &backend);
+    let comment = annotated.comments[2].as_deref().expect("the blunder should be annotated");
+    assert!(comment.starts_with("Blunder ("), "{comment}");
+    assert!(comment.contains("Better was "), "{comment}");
+    let reloaded = GameRecord::from_text(&annotated.to_text()).expect("annotated record should reload");
+    assert_eq!(reloaded.comments[2].as_deref(), Some(comment));
+}
@@ -0,0 +1,107 @@
+use game::*;
+use game::UltimaPieceType::*;
+use game::PlayerColor::*;
+
+mod common;
+use common::*;
+
+fn all_moves(board: &GameBoard, color: PlayerColor) -> Vec<MoveData> {
+    move_validation::get_all_legal_moves_for_color(board, color, &RuleSet::default())
+}
+
+#[test]
+fn geometry_bounds() {
+    assert_eq!(Geometry::new(8, 8), Some(Geometry::STANDARD));
+    assert!(Geometry::new(6, 6).is_some());
+    assert!(Geometry::new(10, 12).is_some());
+    assert!(Geometry::new(1, 8).is_none());
+    assert!(Geometry::new(8, MAX_BOARD_SIZE + 1).is_none());
+    let geometry = Geometry::new(5, 7).unwrap();
+    assert_eq!(geometry.squares().count(), 35);
+    assert!(geometry.contains(square("g5")));
+    assert!(!geometry.contains(square("h5")));
+    assert!(!geometry.contains(square("a6")));
+}
+
+#[test]
+fn squares_past_the_ninth_rank_parse() {
+    let rf = square("b10");
+    assert_eq!(rf.to_signed_coords(), (9, 1));
+    assert_eq!(square_to_string(rf), "b10");
+    assert_eq!(parse_square("a0"), None);
+    assert_eq!(parse_square("a13"), None);
+    assert_eq!(parse_square("m1"), None);
+}
+
+#[test]
+fn small_board_fen_round_trip() {
+    let fen = "lcwkco/pppppp/6/6/PPPPPP/OCKWCL w";
+    let (board, whose_turn) = board_from_fen(fen).expect("6x6 fen should parse");
+    assert_eq!(board.geometry(), Geometry::new(6, 6).unwrap());
+    assert_eq!(board.get_square(square("c1")), Some(piece(King, White)));
+    assert_eq!(board.get_square(square("d6")), Some(piece(King, Black)));
+    assert_eq!(board_to_fen(&board, whose_turn), fen);
+}
+
+#[test]
+fn wide_board_fen_round_trip() {
+    let fen = "4k5/10/10/10/10/10/10/10/10/5K4 b";
+    let (board, _) = board_from_fen(fen).expect("10x10 fen should parse");
+    assert_eq!(board.geometry(), Geometry::new(10, 10).unwrap());
+    assert_eq!(board.get_square(square("e10")), Some(piece(King, Black)));
+    assert_eq!(board_to_fen(&board, Black), fen);
+}
+
+#[test]
+fn fen_ranks_must_match_the_first() {
+    assert_eq!(board_from_fen("k5/6/6/6/6/5K/ b").err(), Some(FenError::BadRankLength(1)));
+    assert_eq!(board_from_fen("k5/7/6/6/6/5K w").err(), Some(FenError::BadRankLength(5)));
+    assert_eq!(board_from_fen("k1 w").err(), Some(FenError::WrongNumberOfRanks));
+}
+
+#[test]
+fn sliding_stops_at_the_edge_of_a_small_board() {
+    let (board, _) = board_from_fen("k5/6/6/6/6/I4K w").unwrap();
+    let ends: Vec<Rankfile> = all_moves(&board, White).into_iter()
+        .filter(|mv| mv.start == square("a1"))
+        .map(|mv| mv.end)
+        .collect();
+    assert_eq!(ends.len(), 13);
+    assert!(ends.iter().all(|&rf| board.contains(rf)));
+    assert!(ends.contains(&square("a5")));
+    assert!(ends.contains(&square("e1")));
+    assert!(ends.contains(&square("e5")));
+}
+
+#[test]
+fn kings_and_longleapers_stay_on_a_non_square_board() {
+    let geometry = Geometry::new(5, 7).unwrap();
+    let board = BoardBuilder::with_geometry(geometry)
+        .place("g5", piece(King, White))
+        .place("a1", piece(King, Black))
+        .place("e5", piece(Longleaper, White))
+        .place("f5", piece(Pawn, Black))
+        .build()
+        .expect("position should build");
+    for mv in all_moves(&board, White).iter().chain(&all_moves(&board, Black)) {
+        assert!(board.contains(mv.end), "{} leaves the board", move_to_string(&board, mv));
+    }
+    assert!(all_moves(&board, White).iter().any(|mv| mv.start == square("g5") && mv.end == square("f4")));
+}
+
+#[test]
+fn pieces_cannot_be_placed_off_the_board() {
+    let result = BoardBuilder::with_geometry(Geometry::new(6, 6).unwrap())
+        .place("g1", piece(King, White))
+        .build();
+    assert_eq!(result.err(), Some(BoardBuildError::BadSquare("g1".to_string())));
+}
+
+#[test]
+fn other_shapes_hash_and_evaluate() {
+    let (small, _) = board_from_fen("lcwkco/pppppp/6/6/PPPPPP/OCKWCL w").unwrap();
+    assert_eq!(hand_written::evaluate(&small, White), 0);
+    let (wide, _) = board_from_fen("k9/10/10/10/10/10/10/10/10/9K w").unwrap();
+    let (moved, _) = board_from_fen("k9/10/10/10/10/10/10/10/9K/10 w").unwrap();
+    assert_ne!(zobrist_hash(&wide, White), zobrist_hash(&moved, White));
+}
@@ -0,0 +1,143 @@
+use game::*;
+use game::nnue::*;
+use proptest::prelude::*;
+
+mod common;
+use common::*;
+
+//A weights file with small random weights, so the accumulator never wraps.
+fn random_weights(seed: u64) -> Vec<u8> {
+    let mut rng = game::Rng::new(seed);
+    let mut out = vec![];
+    out.extend_from_slice(b"ULNN");
+    out.extend_from_slice(&1u32.to_le_bytes());
+    out.extend_from_slice(&(HIDDEN_SIZE as u32).to_le_bytes());
+    for _ in 0..(NUM_FEATURES + 1) * HIDDEN_SIZE {
+        out.extend_from_slice(&(rng.below(64) as i16 - 32).to_le_bytes());
+    }
+    for _ in 0..2 * HIDDEN_SIZE {
+        out.push(rng.below(256) as u8);
+    }
+    out.extend_from_slice(&(rng.below(2000) as i32 - 1000).to_le_bytes());
+    out
+}
+
+fn random_network(seed: u64) -> Network {
+    Network::from_bytes(&random_weights(seed)).expect("test weights should load")
+}
+
+#[test]
+fn weights_round_trip() {
+    let bytes = random_weights(1);
+    let network = Network::from_bytes(&bytes).unwrap();
+    assert!(network.to_bytes() == bytes);
+}
+
+#[test]
+fn bad_weights_files_are_rejected() {
+    let bytes = random_weights(2);
+    assert!(matches!(Network::from_bytes(&bytes[..bytes.len() - 1]), Err(NnueLoadError::UnexpectedEof)));
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(matches!(Network::from_bytes(&trailing), Err(NnueLoadError::TrailingBytes)));
+    let mut magic = bytes.clone();
+    magic[0] = b'X';
+    assert!(matches!(Network::from_bytes(&magic), Err(NnueLoadError::BadMagic)));
+    let mut hidden = bytes;
+    hidden[8] = 64;
+    assert!(matches!(Network::from_bytes(&hidden), Err(NnueLoadError::HiddenSizeMismatch {expected: HIDDEN_SIZE, found: 64})));
+}
+
+#[test]
+fn huge_output_bias_does_not_overflow() {
+    let mut bytes = random_weights(3);
+    let len = bytes.len();
+    bytes[len - 4..].copy_from_slice(&i32::MAX.to_le_bytes());
+    let backend = EvaluationBackend::Nnue(Network::from_bytes(&bytes).unwrap());
+    let board = GameBoard::new_in_start_position();
+    assert_eq!(backend.evaluate(&board, PlayerColor::White), MAX_OUTPUT);
+}
+
+#[test]
+fn search_agrees_with_a_full_refresh() {
+    //The same tree searched with the accumulator kept up to date and with a refresh per position.
+    let network = random_network(4);
+    let board = GameBoard::new_in_start_position();
+    let result = search(&board, PlayerColor::White, 2, &EvaluationBackend::Nnue(network.clone()));
+    let expected = move_validation::get_all_legal_moves_for_color(&board, PlayerColor::White, &RuleSet::default()).into_iter()
+        .map(|mv| {
+            let mut child = board.clone();
+            execute_move(&mut child, mv, PlayerColor::White);
+            -move_validation::get_all_legal_moves_for_color(&child, PlayerColor::Black, &RuleSet::default()).into_iter()
+                .map(|reply| {
+                    let mut grandchild = child.clone();
+                    execute_move(&mut grandchild, reply, PlayerColor::Black);
+                    let accumulator = Accumulator::new(&network, &grandchild).unwrap();
+                    -network.evaluate(&accumulator, PlayerColor::White)
+                })
+                .max()
+                .unwrap()
+        })
+        .max()
+        .unwrap();
+    assert_eq!(result.score, expected);
+}
+
+#[test]
+fn other_board_shapes_have_no_features() {
+    let network = random_network(5);
+    let backend = EvaluationBackend::Nnue(network.clone());
+    for fen in ["k5/6/6/6/6/5K w", "k9/10/10/10/10/10/10/10/10/9K w"] {
+        let (board, whose_turn) = board_from_fen(fen).unwrap();
+        assert!(Accumulator::new(&network, &board).is_none());
+        //They're scored by the hand-written evaluation instead.
+        assert_eq!(backend.evaluate(&board, whose_turn), EvaluationBackend::HandWritten.evaluate(&board, whose_turn));
+    }
+    let king = UltimaPiece {piece_type: UltimaPieceType::King, color: PlayerColor::White};
+    for perspective in [PlayerColor::White, PlayerColor::Black] {
+        assert!(feature_index(perspective, king, Rankfile::from(7, 7).unwrap()).is_some());
+        assert_eq!(feature_index(perspective, king, Rankfile::from(9, 0).unwrap()), None);
+        assert_eq!(feature_index(perspective, king, Rankfile::from(0, 8).unwrap()), None);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn incremental_updates_match_a_refresh(seed in any::<u64>(), plies in 1usize..60, rules_index in 0usize..4) {
+        let rules = rule_sets()[rules_index];
+        let network = random_network(seed);
+        let mut board = StartPosition::Shuffled(seed).board();
+        let mut whose_turn = PlayerColor::White;
+        let mut accumulator = Accumulator::new(&network, &board).unwrap();
+        let mut rng = game::Rng::new(seed);
+        for _ in 0..plies {
+            let moves = move_validation::get_all_legal_moves_for_color(&board, whose_turn, &rules);
+            if moves.is_empty() {
+                break;
+            }
+            let mv = moves[rng.below(moves.len() as u64) as usize].clone();
+            execute_move_with_accumulator(&mut board, mv, whose_turn, &mut accumulator, &network);
+            prop_assert!(Some(&accumulator) == Accumulator::new(&network, &board).as_ref(), "{}", board_to_fen(&board, whose_turn));
+            if board.get_king_locs(whose_turn.opposite()).is_empty() {
+                break;
+            }
+            whose_turn = whose_turn.opposite();
+        }
+    }
+
+    //The search makes and unmakes moves on one board.
+    #[test]
+    fn undo_restores_every_position(seed in any::<u64>(), plies in 0usize..60, rules_index in 0usize..4) {
+        let rules = rule_sets()[rules_index];
+        let (board, whose_turn) = random_position(seed, plies, &rules);
+        let before = board_to_fen(&board, whose_turn);
+        let mut played = board.clone();
+        for mv in move_validation::get_all_legal_moves_for_color(&board, whose_turn, &rules) {
+            execute_move(&mut played, mv.clone(), whose_turn);
+            undo_move(&mut played, &mv);
+            prop_assert_eq!(board_to_fen(&played, whose_turn), before.clone(), "{}", move_to_string(&board, &mv));
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use game::*;
+use game::PlayerColor::*;
+
+mod common;
+use common::*;
+
+fn played(board: &GameBoard, whose_turn: PlayerColor, moves: &[&str]) -> Vec<MoveData> {
+    let mut board = board.clone();
+    let mut whose_turn = whose_turn;
+    let mut out = vec![];
+    for text in moves {
+        let mv = parse_move(&board, whose_turn, text, &RuleSet::default()).expect("test move should be legal");
+        execute_move(&mut board, mv.clone(), whose_turn);
+        out.push(mv);
+        whose_turn = whose_turn.opposite();
+    }
+    out
+}
+
+#[test]
+fn build_save_load_probe() {
+    let start = GameBoard::new_in_start_position();
+    let games = vec![
+        GameRecord::new(played(&start, White, &["b2-b5", "h7-h5"])),
+        GameRecord::new(played(&start, White, &["b2-b5", "g7-g4"]))
+    ];
+    let book = OpeningBook::from_games(&games, 2, &RuleSet::default());
+
+    let path = std::env::temp_dir().join(format!("ultima-book-test-{}.ulbk", std::process::id()));
+    book.save(&path).unwrap();
+    let loaded = OpeningBook::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.len(), book.len());
+
+    let mut rng = game::Rng::new(1);
+    let first = loaded.probe(&start, White, &RuleSet::default(), &mut rng).expect("start position is in the book");
+    assert_eq!(move_to_string(&start, &first), "b2-b5");
+    assert_eq!(loaded.moves_for(&start, White)[0].weight, 2);
+
+    let mut after = start.clone();
+    execute_move(&mut after, first, White);
+    let mut replies: Vec<String> = loaded.moves_for(&after, Black).iter()
+        .map(|book_move| format!("{}-{}", square_to_string(book_move.start), square_to_string(book_move.end)))
+        .collect();
+    replies.sort();
+    assert_eq!(replies, ["g7-g4", "h7-h5"]);
+    assert!(loaded.probe(&after, White, &RuleSet::default(), &mut rng).is_none());
+}
+
+#[test]
+fn games_replay_from_their_own_setup_and_rules() {
+    let abbott = StartPosition::Abbott.board();
+    let mut from_abbott = GameRecord::new(played(&abbott, White, &["b2-b4", "b7-b5"]));
+    from_abbott.set_start_position(StartPosition::Abbott);
+    let mut under_abbott = GameRecord::new(played(&GameBoard::new_in_start_position(), White, &["c2-c4"]));
+    under_abbott.set_rules(&RuleSet::abbott());
+
+    let book = OpeningBook::from_games(&[from_abbott, under_abbott], 2, &RuleSet::default());
+    assert_eq!(book.len(), 2);
+    let first = book.moves_for(&abbott, White);
+    assert_eq!((first.len(), first[0].start, first[0].end), (1, square("b2"), square("b4")));
+    assert!(book.moves_for(&GameBoard::new_in_start_position(), White).is_empty());
+}
+
+#[test]
+fn books_only_cover_the_standard_board() {
+    let (large, _) = board_from_fen("k9/10/10/10/10/10/10/10/10/9K w").unwrap();
+    let mut book = OpeningBook::new();
+    book.add_move(zobrist_hash(&large, White), square("a1"), Rankfile::from(8, 0).unwrap(), 1);
+    assert!(book.is_empty());
+    book.add_move(zobrist_hash(&large, White), square("a1"), square("a2"), 1);
+    assert!(book.moves_for(&large, White).is_empty());
+    let settings = SearchTreeBookSettings::default();
+    assert!(OpeningBook::from_search_tree(&large, White, &settings, &RuleSet::default(), &EvaluationBackend::HandWritten).is_empty());
+}
+
+#[test]
+fn probes_in_proportion_to_weight() {
+    let board = GameBoard::new_in_start_position();
+    let hash = zobrist_hash(&board, White);
+    let mut book = OpeningBook::new();
+    book.add_move(hash, square("b2"), square("b5"), 1);
+    book.add_move(hash, square("g2"), square("g4"), 3);
+    book.add_move(hash, square("c2"), square("c4"), 0);
+    let mut rng = game::Rng::new(7);
+    let mut counts = [0; 2];
+    for _ in 0..4000 {
+        let mv = book.probe(&board, White, &RuleSet::default(), &mut rng).unwrap();
+        assert!(mv.start != square("c2"), "zero-weight moves are never played");
+        counts[(mv.start == square("g2")) as usize] += 1;
+    }
+    assert!((800..1200).contains(&counts[0]), "{counts:?}");
+}
+
+#[test]
+fn illegal_book_moves_are_skipped() {
+    let board = GameBoard::new_in_start_position();
+    let mut book = OpeningBook::new();
+    //The pawn on b2 can't reach b8.
+    book.add_move(zobrist_hash(&board, White), square("b2"), square("b8"), 5);
+    assert!(book.probe(&board, White, &RuleSet::default(), &mut game::Rng::new(0)).is_none());
+}
+
+#[test]
+fn negative_margin_keeps_the_best_moves() {
+    let board = GameBoard::new_in_start_position();
+    let settings = SearchTreeBookSettings {book_plies: 1, search_depth: 1, margin: -50, max_moves_per_position: 100};
+    let book = OpeningBook::from_search_tree(&board, White, &settings, &RuleSet::default(), &EvaluationBackend::HandWritten);
+    let moves = book.moves_for(&board, White);
+    assert!(!moves.is_empty());
+    assert!(moves.iter().all(|book_move| book_move.weight == 1));
+}
+
+#[test]
+fn engine_player_plays_from_the_book() {
+    let board = GameBoard::new_in_start_position();
+    let mut book = OpeningBook::new();
+    book.add_move(zobrist_hash(&board, White), square("h2"), square("h3"), 1);
+    let backend = EvaluationBackend::HandWritten;
+    let mut player = EnginePlayer::new(SkillLevel::strongest(), &backend, 0);
+    player.set_book(Some(&book));
+    let mv = player.choose_move(&board, White, &RuleSet::default()).unwrap();
+    assert_eq!(move_to_string(&board, &mv), "h2-h3");
+}
@@ -0,0 +1,162 @@
+use game::*;
+use game::PlayerColor::*;
+use proptest::prelude::*;
+
+mod common;
+use common::*;
+
+fn details(mv: &MoveData) -> Vec<String> {
+    let mut out: Vec<String> = mv.capture_details.iter()
+        .map(|capture| format!("{:?} {:?} {}", capture.square, capture.piece, capture.kind))
+        .collect();
+    out.sort();
+    out.dedup();
+    out
+}
+
+fn check(board: &GameBoard, color: PlayerColor, rules: &RuleSet) -> Result<(), TestCaseError> {
+    let moves = move_validation::get_all_legal_moves_for_color(board, color, rules);
+    let mut expected = vec![];
+    for mv in &moves {
+        let packed = PackedMove::from_move(mv, board);
+        prop_assert!(packed.is_some(), "{} doesn't pack", move_to_string(board, mv));
+        let packed = packed.unwrap();
+        let unpacked = packed.to_move(board).expect("packed moves unpack in their own position");
+        prop_assert!((unpacked.start, unpacked.end, unpacked.kind) == (mv.start, mv.end, mv.kind));
+        prop_assert_eq!(details(&unpacked), details(mv));
+        let mut played = board.clone();
+        let mut unpacked_played = board.clone();
+        execute_move(&mut played, mv.clone(), color);
+        execute_move(&mut unpacked_played, unpacked, color);
+        prop_assert_eq!(board_to_fen(&unpacked_played, color), board_to_fen(&played, color));
+        expected.push(packed.bits());
+    }
+    let mut list = PackedMoveList::new();
+    prop_assert!(generate_packed_moves(board, color, rules, &mut list));
+    let mut generated: Vec<u32> = list.iter().map(|mv| mv.bits()).collect();
+    expected.sort();
+    generated.sort();
+    prop_assert_eq!(generated, expected, "{}", board_to_fen(board, color));
+    Ok(())
+}
+
+#[test]
+fn packed_moves_are_four_bytes() {
+    assert_eq!(std::mem::size_of::<PackedMove>(), 4);
+}
+
+#[test]
+fn start_positions_pack() {
+    for setup in [StartPosition::Rotational, StartPosition::Abbott, StartPosition::Shuffled(11)] {
+        for rules in rule_sets() {
+            check(&setup.board(), White, &rules).unwrap();
+        }
+    }
+}
+
+#[test]
+fn packs_a_double_leap() {
+    let (board, _) = board_from_fen("7k/8/8/p7/8/p7/8/L6K w").unwrap();
+    let mv = parse_move(&board, White, "a1-a6", &RuleSet::default()).unwrap();
+    let packed = PackedMove::from_move(&mv, &board).unwrap();
+    assert!(packed.is_capture());
+    let captured: Vec<String> = packed.captures(&board).map(square_to_string).collect();
+    assert_eq!(captured, ["a3", "a5"]);
+    assert_eq!(move_to_string(&board, &packed.to_move(&board).unwrap()), "a1-a6xa3xa5");
+}
+
+#[test]
+fn suicide_packs() {
+    let (board, _) = board_from_fen("k7/8/8/3i4/3W4/8/8/7K w").unwrap();
+    let mv = parse_move(&board, White, "d4-d4", &RuleSet::abbott()).unwrap();
+    let packed = PackedMove::from_move(&mv, &board).unwrap();
+    assert!(packed.is_suicide());
+    assert!(!packed.is_capture());
+    assert!(packed.to_move(&board).unwrap() == mv);
+}
+
+#[test]
+fn larger_boards_do_not_pack() {
+    let (board, _) = board_from_fen("k9/10/10/10/10/10/10/10/10/9K w").unwrap();
+    let moves = move_validation::get_all_legal_moves_for_color(&board, White, &RuleSet::default());
+    assert!(PackedMove::from_move(&moves[0], &board).is_none());
+    let mut list = PackedMoveList::new();
+    assert!(!generate_packed_moves(&board, White, &RuleSet::default(), &mut list));
+    assert!(list.is_empty());
+}
+
+#[test]
+fn two_kings_do_not_pack() {
+    //Only the king on h1 coordinates with b7, to take on h7.
+    let (board, _) = board_from_fen("k7/7p/8/8/8/8/1O6/K6K w").unwrap();
+    let mv = parse_move(&board, White, "b2-b7", &RuleSet::default()).unwrap();
+    assert_eq!(move_to_string(&board, &mv), "b2-b7xh7");
+    assert!(PackedMove::from_move(&mv, &board).is_none());
+    let mut list = PackedMoveList::new();
+    assert!(!generate_packed_moves(&board, White, &RuleSet::default(), &mut list));
+    assert!(list.is_empty());
+}
+
+#[test]
+fn small_boards_pack() {
+    let (board, _) = board_from_fen("lcwkco/pppppp/6/6/PPPPPP/OCKWCL w").unwrap();
+    for rules in rule_sets() {
+        check(&board, White, &rules).unwrap();
+    }
+}
+
+//A house immobilizer that offers the same step over and over, more times than a list holds.
+struct Restless;
+
+impl PieceBehavior for Restless {
+    fn piece_type(&self) -> UltimaPieceType {UltimaPieceType::Immobilizer}
+    fn name(&self) -> &'static str {"restless immobilizer"}
+    fn generate_moves(&self, board: &GameBoard, start: Rankfile, color: PlayerColor, _rules: &RuleSet) -> Vec<MoveData> {
+        let piece = UltimaPiece {piece_type: UltimaPieceType::Immobilizer, color};
+        let end = board.los(start, (1, 0)).next().expect("the immobilizer should have room to step");
+        vec![MoveData::new(piece, start, end); MAX_PACKED_MOVES + 1]
+    }
+    fn immobilizes(&self, _target: UltimaPieceType) -> bool {true}
+}
+
+#[test]
+fn full_lists_refuse_moves() {
+    let mut list = PackedMoveList::new();
+    let mv = PackedMove::default();
+    for _ in 0..MAX_PACKED_MOVES {
+        assert!(list.push(mv));
+    }
+    assert!(!list.push(mv));
+    assert_eq!(list.len(), MAX_PACKED_MOVES);
+
+    let (board, _) = board_from_fen("k7/8/8/8/3I4/8/8/7K w").unwrap();
+    let rules = RuleSet {pieces: PieceSet::ultima().with(&Restless), ..RuleSet::default()};
+    assert!(!generate_packed_moves(&board, White, &rules, &mut list));
+    assert!(list.is_empty());
+}
+
+#[test]
+fn unpackable_moves_are_reported() {
+    //e1-e2 takes a2 and e5 by coordination with a5, which the encoding has no room for.
+    let (board, _) = board_from_fen("7k/8/8/O3p3/8/8/p7/4K3 w").unwrap();
+    let coordinating = RuleSet {king_coordinates: true, ..RuleSet::default()};
+    let mut list = PackedMoveList::new();
+    assert!(!generate_packed_moves(&board, White, &coordinating, &mut list));
+    assert!(list.is_empty());
+    //Without the pawns there's nothing to coordinate against, so everything packs.
+    let (board, _) = board_from_fen("7k/8/8/O7/8/8/8/4K3 w").unwrap();
+    assert!(generate_packed_moves(&board, White, &coordinating, &mut list));
+    assert_eq!(list.len(), move_validation::get_all_legal_moves_for_color(&board, White, &coordinating).len());
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn packed_generation_matches_move_validation(seed in any::<u64>(), plies in 0usize..80, rules_index in 0usize..4) {
+        let rules = rule_sets()[rules_index];
+        let (board, whose_turn) = random_position(seed, plies, &rules);
+        check(&board, whose_turn, &rules)?;
+        check(&board, whose_turn.opposite(), &rules)?;
+    }
+}
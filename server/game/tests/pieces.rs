@@ -0,0 +1,162 @@
+use game::*;
+
+mod common;
+use common::*;
+
+//A house withdrawer that only steps one square, like a king, and never captures.
+struct SteppingWithdrawer;
+
+impl PieceBehavior for SteppingWithdrawer {
+    fn piece_type(&self) -> UltimaPieceType {UltimaPieceType::Withdrawer}
+    fn name(&self) -> &'static str {"stepping withdrawer"}
+    fn generate_moves(&self, board: &GameBoard, start: Rankfile, color: PlayerColor, _rules: &RuleSet) -> Vec<MoveData> {
+        let piece = UltimaPiece {piece_type: UltimaPieceType::Withdrawer, color};
+        start.surrounding_rankfiles()
+            .filter(|&end| board.get_square(end).is_none())
+            .map(|end| MoveData::new(piece, start, end))
+            .collect()
+    }
+}
+
+//A house piece, 'G' in FENs, that steps one square and freezes the pawns next to it.
+struct Guard;
+
+const GUARD: UltimaPieceType = UltimaPieceType::custom('G').unwrap();
+
+impl PieceBehavior for Guard {
+    fn piece_type(&self) -> UltimaPieceType {GUARD}
+    fn name(&self) -> &'static str {"guard"}
+    fn generate_moves(&self, board: &GameBoard, start: Rankfile, color: PlayerColor, _rules: &RuleSet) -> Vec<MoveData> {
+        let piece = UltimaPiece {piece_type: self.piece_type(), color};
+        start.surrounding_rankfiles()
+            .filter(|&end| board.contains(end) && board.get_square(end).is_none())
+            .map(|end| MoveData::new(piece, start, end))
+            .collect()
+    }
+    fn immobilizes(&self, target: UltimaPieceType) -> bool {target == UltimaPieceType::Pawn}
+}
+
+#[test]
+fn default_set_is_ultima() {
+    let pieces = PieceSet::ultima();
+    for piece_type in UltimaPieceType::ALL {
+        assert_eq!(pieces.behavior(piece_type).unwrap().piece_type(), piece_type);
+    }
+    assert_eq!(RuleSet::default().pieces, pieces);
+}
+
+#[test]
+fn registered_behavior_replaces_the_default() {
+    let (board, _) = position("k7/8/8/8/3W4/8/8/7K w");
+    let house = RuleSet {pieces: PieceSet::ultima().with(&SteppingWithdrawer), ..RuleSet::default()};
+    assert!(house != RuleSet::default());
+    let piece = board.get_square(square("d4")).expect("withdrawer should be there");
+    assert_eq!(move_validation::get_all_legal_moves(&board, square("d4"), piece, &house).len(), 8);
+    assert!(move_validation::get_all_legal_moves(&board, square("d4"), piece, &RuleSet::default()).len() > 8);
+}
+
+#[test]
+fn chameleon_copies_only_copyable_pieces() {
+    let pieces = PieceSet::ultima();
+    let rules = RuleSet::default();
+    let copyable: Vec<UltimaPieceType> = pieces.behaviors()
+        .filter(|behavior| behavior.chameleon_can_copy(&rules))
+        .map(|behavior| behavior.piece_type())
+        .collect();
+    assert!(!copyable.contains(&UltimaPieceType::Immobilizer));
+    assert!(!copyable.contains(&UltimaPieceType::Chameleon));
+    let no_king = RuleSet {chameleon_captures_king: false, ..RuleSet::default()};
+    assert!(!pieces.behavior(UltimaPieceType::King).unwrap().chameleon_can_copy(&no_king));
+}
+
+#[test]
+fn chameleon_captures_coordinators_by_coordination() {
+    //Moving c1-c5 puts the chameleon on the 5th rank and the white king on the e-file: e5 is taken.
+    let (board, _) = position("k7/8/8/4o3/8/8/8/2C1K3 w");
+    let piece = board.get_square(square("c1")).expect("chameleon should be there");
+    let moves = move_validation::get_all_legal_moves(&board, square("c1"), piece, &RuleSet::default());
+    let mv = moves.iter().find(|mv| mv.end == square("c5")).expect("c1-c5 should be legal");
+    assert_eq!(mv.capture_details.len(), 1);
+    assert_eq!(mv.capture_details[0].square, square("e5"));
+    assert!(mv.capture_details[0].kind == CaptureKind::ChameleonAs(UltimaPieceType::Coordinator));
+}
+
+#[test]
+fn captures_record_how_they_happen() {
+    use UltimaPieceType::*;
+    let withdrawing = RuleSet {chameleon_withdraws_from_immobilizer: true, ..RuleSet::default()};
+    let cases = [
+        ("7k/8/8/8/4pP2/8/3P4/7K w", "d2-d4", CaptureKind::Custodian, &RuleSet::default()),
+        ("7k/8/8/3p4/3W4/8/8/7K w", "d4-d3", CaptureKind::Withdrawal, &RuleSet::default()),
+        ("7k/8/8/8/8/3p4/8/3L3K w", "d1-d4", CaptureKind::Leap, &RuleSet::default()),
+        ("7k/8/8/8/2p5/8/O7/2K5 w", "a2-a4", CaptureKind::Coordination, &RuleSet::default()),
+        ("7k/8/8/8/8/8/6p1/7K w", "h1-g2", CaptureKind::KingDisplacement, &RuleSet::default()),
+        ("7k/8/8/8/4pP2/8/3C4/7K w", "d2-d4", CaptureKind::ChameleonAs(Pawn), &RuleSet::default()),
+        ("7k/8/8/3w4/3C4/8/8/7K w", "d4-d3", CaptureKind::ChameleonAs(Withdrawer), &RuleSet::default()),
+        ("7k/8/8/8/8/3l4/8/3C3K w", "d1-d4", CaptureKind::ChameleonAs(Longleaper), &RuleSet::default()),
+        ("7k/8/8/8/2o5/8/C7/2K5 w", "a2-a4", CaptureKind::ChameleonAs(Coordinator), &RuleSet::default()),
+        ("8/8/8/8/8/8/6k1/5C1K w", "f1-g2", CaptureKind::ChameleonAs(King), &RuleSet::default()),
+        ("k7/8/8/3i4/3C4/8/8/7K w", "d4-d3", CaptureKind::ChameleonAs(Withdrawer), &withdrawing)
+    ];
+    for (fen, written, kind, rules) in cases {
+        let (board, whose_turn) = position(fen);
+        let mv = parse_move(&board, whose_turn, written, rules).expect("test move should be legal");
+        let kinds: Vec<CaptureKind> = mv.capture_details.iter().map(|capture| capture.kind).collect();
+        assert!(kinds == [kind], "{fen}: {written} captures by {}", describe_captures(&mv));
+        assert!(mv.captures().eq(mv.capture_details.iter().map(|capture| capture.square)));
+        assert_eq!(mv.capture_details[0].piece, board.get_square(mv.capture_details[0].square).unwrap());
+    }
+}
+
+#[test]
+fn custom_pieces_can_be_registered() {
+    assert_eq!(UltimaPieceType::from_char('g'), Some(GUARD));
+    assert_eq!(UltimaPieceType::custom('w'), None);
+    let (board, whose_turn) = position("k7/8/8/3p4/3G4/8/8/7K w");
+    assert_eq!(board_to_fen(&board, whose_turn), "k7/8/8/3p4/3G4/8/8/7K w");
+    let guard = board.get_square(square("d4")).expect("guard should be there");
+
+    //Ultima's pieces don't include it, so it can't move.
+    assert!(PieceSet::ultima().behavior(guard.piece_type).is_none());
+    assert!(move_validation::get_all_legal_moves(&board, square("d4"), guard, &RuleSet::default()).is_empty());
+
+    let house = RuleSet {pieces: PieceSet::ultima().with(&Guard), ..RuleSet::default()};
+    assert_eq!(house.pieces.behaviors().count(), 8);
+    assert_eq!(move_validation::get_all_legal_moves(&board, square("d4"), guard, &house).len(), 7);
+    let frozen = board.immobilization(square("d5"), &house).expect("the pawn is frozen");
+    assert_eq!(frozen.immobilized_by[0].rule, ImmobilizationRule::FreezesOnly(UltimaPieceType::Pawn));
+    assert!(board.immobilization(square("d5"), &RuleSet::default()).is_none());
+}
+
+//A piece of any type that never moves.
+struct Statue(UltimaPieceType);
+
+impl PieceBehavior for Statue {
+    fn piece_type(&self) -> UltimaPieceType {self.0}
+    fn name(&self) -> &'static str {"statue"}
+    fn generate_moves(&self, _board: &GameBoard, _start: Rankfile, _color: PlayerColor, _rules: &RuleSet) -> Vec<MoveData> {
+        vec![]
+    }
+}
+
+#[test]
+fn custom_letters_are_checked() {
+    for c in ['1', '@', '[', '`', '{', 'K', 'p', 'é'] {
+        assert_eq!(UltimaPieceType::custom(c), None, "{c:?}");
+    }
+    //Every letter that's left has its own slot in a piece set.
+    let mut pieces = PieceSet::ultima();
+    let mut custom = vec![];
+    for c in 'A'..='Z' {
+        let Some(piece_type) = UltimaPieceType::custom(c) else {continue};
+        assert_eq!(UltimaPieceType::custom(c.to_ascii_lowercase()), Some(piece_type));
+        assert_eq!(piece_type.to_char(), c);
+        assert!(piece_type.as_index() < UltimaPieceType::COUNT);
+        pieces = pieces.with(Box::leak(Box::new(Statue(piece_type))));
+        custom.push(piece_type);
+    }
+    assert_eq!(custom.len(), 26 - UltimaPieceType::ALL.len());
+    for piece_type in custom {
+        assert_eq!(pieces.behavior(piece_type).unwrap().piece_type(), piece_type);
+    }
+}
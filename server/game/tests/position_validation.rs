@@ -0,0 +1,69 @@
+use game::*;
+
+mod common;
+use common::*;
+
+fn kinds(problems: &[PositionProblem]) -> Vec<PositionProblemKind> {
+    problems.iter().map(|problem| problem.kind.clone()).collect()
+}
+
+#[test]
+fn start_positions_are_legal() {
+    for setup in [StartPosition::Rotational, StartPosition::Abbott, StartPosition::Shuffled(3)] {
+        assert!(validate_position(&setup.board(), PlayerColor::White, &RuleSet::default()).is_empty());
+    }
+}
+
+#[test]
+fn king_counts() {
+    let (board, turn) = position("k7/8/8/8/8/8/8/8 w");
+    let problems = validate_position(&board, turn, &RuleSet::default());
+    assert_eq!(problems, vec![PositionProblem {square: None, kind: PositionProblemKind::MissingKing {color: PlayerColor::White}}]);
+
+    let (board, turn) = position("k6k/8/8/8/8/8/8/K7 w");
+    let problems = validate_position(&board, turn, &RuleSet::default());
+    assert_eq!(problems, vec![PositionProblem {
+        square: Some(square("h8")),
+        kind: PositionProblemKind::TooManyKings {color: PlayerColor::Black}
+    }]);
+}
+
+#[test]
+fn too_much_material() {
+    let (board, turn) = position("k7/8/8/8/8/8/8/WW5K w");
+    let problems = validate_position(&board, turn, &RuleSet::default());
+    assert_eq!(problems, vec![PositionProblem {
+        square: Some(square("b1")),
+        kind: PositionProblemKind::TooManyPieces {
+            piece: UltimaPiece {piece_type: UltimaPieceType::Withdrawer, color: PlayerColor::White},
+            allowed: 1
+        }
+    }]);
+}
+
+#[test]
+fn side_not_to_move_in_check() {
+    //The black king on e2 sits next to the white king on d1, which could just take it.
+    let (board, _) = position("8/8/8/8/8/8/4k3/3K4 w");
+    let problems = validate_position(&board, PlayerColor::White, &RuleSet::default());
+    assert_eq!(kinds(&problems), vec![PositionProblemKind::KingCapturable {attackers: vec![square("d1")]}]);
+    assert_eq!(problems[0].square, Some(square("e2")));
+    assert_eq!(problems[0].to_string(), "e2: the side to move can take this king from d1");
+}
+
+#[test]
+fn inconsistent_king_caches() {
+    let mut board = BoardBuilder::new()
+        .place("a8", UltimaPiece {piece_type: UltimaPieceType::King, color: PlayerColor::Black})
+        .place("h1", UltimaPiece {piece_type: UltimaPieceType::King, color: PlayerColor::White})
+        .build()
+        .expect("position should build");
+    //Raw writes bypass the caches.
+    board.set_square(square("h1"), None);
+    board.set_square(square("a1"), Some(UltimaPiece {piece_type: UltimaPieceType::King, color: PlayerColor::White}));
+    let problems = validate_position(&board, PlayerColor::White, &RuleSet::default());
+    assert_eq!(problems, vec![
+        PositionProblem {square: Some(square("h1")), kind: PositionProblemKind::CachedKingMissing {color: PlayerColor::White}},
+        PositionProblem {square: Some(square("a1")), kind: PositionProblemKind::KingNotCached}
+    ]);
+}
@@ -0,0 +1,56 @@
+use game::*;
+
+mod common;
+use common::*;
+
+#[test]
+fn mate_in_one() {
+    //The withdrawer backs away from the king into the corner, the only square it can withdraw to.
+    let (board, whose_turn) = position("8/8/8/8/8/k7/W7/7K w");
+    assert_eq!(mate_distance(&board, whose_turn, 3, &RuleSet::default()), Some(1));
+    let puzzle = puzzle_from_position(&board, whose_turn, 3, &RuleSet::default()).expect("a lone mate in 1 is a puzzle");
+    assert_eq!(puzzle.solution, ["a2-a1xa3"]);
+    assert_eq!(puzzle.themes, [PuzzleTheme::Withdrawal]);
+    assert_eq!(puzzle.to_string(), "8/8/8/8/8/k7/W7/7K w; a2-a1xa3; withdrawal");
+}
+
+#[test]
+fn mate_in_two() {
+    //The immobilizer on b6 freezes the black king, so the white king walks up to it and takes it.
+    //Nothing black can do with its pawns stops that.
+    let (board, whose_turn) = position("4K3/8/OIk5/8/8/6p1/3p4/8 w");
+    assert_eq!(mate_distance(&board, whose_turn, 3, &RuleSet::default()), Some(2));
+    let mating = mating_moves(&board, whose_turn, 3, &RuleSet::default());
+    assert_eq!(mating.len(), 1);
+    assert_eq!(move_to_string(&board, &mating[0].0), "e8-d7");
+    let puzzle = puzzle_from_position(&board, whose_turn, 3, &RuleSet::default()).expect("a lone mate in 2 is a puzzle");
+    assert_eq!(puzzle.solution.len(), 3);
+    assert_eq!(puzzle.solution[0], "e8-d7");
+    assert_eq!(puzzle.solution[2], "d7-c6xc6");
+    assert!(puzzle.themes.contains(&PuzzleTheme::KingDisplacement));
+}
+
+#[test]
+fn two_winning_moves_are_not_a_puzzle() {
+    //The withdrawer takes the king by backing off to either d2 or d1.
+    let (board, whose_turn) = position("8/8/8/8/3k4/3W4/8/K7 w");
+    let mut mating: Vec<String> = mating_moves(&board, whose_turn, 2, &RuleSet::default()).iter()
+        .map(|(mv, _)| move_to_string(&board, mv))
+        .collect();
+    mating.sort();
+    assert_eq!(mating, ["d3-d1xd4", "d3-d2xd4"]);
+    assert!(puzzle_from_position(&board, whose_turn, 2, &RuleSet::default()).is_none());
+}
+
+#[test]
+fn games_are_mined_from_their_own_setup() {
+    //From Abbott's setup, black's coordinator on h8 goes to a1 and crosses the white king on e1.
+    let record = GameRecord::from_text("[Setup \"abbott\"]\n\n1. b2-b5 h7-h4 2. a1-b2 g7-g3 3. b2-b4 h8-a1").unwrap();
+    let puzzles = mine_puzzles(std::slice::from_ref(&record), 1, &RuleSet::default());
+    assert_eq!(puzzles.len(), 1);
+    assert_eq!(puzzles[0].to_string(), "ilcwkclo/pppppp2/8/1P6/1I5p/6p1/P1PPPPPP/1LCWKCLO b; h8-a1xe1; coordinator crossfire");
+
+    let mut under_abbott = record;
+    under_abbott.set_rules(&RuleSet::abbott());
+    assert!(mine_puzzles(&[under_abbott], 1, &RuleSet::default()).is_empty());
+}
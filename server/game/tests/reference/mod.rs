@@ -0,0 +1,275 @@
+//A deliberately naive move generator, written straight from the rules, to check the real one against.
+//It looks at the board one square at a time and never caches anything, so it's slow but easy to read.
+//It only knows the standard pieces, not house `PieceBehavior`s.
+
+use game::*;
+use game::UltimaPieceType::*;
+
+//(rank, file), which may be off the board.
+type Coords = (i8, i8);
+
+//A move as the reference sees it: where it goes and what it takes.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+pub struct ReferenceMove {
+    pub start: Coords,
+    pub end: Coords,
+    pub suicide: bool,
+    //(square, captured piece, capture kind), sorted.
+    pub captures: Vec<(Coords, String, String)>
+}
+
+impl ReferenceMove {
+    //The same summary of a move from the real generator.
+    pub fn from_move(mv: &MoveData) -> Self {
+        let mut captures: Vec<_> = mv.capture_details.iter()
+            .map(|capture| (capture.square.to_signed_coords(), format!("{:?}", capture.piece), capture.kind.to_string()))
+            .collect();
+        captures.sort();
+        captures.dedup();
+        ReferenceMove {
+            start: mv.start.to_signed_coords(),
+            end: mv.end.to_signed_coords(),
+            suicide: mv.kind == MoveKind::Suicide,
+            captures
+        }
+    }
+}
+
+const ALL_DIRECTIONS: [Coords; 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const ORTHOGONAL_DIRECTIONS: [Coords; 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+fn at(board: &GameBoard, (r, f): Coords) -> Option<UltimaPiece> {
+    board.get_square(board.rankfile(r, f)?)
+}
+
+fn on_board(board: &GameBoard, (r, f): Coords) -> bool {
+    board.rankfile(r, f).is_some()
+}
+
+fn is_enemy(board: &GameBoard, square: Coords, color: PlayerColor) -> bool {
+    at(board, square).is_some_and(|p| p.color != color)
+}
+
+fn step((r, f): Coords, (dr, df): Coords, times: i8) -> Coords {
+    (r + dr * times, f + df * times)
+}
+
+//Empty squares reachable by sliding from `start` in `dir`.
+fn slide(board: &GameBoard, start: Coords, dir: Coords) -> Vec<Coords> {
+    let mut out = vec![];
+    let mut i = 1;
+    while on_board(board, step(start, dir, i)) && at(board, step(start, dir, i)).is_none() {
+        out.push(step(start, dir, i));
+        i += 1;
+    }
+    out
+}
+
+fn frozen_by(board: &GameBoard, square: Coords, piece: UltimaPiece) -> Vec<Coords> {
+    ALL_DIRECTIONS.iter().map(|&dir| step(square, dir, 1)).filter(|&adjacent| {
+        match at(board, adjacent) {
+            Some(other) if other.color != piece.color => {
+                other.piece_type == Immobilizer || (other.piece_type == Chameleon && piece.piece_type == Immobilizer)
+            },
+            _ => false
+        }
+    }).collect()
+}
+
+//Pieces a piece of `color` captures by ending on `end`, sandwiching them against a friend.
+fn custodian_captures(board: &GameBoard, end: Coords, color: PlayerColor, rules: &RuleSet) -> Vec<Coords> {
+    let directions: &[Coords] = if rules.pawns_capture_diagonally {&ALL_DIRECTIONS} else {&ORTHOGONAL_DIRECTIONS};
+    directions.iter().filter(|&&dir| {
+        is_enemy(board, step(end, dir, 1), color) && at(board, step(end, dir, 2)).is_some_and(|p| p.color == color)
+    }).map(|&dir| step(end, dir, 1)).collect()
+}
+
+fn coordinated_captures(board: &GameBoard, end: Coords, color: PlayerColor) -> Vec<Coords> {
+    let mut out = vec![];
+    for king in board.get_king_locs(color) {
+        let (king_r, king_f) = king.to_signed_coords();
+        for square in [(end.0, king_f), (king_r, end.1)] {
+            if is_enemy(board, square, color) {
+                out.push(square);
+            }
+        }
+    }
+    out
+}
+
+//`RuleSet::king_coordinates`: a king ending on `end` coordinates with each friendly coordinator.
+fn kings_coordinated_captures(board: &GameBoard, end: Coords, color: PlayerColor) -> Vec<Coords> {
+    let mut out = vec![];
+    for r in 0..board.geometry().ranks() {
+        for f in 0..board.geometry().files() {
+            if at(board, (r, f)).is_some_and(|p| p.color == color && p.piece_type == Coordinator) {
+                for square in [(end.0, f), (r, end.1)] {
+                    if square != end && is_enemy(board, square, color) {
+                        out.push(square);
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+//Every leap in `dir`: the landing square, and the enemies jumped on the way.
+fn leaps(board: &GameBoard, start: Coords, dir: Coords, color: PlayerColor) -> Vec<(Coords, Vec<Coords>)> {
+    let mut out = vec![];
+    let mut jumped = vec![];
+    let mut i = 1;
+    while on_board(board, step(start, dir, i)) {
+        let square = step(start, dir, i);
+        match at(board, square) {
+            None => out.push((square, jumped.clone())),
+            Some(p) if p.color == color => break,
+            Some(_) => {
+                let beyond = step(start, dir, i + 1);
+                if !on_board(board, beyond) || at(board, beyond).is_some() {
+                    break;
+                }
+                jumped.push(square);
+            }
+        }
+        i += 1;
+    }
+    out
+}
+
+struct Builder {
+    start: Coords,
+    moves: Vec<ReferenceMove>
+}
+
+impl Builder {
+    //The move to `end`, added if it isn't there yet.
+    fn reach(&mut self, end: Coords) -> usize {
+        match self.moves.iter().position(|mv| mv.end == end) {
+            Some(index) => index,
+            None => {
+                self.moves.push(ReferenceMove {start: self.start, end, suicide: false, captures: vec![]});
+                self.moves.len() - 1
+            }
+        }
+    }
+
+    //Adds a move, or more captures to the move already going to `end`.
+    fn add(&mut self, board: &GameBoard, end: Coords, captures: Vec<Coords>, kind: CaptureKind) {
+        let index = self.reach(end);
+        for square in captures {
+            let captured = at(board, square).expect("only occupied squares are captured");
+            let entry = (square, format!("{captured:?}"), kind.to_string());
+            if !self.moves[index].captures.contains(&entry) {
+                self.moves[index].captures.push(entry);
+            }
+        }
+    }
+}
+
+fn piece_moves(board: &GameBoard, start: Coords, piece: UltimaPiece, rules: &RuleSet) -> Vec<ReferenceMove> {
+    let color = piece.color;
+    let mut builder = Builder {start, moves: vec![]};
+
+    let freezers = frozen_by(board, start, piece);
+    if !freezers.is_empty() {
+        if piece.piece_type == Chameleon && rules.chameleon_withdraws_from_immobilizer && freezers.len() == 1 {
+            let away = (start.0 - freezers[0].0, start.1 - freezers[0].1);
+            for end in slide(board, start, away) {
+                builder.add(board, end, vec![freezers[0]], CaptureKind::ChameleonAs(Withdrawer));
+            }
+        }
+        if rules.immobilized_suicide && piece.piece_type != King {
+            builder.moves.push(ReferenceMove {start, end: start, suicide: true, captures: vec![]});
+        }
+        return builder.moves;
+    }
+
+    match piece.piece_type {
+        Pawn => for dir in ORTHOGONAL_DIRECTIONS {
+            for end in slide(board, start, dir) {
+                builder.add(board, end, custodian_captures(board, end, color, rules), CaptureKind::Custodian);
+            }
+        },
+        Immobilizer => for dir in ALL_DIRECTIONS {
+            for end in slide(board, start, dir) {
+                builder.reach(end);
+            }
+        },
+        Coordinator => for dir in ALL_DIRECTIONS {
+            for end in slide(board, start, dir) {
+                builder.add(board, end, coordinated_captures(board, end, color), CaptureKind::Coordination);
+            }
+        },
+        Longleaper => for dir in ALL_DIRECTIONS {
+            for (end, jumped) in leaps(board, start, dir, color) {
+                builder.add(board, end, jumped, CaptureKind::Leap);
+            }
+        },
+        Withdrawer => for dir in ALL_DIRECTIONS {
+            let behind = step(start, dir, -1);
+            let captures = if is_enemy(board, behind, color) {vec![behind]} else {vec![]};
+            for end in slide(board, start, dir) {
+                builder.add(board, end, captures.clone(), CaptureKind::Withdrawal);
+            }
+        },
+        King => for dir in ALL_DIRECTIONS {
+            let end = step(start, dir, 1);
+            if !on_board(board, end) || at(board, end).is_some_and(|p| p.color == color) {continue}
+            let captures = if is_enemy(board, end, color) {vec![end]} else {vec![]};
+            builder.add(board, end, captures, CaptureKind::KingDisplacement);
+            if rules.king_coordinates {
+                builder.add(board, end, kings_coordinated_captures(board, end, color), CaptureKind::Coordination);
+            }
+        },
+        Chameleon => {
+            //Only pieces of the type being imitated are taken.
+            let only = |captures: Vec<Coords>, piece_type: UltimaPieceType| -> Vec<Coords> {
+                captures.into_iter().filter(|&square| at(board, square).is_some_and(|p| p.piece_type == piece_type)).collect()
+            };
+            for dir in ALL_DIRECTIONS {
+                let behind = step(start, dir, -1);
+                let orthogonal = dir.0 == 0 || dir.1 == 0;
+                for end in slide(board, start, dir) {
+                    builder.reach(end);
+                    if orthogonal {
+                        builder.add(board, end, only(custodian_captures(board, end, color, rules), Pawn), CaptureKind::ChameleonAs(Pawn));
+                    }
+                    builder.add(board, end, only(coordinated_captures(board, end, color), Coordinator), CaptureKind::ChameleonAs(Coordinator));
+                    if is_enemy(board, behind, color) {
+                        builder.add(board, end, only(vec![behind], Withdrawer), CaptureKind::ChameleonAs(Withdrawer));
+                    }
+                }
+                for (end, jumped) in leaps(board, start, dir, color) {
+                    if !jumped.is_empty() && only(jumped.clone(), Longleaper).len() == jumped.len() {
+                        builder.add(board, end, jumped, CaptureKind::ChameleonAs(Longleaper));
+                    }
+                }
+                let adjacent = step(start, dir, 1);
+                if rules.chameleon_captures_king && at(board, adjacent).is_some_and(|p| p.color != color && p.piece_type == King) {
+                    builder.add(board, adjacent, vec![adjacent], CaptureKind::ChameleonAs(King));
+                }
+            }
+        },
+        Custom(_) => {}
+    }
+    builder.moves
+}
+
+//Every legal move for `color`, sorted.
+pub fn legal_moves(board: &GameBoard, color: PlayerColor, rules: &RuleSet) -> Vec<ReferenceMove> {
+    let mut out = vec![];
+    for r in 0..board.geometry().ranks() {
+        for f in 0..board.geometry().files() {
+            let Some(piece) = at(board, (r, f)) else {continue};
+            if piece.color == color {
+                out.extend(piece_moves(board, (r, f), piece, rules));
+            }
+        }
+    }
+    for mv in &mut out {
+        mv.captures.sort();
+    }
+    out.sort();
+    out
+}
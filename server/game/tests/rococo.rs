@@ -0,0 +1,189 @@
+use game::{GameBoard, PlayerColor, UltimaPieceType, Rankfile, CaptureKind, board_to_fen, parse_square, square_to_string, move_to_string,
+    describe_captures, execute_move, captures_king, move_validation};
+use game::rococo::*;
+
+fn position(fen: &str) -> (GameBoard, PlayerColor) {
+    board_from_rococo_fen(fen).expect("test position should parse")
+}
+
+fn square(s: &str) -> Rankfile {
+    parse_square(s).filter(|&square| ROCOCO_GEOMETRY.contains(square)).expect("test square should parse")
+}
+
+fn moves_from(board: &GameBoard, start: &str) -> Vec<String> {
+    let mut out: Vec<String> = legal_moves_from(board, square(start)).iter().map(|mv| move_to_string(board, mv)).collect();
+    out.sort();
+    out
+}
+
+#[test]
+fn start_position() {
+    let board = new_in_start_position();
+    assert_eq!(
+        board_to_fen(&board, PlayerColor::White),
+        "10/1ilcakwcs1/1pppppppp1/10/10/10/10/1PPPPPPPP1/1ILCAKWCS1/10 w"
+    );
+    let (parsed, turn) = position(&board_to_fen(&board, PlayerColor::White));
+    assert!(parsed == board && turn == PlayerColor::White);
+    assert!(board.squares().filter(|&square| is_edge(square)).all(|square| board.get_square(square).is_none()));
+    assert_eq!(board.get_king_locs(PlayerColor::White), &vec![square("f2")]);
+    assert_eq!(board.get_king_locs(PlayerColor::Black), &vec![square("f9")]);
+    assert!(board_from_rococo_fen("8/8/8/8/8/8/8/8 w").is_err());
+}
+
+#[test]
+fn start_position_perft() {
+    let board = new_in_start_position();
+    let first = legal_moves(&board, PlayerColor::White).len() as u64;
+    assert_eq!(perft(&board, PlayerColor::White, 1), first);
+    //The position is symmetrical, so both sides have the same number of moves.
+    assert_eq!(legal_moves(&board, PlayerColor::Black).len() as u64, first);
+    assert!(perft(&board, PlayerColor::White, 2) > first);
+}
+
+#[test]
+fn perft_counts() {
+    //Every piece type, with a chameleon next to an immobilizer and pieces near the edge.
+    //The counts are from the standalone generator Rococo had before it used `PieceBehavior`s.
+    let (board, _) = position("k9/10/10/2s3a3/3C6/4i1P3/3Lc5/2SAp5/3K6/10 w");
+    assert_eq!(perft(&board, PlayerColor::White, 1), 26);
+    assert_eq!(perft(&board, PlayerColor::White, 2), 1386);
+    assert_eq!(perft(&new_in_start_position(), PlayerColor::White, 2), 625);
+}
+
+#[test]
+fn squares_round_trip() {
+    for s in ["a1", "j10", "e10", "f2"] {
+        assert_eq!(square_to_string(square(s)), s);
+    }
+    assert!(parse_square("k1").is_none_or(|square| !ROCOCO_GEOMETRY.contains(square)));
+    assert!(parse_square("a11").is_none_or(|square| !ROCOCO_GEOMETRY.contains(square)));
+}
+
+#[test]
+fn edge_only_when_capturing() {
+    //The withdrawer on c3 can reach b2 but not the edge at a1, unless it withdraws from something.
+    let (board, _) = position("k9/10/10/10/10/10/10/2W7/10/9K w");
+    let moves = moves_from(&board, "c3");
+    assert!(moves.contains(&String::from("c3-b2")));
+    assert!(!moves.contains(&String::from("c3-a1")));
+
+    let (board, _) = position("k9/10/10/10/10/10/3p6/2W7/10/9K w");
+    assert!(moves_from(&board, "c3").contains(&String::from("c3-a1xd4")));
+}
+
+#[test]
+fn advancer_captures_by_approach() {
+    let (board, _) = position("k9/10/10/10/10/3p6/10/3A6/10/9K w");
+    assert!(moves_from(&board, "d3").contains(&String::from("d3-d4xd5")));
+    assert!(!moves_from(&board, "d3").iter().any(|mv| mv.starts_with("d3-d3")));
+}
+
+#[test]
+fn cannon_pawn_jumps_and_captures_diagonally() {
+    //e4 jumps the friendly pawn on e5 to e6, sandwiching d7 against the white pawn on c8.
+    let (board, _) = position("k9/10/2P7/3p6/10/4P5/4P5/10/10/9K w");
+    let moves = moves_from(&board, "e4");
+    assert!(moves.contains(&String::from("e4-e6xd7")));
+    //Steps in every direction, except onto e5.
+    assert!(moves.contains(&String::from("e4-d3")));
+    assert!(!moves.iter().any(|mv| mv.starts_with("e4-e5")));
+}
+
+#[test]
+fn swapper_swaps_and_self_destructs() {
+    //Swaps with the first piece along each line, of either colour, but only destroys itself next to an enemy.
+    let (board, _) = position("k9/10/10/10/10/10/3p6/10/3S1p4/9K w");
+    let moves = moves_from(&board, "d2");
+    assert!(moves.contains(&String::from("d2<>d4")));
+    assert!(moves.contains(&String::from("d2<>f2")));
+    assert!(!moves.iter().any(|mv| mv.contains('*')));
+
+    let (board, _) = position("k9/10/10/10/10/10/10/4p5/3S6/9K w");
+    let mut after = board.clone();
+    let mv = parse_move(&board, PlayerColor::White, "d2*e3").expect("mutual destruction should be legal");
+    execute_move(&mut after, mv, PlayerColor::White);
+    assert!(after.get_square(square("d2")).is_none() && after.get_square(square("e3")).is_none());
+}
+
+#[test]
+fn swap_moves_both_pieces() {
+    let (board, _) = position("k9/10/10/10/10/10/3p6/10/3S6/9K w");
+    let mv = parse_move(&board, PlayerColor::White, "d2<>d4").expect("swap should be legal");
+    let mut after = board.clone();
+    execute_move(&mut after, mv, PlayerColor::White);
+    assert_eq!(after.get_square(square("d2")).map(|p| p.piece_type), Some(CANNON_PAWN));
+    assert_eq!(after.get_square(square("d4")).map(|p| p.piece_type), Some(SWAPPER));
+    assert_eq!(pieces().behavior(SWAPPER).map(|behavior| behavior.name()), Some("swapper"));
+    assert_eq!(SWAPPER, UltimaPieceType::custom('S').unwrap());
+}
+
+#[test]
+fn longleaper_jumps_several_pieces() {
+    let (board, _) = position("k9/10/10/10/3p6/10/3p6/10/3L6/9K w");
+    assert!(moves_from(&board, "d2").contains(&String::from("d2-d7xd4xd6")));
+}
+
+#[test]
+fn immobilizer_freezes_and_chameleon_freezes_immobilizer() {
+    let (board, _) = position("k9/10/10/10/10/10/4i5/3A6/3C6/9K w");
+    assert!(moves_from(&board, "d3").is_empty());
+    assert!(!moves_from(&board, "d2").is_empty());
+    let (board, _) = position("k9/10/10/10/10/10/4i5/3C6/10/9K w");
+    assert!(move_validation::is_immobilized(&board, square("e4"), board.get_square(square("e4")).unwrap(), &rules()));
+    assert!(move_validation::is_immobilized(&board, square("d3"), board.get_square(square("d3")).unwrap(), &rules()));
+}
+
+#[test]
+fn chameleon_captures_each_piece_its_own_way() {
+    //Approaching the advancer on d5 takes it; approaching the pawn on f5 doesn't.
+    let (board, _) = position("k9/10/10/10/10/3a1p4/10/10/3C1C4/9K w");
+    assert!(moves_from(&board, "d2").contains(&String::from("d2-d4xd5")));
+    assert!(moves_from(&board, "f2").contains(&String::from("f2-f4")));
+}
+
+#[test]
+fn taking_the_king() {
+    let (board, _) = position("10/10/10/10/10/10/10/4k5/4K5/10 w");
+    let mv = parse_move(&board, PlayerColor::White, "e2-e3").expect("king capture should be legal");
+    assert!(captures_king(&board, &mv, PlayerColor::White));
+}
+
+#[test]
+fn swaps_and_mutual_destruction_undo() {
+    let (board, _) = position("k9/10/10/10/10/10/3p6/4p5/3S6/9K w");
+    for written in ["d2<>d4", "d2*e3"] {
+        let mv = parse_move(&board, PlayerColor::White, written).expect("move should be legal");
+        let mut after = board.clone();
+        execute_move(&mut after, mv.clone(), PlayerColor::White);
+        game::undo_move(&mut after, &mv);
+        assert!(after == board, "{written}");
+    }
+}
+
+#[test]
+fn chameleon_swaps_only_with_enemy_swappers() {
+    //The chameleon on d2 sees the black swapper up the d-file and the black pawn along the rank.
+    let (board, _) = position("k9/10/10/10/10/3s6/10/10/3C2p3/9K w");
+    let moves = moves_from(&board, "d2");
+    assert!(moves.contains(&String::from("d2<>d5")));
+    assert!(!moves.contains(&String::from("d2<>g2")));
+    assert!(!moves.iter().any(|mv| mv.contains('*')));
+}
+
+#[test]
+fn captures_record_how_they_happen() {
+    let cases = [
+        ("k9/10/10/10/10/3p6/10/3A6/10/9K w", "d3-d4xd5", CaptureKind::Approach),
+        ("k9/10/10/10/10/10/4p5/3S6/10/9K w", "d3*e4", CaptureKind::MutualDestruction),
+        ("k9/10/10/10/10/10/4pP4/3P6/10/9K w", "d3-d4xe4", CaptureKind::Custodian),
+        ("k9/10/10/10/10/3a6/10/3C6/10/9K w", "d3-d4xd5", CaptureKind::ChameleonAs(ADVANCER))
+    ];
+    for (fen, written, kind) in cases {
+        let (board, whose_turn) = position(fen);
+        let mv = legal_moves(&board, whose_turn).into_iter().find(|mv| move_to_string(&board, mv) == written)
+            .expect("test move should be legal");
+        let kinds: Vec<CaptureKind> = mv.capture_details.iter().map(|capture| capture.kind).collect();
+        assert!(kinds == [kind], "{fen}: {written} captures by {}", describe_captures(&mv));
+    }
+}
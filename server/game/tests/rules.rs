@@ -0,0 +1,188 @@
+use game::*;
+
+mod common;
+use common::*;
+
+//All the squares a side's moves would capture, in notation.
+fn captured_squares(board: &GameBoard, color: PlayerColor, rules: &RuleSet) -> Vec<String> {
+    let mut out: Vec<String> = move_validation::get_all_legal_moves_for_color(board, color, rules).iter()
+        .flat_map(|mv| mv.capture_details.iter().map(|capture| square_to_string(capture.square)))
+        .collect();
+    out.sort();
+    out.dedup();
+    out
+}
+
+fn moves_from(board: &GameBoard, start: &str, rules: &RuleSet) -> Vec<MoveData> {
+    let piece = board.get_square(square(start)).expect("test piece should exist");
+    move_validation::get_all_legal_moves(board, square(start), piece, rules)
+}
+
+#[test]
+fn presets_differ_on_frozen_pieces() {
+    //The pawn on c4 is frozen by the immobilizer on d5. Only Abbott's rules let it remove itself.
+    let (board, turn) = position("k7/8/8/3i4/2P5/8/8/7K w");
+    let abbott = move_validation::get_all_legal_moves_for_color(&board, turn, &RuleSet::abbott());
+    let plain = move_validation::get_all_legal_moves_for_color(&board, turn, &RuleSet::default());
+    assert_eq!(abbott.len(), plain.len() + 1);
+    assert!(abbott.iter().any(|mv| mv.kind == MoveKind::Suicide && mv.start == square("c4")));
+    assert!(!plain.iter().any(|mv| mv.kind == MoveKind::Suicide));
+    assert!(plain.iter().all(|mv| abbott.contains(mv)));
+}
+
+#[test]
+fn default_rules_forbid_suicide() {
+    //Same position: the defaults don't let the frozen pawn go.
+    let (board, turn) = position("k7/8/8/3i4/2P5/8/8/7K w");
+    let moves = move_validation::get_all_legal_moves_for_color(&board, turn, &RuleSet::default());
+    assert!(!moves.iter().any(|mv| mv.kind == MoveKind::Suicide));
+}
+
+#[test]
+fn king_coordinates() {
+    //e1-e2 puts the king's rank on a2 and its file on e5, crossing the coordinator on a5.
+    let (board, turn) = position("7k/8/8/O3p3/8/8/p7/4K3 w");
+    let plain = RuleSet::default();
+    let coordinating = RuleSet {king_coordinates: true, ..RuleSet::default()};
+    let mv = |rules: &RuleSet| parse_move(&board, turn, "e1-e2", rules).expect("king move should parse");
+    assert!(mv(&plain).capture_details.is_empty());
+
+    let mv = mv(&coordinating);
+    let mut captured: Vec<(String, CaptureKind)> = mv.capture_details.iter()
+        .map(|capture| (square_to_string(capture.square), capture.kind))
+        .collect();
+    captured.sort_by(|a, b| a.0.cmp(&b.0));
+    assert!(captured == vec![("a2".to_string(), CaptureKind::Coordination), ("e5".to_string(), CaptureKind::Coordination)]);
+}
+
+#[test]
+fn king_coordination_keeps_its_own_capture() {
+    //e1-e2 takes the pawn on e2 by displacement and the one on a2 with the coordinator on a5.
+    let (board, turn) = position("7k/8/8/O7/8/8/p3p3/4K3 w");
+    let rules = RuleSet {king_coordinates: true, ..RuleSet::default()};
+    let mv = parse_move(&board, turn, "e1-e2", &rules).expect("king move should parse");
+    let mut kinds: Vec<String> = mv.capture_details.iter()
+        .map(|capture| format!("{} {}", square_to_string(capture.square), capture.kind))
+        .collect();
+    kinds.sort();
+    assert_eq!(kinds, vec![
+        format!("a2 {}", CaptureKind::Coordination),
+        format!("e2 {}", CaptureKind::KingDisplacement)
+    ]);
+}
+
+#[test]
+fn pawns_capture_orthogonally_under_both_settings() {
+    //c1-c3 sandwiches d3 against e3.
+    let (board, turn) = position("k7/8/8/8/8/3pP3/8/2P4K w");
+    for diagonally in [false, true] {
+        let rules = RuleSet {pawns_capture_diagonally: diagonally, ..RuleSet::default()};
+        assert_eq!(captured_squares(&board, turn, &rules), vec!["d3"]);
+    }
+}
+
+#[test]
+fn pawns_capture_diagonally() {
+    //c1-c3 would sandwich d4 against e5 diagonally.
+    let (board, turn) = position("k7/8/8/4P3/3p4/8/8/2P4K w");
+    let orthogonal = RuleSet {pawns_capture_diagonally: false, ..RuleSet::default()};
+    let diagonal = RuleSet {pawns_capture_diagonally: true, ..RuleSet::default()};
+    assert!(captured_squares(&board, turn, &orthogonal).is_empty());
+    assert_eq!(captured_squares(&board, turn, &diagonal), vec!["d4"]);
+}
+
+#[test]
+fn chameleon_captures_king() {
+    let (board, turn) = position("8/8/8/8/8/8/4k3/3C3K w");
+    let allowed = RuleSet {chameleon_captures_king: true, ..RuleSet::default()};
+    let forbidden = RuleSet {chameleon_captures_king: false, ..RuleSet::default()};
+    assert!(moves_from(&board, "d1", &allowed).iter().any(|mv| captures_king(&board, mv, turn)));
+    assert!(!moves_from(&board, "d1", &forbidden).iter().any(|mv| captures_king(&board, mv, turn)));
+    assert!(is_in_check(&board, PlayerColor::Black, &allowed));
+    assert!(!is_in_check(&board, PlayerColor::Black, &forbidden));
+}
+
+#[test]
+fn chameleon_withdraws_from_immobilizer() {
+    //The chameleon on d4 and the immobilizer on d5 freeze each other.
+    let (board, _) = position("k7/8/8/3i4/3C4/8/8/7K w");
+    let forbidden = RuleSet {chameleon_withdraws_from_immobilizer: false, ..RuleSet::default()};
+    let allowed = RuleSet {chameleon_withdraws_from_immobilizer: true, ..RuleSet::default()};
+    assert!(moves_from(&board, "d4", &forbidden).is_empty());
+
+    let moves = moves_from(&board, "d4", &allowed);
+    let mut ends: Vec<String> = moves.iter().map(|mv| square_to_string(mv.end)).collect();
+    ends.sort();
+    assert_eq!(ends, vec!["d1", "d2", "d3"]);
+    for mv in &moves {
+        assert_eq!(mv.capture_details.len(), 1);
+        assert!(mv.capture_details[0].square == square("d5"));
+        assert!(mv.capture_details[0].kind == CaptureKind::ChameleonAs(UltimaPieceType::Withdrawer));
+    }
+}
+
+#[test]
+fn chameleon_frozen_by_two_immobilizers_cannot_withdraw() {
+    let (board, _) = position("k7/8/8/3ii3/3C4/8/8/7K w");
+    let allowed = RuleSet {chameleon_withdraws_from_immobilizer: true, ..RuleSet::default()};
+    assert!(moves_from(&board, "d4", &allowed).is_empty());
+}
+
+#[test]
+fn immobilized_suicide() {
+    let (board, _) = position("k7/8/8/3i4/2P5/8/8/7K w");
+    let allowed = RuleSet {immobilized_suicide: true, ..RuleSet::default()};
+    let forbidden = RuleSet {immobilized_suicide: false, ..RuleSet::default()};
+    assert!(board.immobilization(square("c4"), &allowed).expect("pawn should be frozen").can_commit_suicide);
+    assert!(!board.immobilization(square("c4"), &forbidden).expect("pawn should be frozen").can_commit_suicide);
+}
+
+#[test]
+fn suicide_moves_only_with_the_rule() {
+    let (board, _) = position("k7/8/8/3i4/2P5/8/8/7K w");
+    let allowed = RuleSet {immobilized_suicide: true, ..RuleSet::default()};
+    let forbidden = RuleSet {immobilized_suicide: false, ..RuleSet::default()};
+    assert!(moves_from(&board, "c4", &forbidden).is_empty());
+
+    let moves = moves_from(&board, "c4", &allowed);
+    assert_eq!(moves.len(), 1);
+    assert!(moves[0].kind == MoveKind::Suicide);
+    assert!(moves[0].start == square("c4") && moves[0].end == square("c4"));
+}
+
+#[test]
+fn frozen_king_cannot_commit_suicide() {
+    let (board, _) = position("k7/8/8/3i4/2K5/8/8/8 w");
+    let allowed = RuleSet {immobilized_suicide: true, ..RuleSet::default()};
+    assert!(moves_from(&board, "c4", &allowed).is_empty());
+    assert!(!board.immobilization(square("c4"), &allowed).expect("king should be frozen").can_commit_suicide);
+}
+
+#[test]
+fn suicide_moves_execute_undo_and_round_trip() {
+    let (board, turn) = position("k7/8/8/3i4/2P5/8/8/7K w");
+    let rules = RuleSet {immobilized_suicide: true, ..RuleSet::default()};
+    let mv = parse_move(&board, turn, "c4-c4", &rules).expect("suicide should parse");
+    assert!(mv.kind == MoveKind::Suicide);
+    assert_eq!(move_to_string(&board, &mv), "c4-c4");
+
+    let mut after = board.clone();
+    execute_move(&mut after, mv.clone(), turn);
+    assert!(after.get_square(square("c4")).is_none());
+    undo_move(&mut after, &mv);
+    assert!(after.get_square(square("c4")) == board.get_square(square("c4")));
+}
+
+#[test]
+fn undo_restores_captures() {
+    //c1-c3 sandwiches d3 against e3.
+    let (board, turn) = position("k7/8/8/8/8/3pP3/8/2P4K w");
+    let mv = parse_move(&board, turn, "c1-c3", &RuleSet::default()).expect("move should parse");
+    let mut after = board.clone();
+    execute_move(&mut after, mv.clone(), turn);
+    assert!(after.get_square(square("d3")).is_none());
+    undo_move(&mut after, &mv);
+    for s in ["c1", "c3", "d3", "e3"] {
+        assert!(after.get_square(square(s)) == board.get_square(square(s)));
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use game::*;
+use game::PlayerColor::*;
+
+mod common;
+use common::*;
+
+#[test]
+fn multi_pv_lines_are_distinct_and_ordered() {
+    let board = GameBoard::new_in_start_position();
+    let backend = EvaluationBackend::HandWritten;
+    let progress = Searcher::new(&backend).search_multi_pv(&board, White, 2, 4, |_| {});
+    assert_eq!(progress.depth, 2);
+    assert_eq!(progress.lines.len(), 4);
+    let mut first_moves: Vec<(Rankfile, Rankfile)> = progress.lines.iter().map(|line| (line.moves[0].start, line.moves[0].end)).collect();
+    first_moves.sort_by_key(|&(start, end)| (start.to_unsigned_coords(), end.to_unsigned_coords()));
+    first_moves.dedup();
+    assert_eq!(first_moves.len(), 4);
+    assert!(progress.lines.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    //The best line is what a single-line search finds.
+    assert_eq!(progress.lines[0].score, search(&board, White, 2, &backend).score);
+}
+
+#[test]
+fn multi_pv_asks_for_more_lines_than_there_are_moves() {
+    //The immobilizer on b6 freezes the black king, leaving black only its two pawns.
+    let (board, whose_turn) = position("4K3/8/OIk5/8/8/6p1/3p4/8 b");
+    let moves = move_validation::get_all_legal_moves_for_color(&board, whose_turn, &RuleSet::default()).len();
+    let backend = EvaluationBackend::HandWritten;
+    let progress = Searcher::new(&backend).search_multi_pv(&board, whose_turn, 1, moves + 5, |_| {});
+    assert_eq!(progress.lines.len(), moves);
+}
+
+#[test]
+fn progress_is_reported_once_per_depth() {
+    let board = GameBoard::new_in_start_position();
+    let backend = EvaluationBackend::HandWritten;
+    let mut reported = vec![];
+    let progress = Searcher::new(&backend).search_multi_pv(&board, White, 3, 2, |progress| {
+        reported.push((progress.depth, progress.lines.len(), progress.nodes));
+    });
+    assert_eq!(reported.iter().map(|&(depth, _, _)| depth).collect::<Vec<_>>(), [1, 2, 3]);
+    assert!(reported.iter().all(|&(_, lines, _)| lines == 2));
+    assert!(reported.windows(2).all(|pair| pair[0].2 < pair[1].2), "node counts only go up");
+    assert_eq!(progress.depth, 3);
+    assert_eq!(progress.nodes, reported[2].2);
+}
+
+#[test]
+fn analysis_stops_when_told_to() {
+    let handle = spawn_analysis(GameBoard::new_in_start_position(), White, 50, 3, RuleSet::default(), Arc::new(EvaluationBackend::HandWritten));
+    let first = handle.updates.recv_timeout(Duration::from_secs(60)).expect("the first iteration should finish");
+    assert_eq!(first.depth, 1);
+    assert!(!handle.is_finished());
+
+    let stopped_at = Instant::now();
+    handle.stop();
+    let progress = handle.join();
+    assert!(stopped_at.elapsed() < Duration::from_secs(30));
+    assert!(progress.depth >= 1 && progress.depth < 50);
+    assert_eq!(progress.lines.len(), 3);
+}
+
+#[test]
+fn another_thread_can_stop_a_search() {
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let stopper = {
+        let stop_flag = stop_flag.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            stop_flag.store(true, Ordering::Relaxed);
+        })
+    };
+    let backend = EvaluationBackend::HandWritten;
+    let mut searcher = Searcher::new(&backend);
+    searcher.set_stop_flag(stop_flag);
+    let mut completed = 0;
+    let progress = searcher.search_multi_pv(&GameBoard::new_in_start_position(), White, 50, 2, |progress| completed = progress.depth);
+    stopper.join().unwrap();
+    //Whatever iteration was cut short is thrown away.
+    assert_eq!(progress.depth, completed);
+    assert!(progress.depth < 50);
+    assert_eq!(progress.lines.len(), 2);
+}
@@ -0,0 +1,92 @@
+use game::*;
+use game::PlayerColor::*;
+
+mod common;
+use common::*;
+
+const GOLDEN_DOT: &str = include_str!("data/search_trace.dot");
+const GOLDEN_JSON: &str = include_str!("data/search_trace.json");
+
+fn traced_search(fen: &str, depth: u32, settings: TraceSettings) -> SearchTrace {
+    let (board, whose_turn) = position(fen);
+    let backend = EvaluationBackend::HandWritten;
+    let mut searcher = Searcher::new(&backend);
+    searcher.set_trace(Some(settings));
+    searcher.search(&board, whose_turn, depth);
+    searcher.take_trace().expect("a traced search should leave a trace")
+}
+
+//Parents come before their children, and the two agree about each other.
+fn assert_well_formed(trace: &SearchTrace) {
+    for (i, node) in trace.nodes.iter().enumerate() {
+        if let Some(parent) = node.parent {
+            assert!(parent < i);
+            assert!(trace.nodes[parent].children.contains(&i));
+            assert_eq!(node.ply, trace.nodes[parent].ply + 1);
+        }
+        for &child in &node.children {
+            assert_eq!(trace.nodes[child].parent, Some(i));
+        }
+    }
+}
+
+//Bare kings: white's a1-b2 walks into the black king, which takes it.
+//The goldens are regenerated by writing `to_dot` and `to_json` of this trace over the files in tests/data.
+#[test]
+fn dot_and_json_match_the_goldens() {
+    let trace = traced_search("8/8/8/8/8/2k5/8/K7 w", 2, TraceSettings::default());
+    assert_well_formed(&trace);
+    assert_eq!(trace.to_dot(), GOLDEN_DOT);
+    assert_eq!(trace.to_json(), GOLDEN_JSON);
+}
+
+#[test]
+fn the_golden_tree_has_every_outcome_it_shows() {
+    let trace = traced_search("8/8/8/8/8/2k5/8/K7 w", 2, TraceSettings::default());
+    let outcome = |mv: &str| trace.nodes.iter().find(|node| node.mv.as_deref() == Some(mv)).unwrap().outcome;
+    assert_eq!(outcome("a1-b2"), TraceOutcome::Cutoff {skipped: 7});
+    assert_eq!(outcome("c3-b2xb2"), TraceOutcome::KingCaptured);
+    assert_eq!(trace.roots().collect::<Vec<_>>(), [0]);
+    assert!(!trace.truncated);
+}
+
+#[test]
+fn the_node_cap_truncates_the_trace() {
+    let board = GameBoard::new_in_start_position();
+    let backend = EvaluationBackend::HandWritten;
+    let mut searcher = Searcher::new(&backend);
+    searcher.set_trace(Some(TraceSettings {max_ply: 10, max_nodes: 25}));
+    let result = searcher.search(&board, White, 2);
+    let trace = searcher.take_trace().unwrap();
+    assert_eq!(trace.nodes.len(), 25);
+    assert!(trace.truncated);
+    assert!(result.nodes > 25, "the cap limits the recording, not the search");
+    assert_well_formed(&trace);
+    assert!(trace.to_json().starts_with("{\"truncated\": true,"));
+}
+
+#[test]
+fn the_ply_cap_leaves_out_deeper_nodes() {
+    let trace = traced_search("8/8/8/8/8/2k5/8/K7 w", 3, TraceSettings {max_ply: 1, max_nodes: 10_000});
+    assert!(trace.nodes.iter().all(|node| node.ply <= 1));
+    assert!(trace.nodes.iter().any(|node| node.ply == 1));
+    assert!(!trace.truncated);
+    assert_well_formed(&trace);
+}
+
+#[test]
+fn tablebase_hits_are_marked() {
+    let mut tablebase = Tablebase::new(RuleSet::default());
+    tablebase.generate(&Material::from_name("KvK").unwrap());
+    let (board, whose_turn) = position("8/8/8/8/8/2k5/8/K7 w");
+    let backend = EvaluationBackend::HandWritten;
+    let mut searcher = Searcher::new(&backend);
+    searcher.set_tablebase(Some(&tablebase));
+    searcher.set_trace(Some(TraceSettings::default()));
+    searcher.search(&board, whose_turn, 2);
+    let trace = searcher.take_trace().unwrap();
+    //Every position after white's move is in the table, so the search goes no further.
+    assert!(trace.nodes.iter().filter(|node| node.ply == 1).all(|node| node.outcome == TraceOutcome::TablebaseHit));
+    assert!(trace.nodes.iter().all(|node| node.ply <= 1));
+    assert!(trace.to_dot().contains("tablebase hit\", color=blue"));
+}
@@ -0,0 +1,67 @@
+use game::*;
+
+fn back_rank(board: &GameBoard, rank: i8) -> Vec<UltimaPieceType> {
+    (0..8).map(|file| board.get_square_from_coords(rank, file).expect("back ranks are full").piece_type).collect()
+}
+
+#[test]
+fn rotational_is_the_usual_start_position() {
+    let usual = board_to_fen(&GameBoard::new_in_start_position(), PlayerColor::White);
+    assert_eq!(board_to_fen(&StartPosition::Rotational.board(), PlayerColor::White), usual);
+    assert_eq!(StartPosition::default(), StartPosition::Rotational);
+}
+
+#[test]
+fn abbott_kings_face_each_other() {
+    let board = StartPosition::Abbott.board();
+    for color in [PlayerColor::White, PlayerColor::Black] {
+        assert_eq!(board.get_king_locs(color).len(), 1);
+        assert_eq!(square_to_string(board.get_king_locs(color)[0])[..1], *"e");
+    }
+    assert!(back_rank(&board, 0) == back_rank(&board, 7));
+}
+
+#[test]
+fn identifiers_round_trip() {
+    for setup in [StartPosition::Rotational, StartPosition::Abbott, StartPosition::Shuffled(0), StartPosition::Shuffled(u64::MAX)] {
+        assert_eq!(StartPosition::from_id(&setup.id()), Some(setup));
+    }
+    assert_eq!(StartPosition::from_id("shuffle-"), None);
+    assert_eq!(StartPosition::from_id("chess960"), None);
+}
+
+#[test]
+fn shuffles_are_seeded_and_constrained() {
+    for seed in 0..200 {
+        let rank = shuffled_back_rank(seed);
+        assert!(rank == shuffled_back_rank(seed));
+        let mut sorted = rank;
+        sorted.sort_by_key(|piece_type| piece_type.to_char());
+        let mut expected = StartPosition::Rotational.back_ranks().0;
+        expected.sort_by_key(|piece_type| piece_type.to_char());
+        assert!(sorted == expected);
+        assert!(rank[0] != UltimaPieceType::King && rank[7] != UltimaPieceType::King);
+        for piece_type in [UltimaPieceType::Longleaper, UltimaPieceType::Chameleon] {
+            let files: Vec<usize> = (0..8).filter(|&file| rank[file] == piece_type).collect();
+            assert!(files[0] % 2 != files[1] % 2);
+        }
+    }
+    assert!((0..20).any(|seed| shuffled_back_rank(seed) != shuffled_back_rank(0)));
+}
+
+#[test]
+fn records_name_their_setup() {
+    let setup = StartPosition::Shuffled(7);
+    let board = setup.board();
+    let mv = move_validation::get_all_legal_moves_for_color(&board, PlayerColor::White, &RuleSet::default())
+        .into_iter().next().expect("the start position has moves");
+    let mut record = GameRecord::new(vec![mv]);
+    record.set_start_position(setup);
+
+    let read = GameRecord::from_text(&record.to_text()).expect("record should read back");
+    assert_eq!(read.start_position(), setup);
+    assert_eq!(read.moves.len(), 1);
+
+    let unknown = "[Setup \"nonsense\"]\n\n1. a2-a3\n";
+    assert_eq!(GameRecord::from_text(unknown).err(), Some(GameRecordError::UnknownSetup(String::from("nonsense"))));
+}
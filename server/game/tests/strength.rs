@@ -0,0 +1,91 @@
+use game::*;
+use game::PlayerColor::*;
+
+mod common;
+use common::*;
+
+#[test]
+fn lower_levels_search_shallower_and_fewer_nodes() {
+    for level in 0..=MAX_SKILL_LEVEL {
+        let limits = SkillLevel::new(level).limits();
+        assert_eq!(limits.max_depth, 1 + level as u32 / 5, "level {level}");
+        if level == MAX_SKILL_LEVEL {
+            assert_eq!(limits.max_nodes, None);
+            assert_eq!(limits.candidate_moves, 1);
+            assert_eq!(limits.temperature, 0.0);
+        } else {
+            assert_eq!(limits.max_nodes, Some(200 << (level / 2)), "level {level}");
+        }
+    }
+    for pair in (0..=MAX_SKILL_LEVEL).collect::<Vec<_>>().windows(2) {
+        let (weaker, stronger) = (SkillLevel::new(pair[0]).limits(), SkillLevel::new(pair[1]).limits());
+        assert!(weaker.max_depth <= stronger.max_depth);
+        assert!(weaker.max_nodes.unwrap_or(u64::MAX) <= stronger.max_nodes.unwrap_or(u64::MAX));
+        assert!(weaker.evaluation_noise > stronger.evaluation_noise);
+        assert!(SkillLevel::new(pair[0]).approximate_elo() <= SkillLevel::new(pair[1]).approximate_elo());
+    }
+}
+
+#[test]
+fn the_node_limit_cuts_the_search_short() {
+    //Level 0 only gets a couple of hundred nodes, which run out well before a third iteration finishes.
+    let board = GameBoard::new_in_start_position();
+    let backend = EvaluationBackend::HandWritten;
+    let mut limited = Searcher::new(&backend);
+    limited.set_node_limit(SkillLevel::new(0).limits().max_nodes);
+    let limited = limited.search(&board, White, 3);
+    let unlimited = Searcher::new(&backend).search(&board, White, 3);
+    assert_eq!(unlimited.depth, 3);
+    assert!(limited.depth < 3);
+    assert!(limited.nodes < unlimited.nodes);
+    assert!(limited.best_move.is_some());
+}
+
+#[test]
+fn every_level_plays_legal_moves() {
+    let (board, whose_turn) = random_position(32, 20, &RuleSet::default());
+    let backend = EvaluationBackend::HandWritten;
+    let mut rng = game::Rng::new(32);
+    for level in [0, 7, 14] {
+        let mv = choose_move(&board, whose_turn, SkillLevel::new(level), &RuleSet::default(), &backend, None, &mut rng)
+            .expect("there are moves to play");
+        assert!(move_validation::find_legal_move(&board, whose_turn, mv.start, mv.end, &RuleSet::default()).is_some());
+    }
+}
+
+#[test]
+fn levels_from_elo() {
+    assert_eq!(SkillLevel::from_elo(0).level(), 0);
+    assert_eq!(SkillLevel::from_elo(u32::MAX), SkillLevel::strongest());
+    for level in 0..=MAX_SKILL_LEVEL {
+        let elo = SkillLevel::new(level).approximate_elo();
+        assert!(SkillLevel::from_elo(elo).approximate_elo() == elo);
+    }
+}
+
+//Produces the numbers in `strength::APPROXIMATE_ELO`. Slow, so it only runs when asked for:
+//  cargo test --release --test strength -- --ignored --nocapture
+#[test]
+#[ignore]
+fn calibrate_approximate_elo() {
+    const GAMES: u32 = 20;
+    const MAX_PLIES: u32 = 150;
+    let backend = EvaluationBackend::HandWritten;
+    let mut elo = vec![400.0];
+    for level in (2..=MAX_SKILL_LEVEL).step_by(2) {
+        let mut stronger = EnginePlayer::new(SkillLevel::new(level), &backend, level as u64);
+        let mut weaker = EnginePlayer::new(SkillLevel::new(level - 2), &backend, level as u64 + 1000);
+        let result = play_match(&mut stronger, &mut weaker, GAMES, MAX_PLIES, None, &RuleSet::default());
+        //A stronger level never counts as weaker than the one below it.
+        let difference = result.elo_difference().max(0.0);
+        println!("level {level} vs {}: +{} ={} -{} ({difference:+.0})", level - 2, result.wins, result.draws, result.losses);
+        elo.push(elo.last().unwrap() + difference);
+    }
+    let table: Vec<u32> = (0..=MAX_SKILL_LEVEL as usize)
+        .map(|level| {
+            let rating = if level % 2 == 0 {elo[level / 2]} else {(elo[level / 2] + elo[level / 2 + 1]) / 2.0};
+            (rating / 5.0).round() as u32 * 5
+        })
+        .collect();
+    println!("{table:?}");
+}
@@ -0,0 +1,77 @@
+use game::*;
+use game::PlayerColor::*;
+use proptest::prelude::*;
+
+mod common;
+use common::*;
+
+type Coords = (usize, usize);
+type NormalizedMove = (Coords, Coords, Vec<(Coords, String)>);
+
+//Moves in a form that doesn't depend on the order generators happen to produce them in.
+fn normalized(moves: &[MoveData]) -> Vec<NormalizedMove> {
+    let mut out: Vec<_> = moves.iter().map(|mv| {
+        let mut details: Vec<(Coords, String)> = mv.capture_details.iter()
+            .map(|capture| (capture.square.to_unsigned_coords(), format!("{:?} {}", capture.piece, capture.kind)))
+            .collect();
+        details.sort();
+        (mv.start.to_unsigned_coords(), mv.end.to_unsigned_coords(), details)
+    }).collect();
+    out.sort();
+    out
+}
+
+#[test]
+fn transforms_are_involutions() {
+    let (board, whose_turn) = random_position(7, 20, &RuleSet::default());
+    for symmetry in Symmetry::ALL {
+        let twice = board.transformed(symmetry).transformed(symmetry);
+        assert_eq!(board_to_fen(&twice, whose_turn), board_to_fen(&board, whose_turn));
+    }
+}
+
+#[test]
+fn flipping_the_start_position() {
+    let board = GameBoard::new_in_start_position();
+    assert_eq!(board_to_fen(&board.flipped_vertically(), Black), "ilckwclo/pppppppp/8/8/8/8/PPPPPPPP/OLCWKCLI b");
+    assert_eq!(board_to_fen(&board.mirrored_horizontally(), White), "ilckwclo/pppppppp/8/8/8/8/PPPPPPPP/OLCWKCLI w");
+    assert_eq!(board_to_fen(&board.rotated_180(), White), "OLCWKCLI/PPPPPPPP/8/8/8/8/pppppppp/ilckwclo w");
+    assert!(board.rotated_180().get_king_locs(White) == &vec![parse_square("e8").unwrap()]);
+}
+
+#[test]
+fn transforms_keep_the_board_shape() {
+    let (board, _) = board_from_fen("k6/7/7/7/6K w").unwrap();
+    let rotated = board.rotated_180();
+    assert_eq!(rotated.geometry(), board.geometry());
+    assert!(rotated.get_king_locs(White) == &vec![parse_square("a5").unwrap()]);
+    assert!(rotated.get_king_locs(Black) == &vec![parse_square("g1").unwrap()]);
+}
+
+proptest! {
+    #[test]
+    fn move_generation_commutes_with_symmetries(seed in any::<u64>(), plies in 0usize..40) {
+        for rules in rule_sets() {
+            let (board, whose_turn) = random_position(seed, plies, &rules);
+            let moves = move_validation::get_all_legal_moves_for_color(&board, whose_turn, &rules);
+            for symmetry in Symmetry::ALL {
+                let transformed_board = board.transformed(symmetry);
+                let transformed_moves: Vec<MoveData> = moves.iter()
+                    .map(|mv| mv.transformed(symmetry, board.geometry()))
+                    .collect();
+                let generated = move_validation::get_all_legal_moves_for_color(&transformed_board, symmetry.color(whose_turn), &rules);
+                prop_assert_eq!(normalized(&generated), normalized(&transformed_moves), "{:?} of {}", symmetry, board_to_fen(&board, whose_turn));
+            }
+        }
+    }
+
+    #[test]
+    fn evaluation_survives_flipping_colours(seed in any::<u64>(), plies in 0usize..40) {
+        let (board, whose_turn) = random_position(seed, plies, &RuleSet::default());
+        let flipped = board.flipped_vertically();
+        prop_assert_eq!(
+            hand_written::evaluate(&flipped, whose_turn.opposite()),
+            hand_written::evaluate(&board, whose_turn)
+        );
+    }
+}
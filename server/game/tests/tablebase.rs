@@ -0,0 +1,291 @@
+use std::sync::OnceLock;
+
+use game::*;
+use game::PlayerColor::*;
+use game::UltimaPieceType::*;
+use proptest::prelude::*;
+
+mod common;
+use common::*;
+
+//Generating KWvK takes a while without optimizations, so every test shares one copy.
+fn kwk() -> &'static Tablebase {
+    static TABLEBASE: OnceLock<Tablebase> = OnceLock::new();
+    TABLEBASE.get_or_init(|| {
+        let mut tablebase = Tablebase::new(RuleSet::default());
+        tablebase.generate(&Material::from_name("KWvK").unwrap());
+        tablebase
+    })
+}
+
+fn board_with(pieces: &[(UltimaPiece, Rankfile)]) -> GameBoard {
+    let mut board = GameBoard::new_empty();
+    for &(piece, rf) in pieces {
+        board.place_piece(rf, piece);
+    }
+    board
+}
+
+fn square_at(index: u64) -> Rankfile {
+    Rankfile::from((index / 8) as i8, (index % 8) as i8).unwrap()
+}
+
+//Every turn and reflection of the board that keeps the colours.
+fn reflections(board: &GameBoard) -> Vec<GameBoard> {
+    let mut out = vec![];
+    for i in 0..8 {
+        let mut reflected = GameBoard::new_empty();
+        for rf in board.squares() {
+            let Some(piece) = board.get_square(rf) else {continue};
+            let (mut r, mut f) = rf.to_signed_coords();
+            if i & 1 != 0 {r = 7 - r}
+            if i & 2 != 0 {f = 7 - f}
+            if i & 4 != 0 {(r, f) = (f, r)}
+            reflected.place_piece(Rankfile::from(r, f).unwrap(), piece);
+        }
+        out.push(reflected);
+    }
+    out
+}
+
+//Random positions with `pieces` on distinct squares.
+fn random_boards(pieces: &[UltimaPiece], seed: u64, count: usize) -> Vec<(GameBoard, PlayerColor)> {
+    let mut rng = game::Rng::new(seed);
+    let mut out = vec![];
+    while out.len() < count {
+        let squares: Vec<u64> = pieces.iter().map(|_| rng.below(64)).collect();
+        if (1..squares.len()).any(|i| squares[..i].contains(&squares[i])) {
+            continue;
+        }
+        let placed: Vec<(UltimaPiece, Rankfile)> = pieces.iter().zip(&squares).map(|(&p, &sq)| (p, square_at(sq))).collect();
+        let whose_turn = if rng.below(2) == 0 {White} else {Black};
+        out.push((board_with(&placed), whose_turn));
+    }
+    out
+}
+
+//What the position is worth given the table's values for its children, counting a king capture as a win in 1.
+fn value_from_children(tablebase: &Tablebase, board: &GameBoard, whose_turn: PlayerColor) -> TablebaseResult {
+    let mut best = None;
+    for mv in move_validation::get_all_legal_moves_for_color(board, whose_turn, tablebase.rules()) {
+        let result = if captures_king(board, &mv, whose_turn) {
+            TablebaseResult::Win {plies_to_mate: 1}
+        } else {
+            let mut child = board.clone();
+            execute_move(&mut child, mv, whose_turn);
+            match tablebase.probe(&child, whose_turn.opposite()).expect("every child should be in the tablebase") {
+                TablebaseResult::Win {plies_to_mate} => TablebaseResult::Loss {plies_to_mate: plies_to_mate + 1},
+                TablebaseResult::Loss {plies_to_mate} => TablebaseResult::Win {plies_to_mate: plies_to_mate + 1},
+                TablebaseResult::Draw => TablebaseResult::Draw
+            }
+        };
+        best = Some(match best {
+            None => result,
+            Some(best) => better(best, result)
+        });
+    }
+    best.unwrap_or(TablebaseResult::Draw)
+}
+
+fn better(a: TablebaseResult, b: TablebaseResult) -> TablebaseResult {
+    use TablebaseResult::*;
+    match (a, b) {
+        (Win {plies_to_mate: x}, Win {plies_to_mate: y}) => Win {plies_to_mate: x.min(y)},
+        (Win {..}, _) => a,
+        (_, Win {..}) => b,
+        (Draw, _) => a,
+        (_, Draw) => b,
+        (Loss {plies_to_mate: x}, Loss {plies_to_mate: y}) => Loss {plies_to_mate: x.max(y)}
+    }
+}
+
+#[test]
+fn kings_alone_only_win_when_adjacent() {
+    let mut tablebase = Tablebase::new(RuleSet::default());
+    tablebase.generate(&Material::from_name("KvK").unwrap());
+    for white in 0..64 {
+        for black in 0..64 {
+            if white == black {
+                continue;
+            }
+            let (white, black) = (square_at(white), square_at(black));
+            let board = board_with(&[(piece(King, White), white), (piece(King, Black), black)]);
+            let (wr, wf) = white.to_unsigned_coords();
+            let (br, bf) = black.to_unsigned_coords();
+            let adjacent = wr.abs_diff(br) <= 1 && wf.abs_diff(bf) <= 1;
+            let expected = if adjacent {TablebaseResult::Win {plies_to_mate: 1}} else {TablebaseResult::Draw};
+            for whose_turn in [White, Black] {
+                assert_eq!(tablebase.probe(&board, whose_turn), Some(expected), "{}", board_to_fen(&board, whose_turn));
+            }
+        }
+    }
+}
+
+#[test]
+fn withdrawer_next_to_the_king() {
+    //White withdraws d4-d3 and takes the king; black to move takes the withdrawer instead, leaving bare kings.
+    let (board, _) = position("8/8/8/3k4/3W4/8/8/K7 w");
+    assert_eq!(kwk().probe(&board, White), Some(TablebaseResult::Win {plies_to_mate: 1}));
+    assert_eq!(kwk().probe(&board, Black), Some(TablebaseResult::Draw));
+}
+
+#[test]
+fn generation_covers_the_smaller_tables() {
+    let mut names: Vec<String> = kwk().materials().map(Material::name).collect();
+    names.sort();
+    assert!(names.contains(&"KWvK".to_string()));
+    assert!(names.contains(&"KvK".to_string()));
+}
+
+#[test]
+fn tables_agree_with_their_children() {
+    let tablebase = kwk();
+    for (board, whose_turn) in random_boards(&[piece(King, White), piece(Withdrawer, White), piece(King, Black)], 28, 2000) {
+        let expected = value_from_children(tablebase, &board, whose_turn);
+        assert_eq!(tablebase.probe(&board, whose_turn), Some(expected), "{}", board_to_fen(&board, whose_turn));
+    }
+}
+
+#[test]
+fn reflected_positions_share_their_values() {
+    let tablebase = kwk();
+    for (board, whose_turn) in random_boards(&[piece(King, White), piece(Withdrawer, White), piece(King, Black)], 29, 500) {
+        let expected = tablebase.probe(&board, whose_turn);
+        for reflected in reflections(&board) {
+            assert_eq!(tablebase.probe(&reflected, whose_turn), expected, "{}", board_to_fen(&reflected, whose_turn));
+        }
+    }
+}
+
+#[test]
+fn materials_have_up_to_four_pieces() {
+    assert_eq!(Material::from_name("KWvKP").unwrap().name(), "KWvKP");
+    assert_eq!(Material::from_name("KPPvK").unwrap().pieces().len(), 4);
+    assert!(Material::from_name("KWPvKP").is_none());
+    let (board, _) = position("8/8/8/3k4/3W4/8/8/K7 w");
+    assert!(Material::from_board(&board).is_some());
+}
+
+//Four-piece tables take a few minutes without optimizations, so this only runs when asked for:
+//  cargo test --release --test tablebase -- --ignored
+#[test]
+#[ignore]
+fn four_piece_tables_agree_with_their_children() {
+    let mut tablebase = Tablebase::new(RuleSet::default());
+    tablebase.generate(&Material::from_name("KWvKP").unwrap());
+    let mut names: Vec<String> = tablebase.materials().map(Material::name).collect();
+    names.sort();
+    assert_eq!(names, ["KWvK", "KWvKP", "KvK", "KvKP"]);
+
+    //d4-d3 takes the king, whatever the pawn does.
+    let (board, _) = position("8/8/8/3k4/3W4/8/7p/K7 w");
+    assert_eq!(tablebase.probe(&board, White), Some(TablebaseResult::Win {plies_to_mate: 1}));
+
+    let pieces = [piece(King, White), piece(Withdrawer, White), piece(King, Black), piece(Pawn, Black)];
+    for (board, whose_turn) in random_boards(&pieces, 40, 2000) {
+        let expected = value_from_children(&tablebase, &board, whose_turn);
+        for reflected in reflections(&board) {
+            assert_eq!(tablebase.probe(&reflected, whose_turn), Some(expected), "{}", board_to_fen(&reflected, whose_turn));
+        }
+    }
+}
+
+#[test]
+fn tables_round_trip_with_their_rules() {
+    let rules = RuleSet {pawns_capture_diagonally: true, ..RuleSet::abbott()};
+    let mut tablebase = Tablebase::new(rules);
+    tablebase.generate(&Material::from_name("KvK").unwrap());
+    let dir = std::env::temp_dir().join(format!("ultima-tablebase-test-{}", std::process::id()));
+    tablebase.save_dir(&dir).unwrap();
+    let loaded = Tablebase::load_dir(&dir, rules).unwrap();
+    assert_eq!(loaded.materials().count(), 1);
+    let (board, _) = position("8/8/8/3k4/3K4/8/8/8 w");
+    assert_eq!(loaded.probe(&board, White), Some(TablebaseResult::Win {plies_to_mate: 1}));
+
+    let wrong = Tablebase::load_dir(&dir, RuleSet::default());
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(matches!(wrong, Err(TablebaseLoadError::WrongRules {..})));
+}
+
+#[test]
+fn tables_with_unknown_rules_are_rejected() {
+    let mut tablebase = Tablebase::new(RuleSet::default());
+    tablebase.generate(&Material::from_name("KvK").unwrap());
+    let dir = std::env::temp_dir().join(format!("ultima-tablebase-rules-test-{}", std::process::id()));
+    tablebase.save_dir(&dir).unwrap();
+    let path = dir.join("KvK.ultb");
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let table = Table::from_bytes(&bytes).unwrap();
+    assert!(*table.rules() == RuleSet::default());
+
+    //The rules id starts right after the magic, the version and its length byte.
+    let mut corrupted = bytes;
+    corrupted[9] = b'?';
+    assert!(matches!(Table::from_bytes(&corrupted), Err(TablebaseLoadError::BadRules(_))));
+}
+
+#[test]
+fn search_sees_tablebase_wins_past_its_horizon() {
+    let tablebase = kwk();
+    let white_king = square("a1");
+    let withdrawer = square("e4");
+    let (board, plies) = (0..64)
+        .map(square_at)
+        .filter(|&rf| rf != white_king && rf != withdrawer)
+        .map(|rf| board_with(&[(piece(King, White), white_king), (piece(Withdrawer, White), withdrawer), (piece(King, Black), rf)]))
+        .find_map(|board| match tablebase.probe(&board, White) {
+            Some(TablebaseResult::Win {plies_to_mate}) if plies_to_mate >= 5 => Some((board, plies_to_mate)),
+            _ => None
+        })
+        .expect("some black king square should lose slowly");
+
+    let backend = EvaluationBackend::HandWritten;
+    let mut searcher = Searcher::new(&backend);
+    assert!(searcher.search(&board, White, 1).score < MATE_THRESHOLD);
+
+    let mut searcher = Searcher::new(&backend);
+    searcher.set_tablebase(Some(tablebase));
+    let result = searcher.search(&board, White, 1);
+    assert_eq!(result.score, MATE_SCORE - plies as i32);
+    let mut after = board.clone();
+    execute_move(&mut after, result.best_move.unwrap(), White);
+    assert_eq!(tablebase.probe(&after, Black), Some(TablebaseResult::Loss {plies_to_mate: plies - 1}));
+}
+
+#[test]
+fn search_ignores_tablebases_for_other_rules() {
+    let (board, _) = position("8/8/8/3k4/8/3K4/8/8 w");
+    let backend = EvaluationBackend::HandWritten;
+    let plain = Searcher::new(&backend).search(&board, White, 2).nodes;
+
+    let mut matching = Tablebase::new(RuleSet::default());
+    matching.generate(&Material::from_name("KvK").unwrap());
+    let mut searcher = Searcher::new(&backend);
+    searcher.set_tablebase(Some(&matching));
+    assert!(searcher.search(&board, White, 2).nodes < plain);
+
+    let mut other = Tablebase::new(RuleSet::abbott());
+    other.generate(&Material::from_name("KvK").unwrap());
+    let mut searcher = Searcher::new(&backend);
+    searcher.set_tablebase(Some(&other));
+    assert_eq!(searcher.search(&board, White, 2).nodes, plain);
+}
+
+proptest! {
+    #[test]
+    fn packbits_round_trips(data in prop::collection::vec(prop_oneof![Just(0u8), any::<u8>()], 0..2000)) {
+        let compressed = packbits::compress(&data);
+        prop_assert_eq!(packbits::decompress(&compressed, data.len()), Some(data.clone()));
+        //Literal runs cost one header byte per 128.
+        prop_assert!(compressed.len() <= data.len() + data.len().div_ceil(128));
+    }
+
+    #[test]
+    fn packbits_rejects_the_wrong_length(data in prop::collection::vec(any::<u8>(), 1..500)) {
+        let compressed = packbits::compress(&data);
+        prop_assert_eq!(packbits::decompress(&compressed, data.len() - 1), None);
+        prop_assert_eq!(packbits::decompress(&compressed, data.len() + 1), None);
+        prop_assert_eq!(packbits::decompress(&compressed[..compressed.len() - 1], data.len()), None);
+    }
+}
@@ -0,0 +1,123 @@
+use game::*;
+use game::PlayerColor::*;
+use game::UltimaPieceType::*;
+
+mod common;
+use common::*;
+
+//Every square the side to move threatens, with the moves that threaten it.
+fn threats(fen: &str, rules: &RuleSet) -> Vec<(String, Vec<String>)> {
+    let (board, whose_turn) = position(fen);
+    board.threats_by(whose_turn, rules).threats.iter()
+        .map(|threat| (square_to_string(threat.square), threat.moves.iter().map(|mv| move_to_string(&board, mv)).collect()))
+        .collect()
+}
+
+fn expected(square: &str, moves: &[&str]) -> Vec<(String, Vec<String>)> {
+    vec![(square.to_string(), moves.iter().map(|mv| mv.to_string()).collect())]
+}
+
+#[test]
+fn pawns_capture_custodially() {
+    //The pawn lands on d4, pinning e4 against the pawn on f4.
+    assert_eq!(threats("7k/8/8/8/4pP2/8/3P4/7K w", &RuleSet::default()), expected("e4", &["d2-d4xe4"]));
+}
+
+#[test]
+fn withdrawers_capture_by_moving_away() {
+    assert_eq!(threats("7k/8/8/3p4/3W4/8/8/7K w", &RuleSet::default()), expected("d5", &["d4-d3xd5", "d4-d2xd5", "d4-d1xd5"]));
+}
+
+#[test]
+fn coordinators_capture_where_their_lines_cross_the_king() {
+    //From a4, the coordinator's rank crosses the king's file on c4.
+    assert_eq!(threats("7k/8/8/8/2p5/8/O7/2K5 w", &RuleSet::default()), expected("c4", &["a2-a4xc4"]));
+}
+
+#[test]
+fn longleapers_capture_by_jumping() {
+    assert_eq!(
+        threats("7k/8/8/8/8/3p4/8/3L3K w", &RuleSet::default()),
+        expected("d3", &["d1-d4xd3", "d1-d5xd3", "d1-d6xd3", "d1-d7xd3", "d1-d8xd3"])
+    );
+}
+
+#[test]
+fn kings_capture_by_displacement() {
+    assert_eq!(threats("7k/8/8/8/8/8/6p1/7K w", &RuleSet::default()), expected("g2", &["h1-g2xg2"]));
+}
+
+#[test]
+fn chameleons_only_capture_what_they_imitate() {
+    //The chameleon can withdraw from the withdrawer, but can't take the pawn beside it, which would need a custodian.
+    assert_eq!(threats("7k/8/8/3w4/3Cp3/8/8/7K w", &RuleSet::default()), expected("d5", &["d4-d3xd5", "d4-d2xd5", "d4-d1xd5"]));
+    //It can jump the longleaper, but not the pawn behind it.
+    assert_eq!(threats("7k/8/8/3p4/8/3l4/8/3C3K w", &RuleSet::default()), expected("d3", &["d1-d4xd3"]));
+}
+
+#[test]
+fn frozen_pieces_threaten_nothing() {
+    //The withdrawer on d4 sits next to the black immobilizer on c3.
+    assert!(threats("7k/8/8/3p4/3W4/2i5/8/7K w", &RuleSet::default()).is_empty());
+}
+
+#[test]
+fn threats_against_looks_from_the_other_side() {
+    let (board, _) = position("7k/8/8/3p4/3W4/8/8/7K w");
+    let map = board.threats_against(Black, &RuleSet::default());
+    assert_eq!(map.attacker, White);
+    assert!(map.is_threatened(square("d5")));
+    assert!(!map.is_threatened(square("d4")));
+    assert_eq!(map.threat_at(square("d5")).unwrap().piece, piece(Pawn, Black));
+}
+
+#[test]
+fn immobilization_is_explained() {
+    let (board, _) = position("7k/8/8/3p4/3W4/2i5/8/7K w");
+    let frozen = board.immobilization(square("d4"), &RuleSet::default()).expect("the withdrawer is frozen");
+    assert_eq!(frozen.immobilized_by.len(), 1);
+    assert_eq!(frozen.immobilized_by[0].rule, ImmobilizationRule::FreezesNeighbours);
+    assert_eq!(frozen.explanation(), "The withdrawer on d4 can't move: it's next to an enemy immobilizer (immobilizer on c3).");
+    assert!(board.immobilization(square("d5"), &RuleSet::default()).is_none());
+    assert!(board.immobilization(square("e5"), &RuleSet::default()).is_none());
+
+    let abbott = board.immobilization(square("d4"), &RuleSet::abbott()).unwrap();
+    assert!(abbott.can_commit_suicide);
+    assert!(abbott.explanation().ends_with(" It can still remove itself from the board."));
+}
+
+#[test]
+fn chameleons_freeze_immobilizers() {
+    //The white immobilizer on d4 is frozen both by the chameleon on d5 and the black immobilizer on e3.
+    let (board, _) = position("7k/8/8/3c4/3I4/4i3/8/7K w");
+    let frozen = board.immobilization(square("d4"), &RuleSet::default()).expect("the immobilizer is frozen");
+    let mut rules: Vec<ImmobilizationRule> = frozen.immobilized_by.iter().map(|by| by.rule).collect();
+    rules.sort_by_key(|rule| matches!(rule, ImmobilizationRule::FreezesNeighbours));
+    assert_eq!(rules, [ImmobilizationRule::FreezesOnly(Immobilizer), ImmobilizationRule::FreezesNeighbours]);
+    assert!(frozen.explanation().contains("an immobilizer next to an enemy chameleon (chameleon on d5)"));
+    assert!(frozen.explanation().contains(", and "));
+    let white_frozen: Vec<Rankfile> = board.immobilized_pieces(White, &RuleSet::default()).iter().map(|frozen| frozen.square).collect();
+    assert_eq!(white_frozen, [square("d4")]);
+}
+
+#[test]
+fn chameleons_can_withdraw_from_immobilizers() {
+    //The chameleon on d4 is frozen by the immobilizer on d5, with d3 to d1 free behind it.
+    let (board, _) = position("k7/8/8/3i4/3C4/8/8/7K w");
+    let withdrawing = RuleSet {chameleon_withdraws_from_immobilizer: true, ..RuleSet::default()};
+    let frozen = board.immobilization(square("d4"), &withdrawing).expect("the chameleon is frozen");
+    assert!(frozen.can_withdraw);
+    assert_eq!(
+        frozen.explanation(),
+        "The chameleon on d4 can only withdraw from the piece freezing it: it's next to an enemy immobilizer (immobilizer on d5)."
+    );
+    assert_eq!(threats("k7/8/8/3i4/3C4/8/8/7K w", &withdrawing), expected("d5", &["d4-d3xd5", "d4-d2xd5", "d4-d1xd5"]));
+
+    assert!(!board.immobilization(square("d4"), &RuleSet::default()).unwrap().can_withdraw);
+    //Blocked behind, it has nowhere to withdraw to.
+    let (blocked, _) = position("k7/8/8/3i4/3C4/3P4/8/7K w");
+    assert!(!blocked.immobilization(square("d4"), &withdrawing).unwrap().can_withdraw);
+    //Nor with a second piece freezing it.
+    let (two, _) = position("k7/8/8/3i4/3Ci3/8/8/7K w");
+    assert!(!two.immobilization(square("d4"), &withdrawing).unwrap().can_withdraw);
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use game::*;
+use game::PlayerColor::*;
+
+mod common;
+use common::*;
+
+fn clock(remaining_ms: u64, increment_ms: u64, moves_to_go: Option<u32>) -> TimeControl {
+    TimeControl {
+        remaining: Duration::from_millis(remaining_ms),
+        increment: Duration::from_millis(increment_ms),
+        moves_to_go
+    }
+}
+
+#[test]
+fn sudden_death_spreads_the_time_over_thirty_moves() {
+    //30ms is kept back for getting the move out.
+    let budget = TimeBudget::new(&clock(60_030, 0, None));
+    assert_eq!(budget.optimum, Duration::from_secs(2));
+    assert_eq!(budget.maximum, Duration::from_secs(10));
+}
+
+#[test]
+fn most_of_the_increment_is_spent() {
+    let budget = TimeBudget::new(&clock(60_030, 2000, None));
+    assert_eq!(budget.optimum, Duration::from_millis(3500));
+}
+
+#[test]
+fn moves_to_go_divides_the_time() {
+    let budget = TimeBudget::new(&clock(10_030, 0, Some(5)));
+    assert_eq!(budget.optimum, Duration::from_secs(2));
+    //Five times the optimum would be everything, so the maximum is held to 80%.
+    assert_eq!(budget.maximum, Duration::from_secs(8));
+
+    //The last move before the time control can use all of it.
+    let last = TimeBudget::new(&clock(10_030, 0, Some(1)));
+    assert_eq!(last.optimum, Duration::from_secs(10));
+    assert_eq!(last.maximum, Duration::from_secs(10));
+}
+
+#[test]
+fn the_budget_never_exceeds_the_clock() {
+    for (remaining, increment, moves_to_go) in [(1000, 5000, None), (100, 0, Some(1)), (10, 1000, None), (0, 0, Some(0))] {
+        let control = clock(remaining, increment, moves_to_go);
+        let budget = TimeBudget::new(&control);
+        let usable = control.remaining.saturating_sub(Duration::from_millis(30));
+        assert!(budget.optimum <= usable, "{control:?}");
+        assert!(budget.maximum <= usable, "{control:?}");
+        assert!(budget.optimum <= budget.maximum, "{control:?}");
+    }
+}
+
+#[test]
+fn instability_stretches_the_optimum_up_to_the_maximum() {
+    let budget = TimeBudget::new(&clock(60_030, 0, None));
+    assert_eq!(budget.optimum_with_instability(0.0), budget.optimum);
+    assert_eq!(budget.optimum_with_instability(1.5), Duration::from_secs(5));
+    assert_eq!(budget.optimum_with_instability(100.0), budget.maximum);
+}
+
+#[test]
+fn ponderhit_puts_the_search_on_the_clock() {
+    let rules = RuleSet::default();
+    let mut board = GameBoard::new_in_start_position();
+    let first = parse_move(&board, White, "b2-b5", &rules).unwrap();
+    execute_move(&mut board, first, White);
+    let reply = parse_move(&board, Black, "h7-h5", &rules).unwrap();
+    let handle = spawn_ponder(&board, Black, reply.clone(), rules, Arc::new(EvaluationBackend::HandWritten))
+        .expect("the reply doesn't take a king");
+
+    let mut expected = board.clone();
+    execute_move(&mut expected, reply, Black);
+    assert!(handle.is_hit(&expected, White));
+    assert!(!handle.is_hit(&board, Black));
+
+    //Without a clock the ponder search carries on until it's told otherwise.
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(!handle.is_finished());
+
+    let hit_at = Instant::now();
+    handle.ponderhit(&TimeControl::move_time(Duration::from_millis(300)));
+    let result = handle.join();
+    assert!(hit_at.elapsed() < Duration::from_secs(5));
+    let best_move = result.best_move.expect("the ponder search should have a move");
+    assert!(move_validation::find_legal_move(&expected, White, best_move.start, best_move.end, &rules).is_some());
+}
+
+#[test]
+fn a_missed_ponder_stops_when_told_to() {
+    let rules = RuleSet::default();
+    let board = GameBoard::new_in_start_position();
+    let reply = parse_move(&board, White, "b2-b5", &rules).unwrap();
+    let handle = spawn_ponder(&board, White, reply, rules, Arc::new(EvaluationBackend::HandWritten)).unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    let stopped_at = Instant::now();
+    handle.stop();
+    handle.join();
+    assert!(stopped_at.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn no_pondering_on_a_king_capture() {
+    let (board, whose_turn) = position("8/8/8/3k4/3K4/8/8/8 b");
+    let capture = parse_move(&board, whose_turn, "d5-d4", &RuleSet::default()).unwrap();
+    assert!(spawn_ponder(&board, whose_turn, capture, RuleSet::default(), Arc::new(EvaluationBackend::HandWritten)).is_none());
+}
+
+#[test]
+fn dropping_the_player_joins_its_ponder_search() {
+    let backend = Arc::new(EvaluationBackend::HandWritten);
+    let mut player = TimedEnginePlayer::new(backend.clone(), Duration::from_millis(100), true);
+    let board = GameBoard::new_in_start_position();
+    player.choose_move(&board, White, &RuleSet::default()).expect("there are moves to play");
+    drop(player);
+    //The ponder thread held the only other reference to the backend.
+    assert_eq!(Arc::strong_count(&backend), 1);
+}